@@ -0,0 +1,54 @@
+## Error codes
+
+validation_error = Die Anfrage ist ungültig
+authentication_error = Authentifizierung fehlgeschlagen
+authorization_error = Sie sind dazu nicht berechtigt
+not_found = Ressource nicht gefunden
+conflict = Die Ressource steht im Konflikt mit einer bestehenden
+precondition_failed = Die Ressource wurde seit dem Abruf geändert
+precondition_required = Die Anfrage muss bedingt sein, senden Sie If-Match
+failed_dependency = Der Vorgang hängt von einem fehlgeschlagenen Vorgang ab
+method_not_allowed = Die Methode wird für diese Ressource nicht unterstützt
+payload_too_large = Der Anfrageinhalt ist zu groß
+not_acceptable = Keiner der akzeptierten Antworttypen wird unterstützt
+unsupported_media_type = Der Typ des Anfrageinhalts wird nicht unterstützt
+database_error = Datenbankvorgang fehlgeschlagen
+internal_error = Interner Serverfehler
+
+## Messages
+
+message-email-already-exists = E-Mail-Adresse existiert bereits
+message-invalid-credentials = Ungültige Anmeldedaten
+message-current-password-is-incorrect = Das aktuelle Passwort ist falsch
+message-no-password-is-set-for-this-account = Für dieses Konto ist kein Passwort festgelegt
+message-missing-authorization-header = Authorization-Header fehlt
+message-invalid-token = Ungültiges Token
+message-invalid-or-expired-token = Ungültiges oder abgelaufenes Token
+message-token-has-been-revoked = Das Token wurde widerrufen
+message-user-no-longer-exists = Der Benutzer existiert nicht mehr
+message-admin-access-required = Administratorrechte erforderlich
+message-missing-or-invalid-csrf-token = CSRF-Token fehlt oder ist ungültig
+message-if-match-header-is-required = Der If-Match-Header ist erforderlich
+message-at-least-one-of-name-or-email-is-required = Name oder E-Mail-Adresse ist erforderlich
+
+## Validation rules
+
+validation-required = { $field } ist erforderlich
+validation-email = { $field } muss eine gültige E-Mail-Adresse sein
+validation-role = { $field } muss user, admin oder moderator sein
+validation-length-min-max = { $field } muss zwischen { $min } und { $max } Zeichen lang sein
+validation-length-min = { $field } muss mindestens { $min } Zeichen lang sein
+validation-length-max = { $field } darf höchstens { $max } Zeichen lang sein
+validation-length-equal = { $field } muss genau { $equal } Zeichen lang sein
+validation-range-min-max = { $field } muss zwischen { $min } und { $max } liegen
+validation-range-min = { $field } muss mindestens { $min } sein
+validation-range-max = { $field } darf höchstens { $max } sein
+validation-invalid = { $field } ist ungültig
+validation-syntax = Der Anfrageinhalt ist kein gültiges JSON
+validation-unknown_field = { $field } ist kein bekanntes Feld
+
+## Role descriptions
+
+role-user = Normaler Benutzer mit grundlegenden Berechtigungen
+role-admin = Administrator mit allen Berechtigungen
+role-moderator = Moderator mit erweiterten Berechtigungen
@@ -0,0 +1,56 @@
+# English is the language of the messages in the code. This catalog is the
+# fallback for keys missing from other catalogs.
+
+## Error codes; in other locales these replace error messages that have
+## no translation of their own, and the English message moves to `detail`
+
+validation_error = The request is invalid
+authentication_error = Authentication failed
+authorization_error = You are not allowed to do this
+not_found = Resource not found
+conflict = The resource conflicts with an existing one
+precondition_failed = The resource was modified since it was fetched
+precondition_required = The request must be conditional, send If-Match
+failed_dependency = The operation depends on one that failed
+method_not_allowed = The method is not supported for this resource
+payload_too_large = The request body is too large
+not_acceptable = None of the acceptable response types is supported
+unsupported_media_type = The request body type is not supported
+database_error = Database operation failed
+internal_error = Internal server error
+
+## Messages are translated under `message-` and the English message in
+## lower case with dashes between words, such as `message-invalid-credentials`.
+## English needs no entries.
+
+## Validation rules; $field is the path of the field and the other
+## variables are parameters of the rule
+
+validation-required = { $field } is required
+validation-email = { $field } must be a valid email address
+validation-role = { $field } must be one of user, admin or moderator
+validation-length-min-max = { $field } must be between { $min } and { $max } characters
+validation-length-min = { $field } must be at least { $min } { $min ->
+        [one] character
+       *[other] characters
+    }
+validation-length-max = { $field } must be at most { $max } { $max ->
+        [one] character
+       *[other] characters
+    }
+validation-length-equal = { $field } must be exactly { $equal } { $equal ->
+        [one] character
+       *[other] characters
+    }
+validation-range-min-max = { $field } must be between { $min } and { $max }
+validation-range-min = { $field } must be at least { $min }
+validation-range-max = { $field } must be at most { $max }
+validation-invalid = { $field } is invalid
+validation-syntax = The request body is not valid JSON
+validation-unknown_field = { $field } is not a known field
+
+## Role descriptions
+
+role-user = Regular user with basic permissions
+role-admin = Administrator with full permissions
+role-moderator = Moderator with elevated permissions
@@ -0,0 +1,63 @@
+## Error codes
+
+validation_error = La requête est invalide
+authentication_error = Échec de l'authentification
+authorization_error = Vous n'êtes pas autorisé à effectuer cette action
+not_found = Ressource introuvable
+conflict = La ressource entre en conflit avec une ressource existante
+precondition_failed = La ressource a été modifiée depuis sa récupération
+precondition_required = La requête doit être conditionnelle, envoyez If-Match
+failed_dependency = L'opération dépend d'une opération qui a échoué
+method_not_allowed = La méthode n'est pas prise en charge pour cette ressource
+payload_too_large = Le corps de la requête est trop volumineux
+not_acceptable = Aucun des types de réponse acceptés n'est pris en charge
+unsupported_media_type = Le type du corps de la requête n'est pas pris en charge
+database_error = Échec de l'opération sur la base de données
+internal_error = Erreur interne du serveur
+
+## Messages
+
+message-email-already-exists = L'adresse e-mail existe déjà
+message-invalid-credentials = Identifiants invalides
+message-current-password-is-incorrect = Le mot de passe actuel est incorrect
+message-no-password-is-set-for-this-account = Aucun mot de passe n'est défini pour ce compte
+message-missing-authorization-header = En-tête Authorization manquant
+message-invalid-token = Jeton invalide
+message-invalid-or-expired-token = Jeton invalide ou expiré
+message-token-has-been-revoked = Le jeton a été révoqué
+message-user-no-longer-exists = L'utilisateur n'existe plus
+message-admin-access-required = Accès administrateur requis
+message-missing-or-invalid-csrf-token = Jeton CSRF manquant ou invalide
+message-if-match-header-is-required = L'en-tête If-Match est requis
+message-at-least-one-of-name-or-email-is-required = Le nom ou l'adresse e-mail est requis
+
+## Validation rules
+
+validation-required = { $field } est obligatoire
+validation-email = { $field } doit être une adresse e-mail valide
+validation-role = { $field } doit être user, admin ou moderator
+validation-length-min-max = { $field } doit contenir entre { $min } et { $max } caractères
+validation-length-min = { $field } doit contenir au moins { $min } { $min ->
+        [one] caractère
+       *[other] caractères
+    }
+validation-length-max = { $field } doit contenir au plus { $max } { $max ->
+        [one] caractère
+       *[other] caractères
+    }
+validation-length-equal = { $field } doit contenir exactement { $equal } { $equal ->
+        [one] caractère
+       *[other] caractères
+    }
+validation-range-min-max = { $field } doit être compris entre { $min } et { $max }
+validation-range-min = { $field } doit être au moins { $min }
+validation-range-max = { $field } doit être au plus { $max }
+validation-invalid = { $field } est invalide
+validation-syntax = Le corps de la requête n'est pas un JSON valide
+validation-unknown_field = { $field } n'est pas un champ connu
+
+## Role descriptions
+
+role-user = Utilisateur standard avec des permissions de base
+role-admin = Administrateur avec toutes les permissions
+role-moderator = Modérateur avec des permissions étendues
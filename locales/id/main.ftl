@@ -0,0 +1,54 @@
+## Error codes
+
+validation_error = Permintaan tidak valid
+authentication_error = Autentikasi gagal
+authorization_error = Anda tidak diizinkan melakukan tindakan ini
+not_found = Sumber daya tidak ditemukan
+conflict = Sumber daya bertentangan dengan yang sudah ada
+precondition_failed = Sumber daya telah diubah sejak diambil
+precondition_required = Permintaan harus bersyarat, kirim If-Match
+failed_dependency = Operasi bergantung pada operasi lain yang gagal
+method_not_allowed = Metode tidak didukung untuk sumber daya ini
+payload_too_large = Isi permintaan terlalu besar
+not_acceptable = Tidak ada jenis respons yang dapat diterima yang didukung
+unsupported_media_type = Jenis isi permintaan tidak didukung
+database_error = Operasi basis data gagal
+internal_error = Terjadi kesalahan pada server
+
+## Messages
+
+message-email-already-exists = Email sudah terdaftar
+message-invalid-credentials = Kredensial tidak valid
+message-current-password-is-incorrect = Kata sandi saat ini salah
+message-no-password-is-set-for-this-account = Akun ini tidak memiliki kata sandi
+message-missing-authorization-header = Header Authorization tidak ada
+message-invalid-token = Token tidak valid
+message-invalid-or-expired-token = Token tidak valid atau kedaluwarsa
+message-token-has-been-revoked = Token telah dicabut
+message-user-no-longer-exists = Pengguna sudah tidak ada
+message-admin-access-required = Diperlukan akses admin
+message-missing-or-invalid-csrf-token = Token CSRF tidak ada atau tidak valid
+message-if-match-header-is-required = Header If-Match wajib diisi
+message-at-least-one-of-name-or-email-is-required = Nama atau email wajib diisi
+
+## Validation rules
+
+validation-required = { $field } wajib diisi
+validation-email = { $field } harus berupa alamat email yang valid
+validation-role = { $field } harus salah satu dari user, admin atau moderator
+validation-length-min-max = { $field } harus terdiri dari { $min } sampai { $max } karakter
+validation-length-min = { $field } minimal { $min } karakter
+validation-length-max = { $field } maksimal { $max } karakter
+validation-length-equal = { $field } harus tepat { $equal } karakter
+validation-range-min-max = { $field } harus antara { $min } dan { $max }
+validation-range-min = { $field } minimal { $min }
+validation-range-max = { $field } maksimal { $max }
+validation-invalid = { $field } tidak valid
+validation-syntax = Isi permintaan bukan JSON yang valid
+validation-unknown_field = { $field } bukan kolom yang dikenal
+
+## Role descriptions
+
+role-user = Pengguna biasa dengan izin dasar
+role-admin = Administrator dengan izin penuh
+role-moderator = Moderator dengan izin yang lebih tinggi
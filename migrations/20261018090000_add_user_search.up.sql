@@ -0,0 +1,15 @@
+-- Full-text and fuzzy search over user names and emails
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- Names rank above emails; email separators are split into words
+ALTER TABLE public.users
+ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('simple', coalesce(name, '')), 'A') ||
+    setweight(to_tsvector('simple', translate(coalesce(email, ''), '@._-+', '     ')), 'B')
+) STORED;
+
+CREATE INDEX idx_users_search_vector ON public.users USING GIN (search_vector);
+
+-- Trigram indexes for typo tolerant matching
+CREATE INDEX idx_users_name_trgm ON public.users USING GIN (name gin_trgm_ops);
+CREATE INDEX idx_users_email_trgm ON public.users USING GIN (email gin_trgm_ops);
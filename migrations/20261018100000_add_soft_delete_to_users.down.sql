@@ -0,0 +1,8 @@
+-- Soft deleted users are removed so emails are unique again
+DELETE FROM public.users WHERE deleted_at IS NOT NULL;
+
+DROP INDEX IF EXISTS idx_users_deleted_at;
+DROP INDEX IF EXISTS public.users_email_key;
+CREATE UNIQUE INDEX users_email_key ON public.users (email);
+
+ALTER TABLE public.users DROP COLUMN IF EXISTS deleted_at;
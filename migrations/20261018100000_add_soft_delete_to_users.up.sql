@@ -0,0 +1,10 @@
+-- Soft delete: deleted users keep their row until purged
+ALTER TABLE public.users
+ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
+
+-- Emails only need to be unique among users that are not deleted
+DROP INDEX IF EXISTS public.users_email_key;
+CREATE UNIQUE INDEX users_email_key ON public.users (email) WHERE deleted_at IS NULL;
+
+-- Find users due for purging
+CREATE INDEX idx_users_deleted_at ON public.users (deleted_at) WHERE deleted_at IS NOT NULL;
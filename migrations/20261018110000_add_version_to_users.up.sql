@@ -0,0 +1,3 @@
+-- Row version for optimistic concurrency control, incremented on every update
+ALTER TABLE public.users
+ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
@@ -0,0 +1,3 @@
+-- Emails stay in lower case
+DROP INDEX IF EXISTS public.users_email_key;
+CREATE UNIQUE INDEX users_email_key ON public.users (email) WHERE deleted_at IS NULL;
@@ -0,0 +1,6 @@
+-- Emails are compared in lower case. Fails if two users that are not
+-- deleted have emails differing only in case; merge them first.
+UPDATE public.users SET email = lower(email) WHERE email <> lower(email);
+
+DROP INDEX IF EXISTS public.users_email_key;
+CREATE UNIQUE INDEX users_email_key ON public.users (lower(email)) WHERE deleted_at IS NULL;
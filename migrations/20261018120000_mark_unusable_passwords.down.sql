@@ -0,0 +1 @@
+UPDATE public.users SET password_hash = '' WHERE password_hash = '!';
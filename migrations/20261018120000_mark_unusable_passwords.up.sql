@@ -0,0 +1,3 @@
+-- Users created without a password had an empty hash, which bcrypt rejects
+-- as malformed; mark them explicitly instead
+UPDATE public.users SET password_hash = '!' WHERE password_hash = '';
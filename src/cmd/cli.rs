@@ -1,16 +1,318 @@
 use clap::Args;
+use futures::StreamExt;
+use sqlx::PgPool;
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
 
-#[derive(Debug, Args)]
+use crate::{
+    config::Config,
+    core::{
+        domain::users::{
+            export::{export_users, ExportColumn, ExportFormat},
+            import::{import_users, ColumnMap, ImportFormat, ImportOptions},
+            query::UserQuery,
+        },
+        rest::{openapi::openapi, version::ApiVersion},
+    },
+    pkg::openapi_diff::{self, Severity},
+};
+
+#[derive(Debug, Default, Args)]
 pub struct CliArgs {
     /// Name of the task to perform
     #[arg(short, long)]
     pub task: String,
+
+    #[command(flatten)]
+    pub users: UserFileArgs,
+
+    #[command(flatten)]
+    pub openapi: OpenApiArgs,
+}
+
+/// Options of the `import-users` and `export-users` tasks
+#[derive(Debug, Default, Args)]
+pub struct UserFileArgs {
+    /// CSV or JSONL file to import users from or export them to
+    #[arg(long)]
+    pub file: Option<PathBuf>,
+    /// `csv`, `jsonl` or `ndjson`; defaults to the file extension
+    #[arg(long)]
+    pub format: Option<String>,
+    /// Validate and report without inserting
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Insert the valid rows even if other rows are invalid
+    #[arg(long)]
+    pub skip_invalid: bool,
+    /// Rows inserted per transaction
+    #[arg(long)]
+    pub batch_size: Option<usize>,
+    /// Import: column mapping as `source:field` pairs, e.g. `mail:email`.
+    /// Export: comma separated columns, e.g. `id,name,email`
+    #[arg(long)]
+    pub columns: Option<String>,
+    /// Export only users matching this filter, as for `GET /users`
+    #[arg(long)]
+    pub filter: Option<String>,
+    /// Export sort order, as for `GET /users`
+    #[arg(long)]
+    pub sort: Option<String>,
+    /// Also export soft deleted users
+    #[arg(long)]
+    pub include_deleted: bool,
+}
+
+/// Options of the `export-openapi` and `diff-openapi` tasks
+#[derive(Debug, Default, Args)]
+pub struct OpenApiArgs {
+    /// OpenAPI document to export to, or to compare with `--base` instead
+    /// of the current API
+    #[arg(long)]
+    pub spec: Option<PathBuf>,
+    /// `json` or `yaml`; defaults to the `--spec` file extension
+    #[arg(long)]
+    pub spec_format: Option<String>,
+    /// Previously published OpenAPI document to compare with
+    #[arg(long)]
+    pub base: Option<PathBuf>,
+    /// API version to document, such as `2`. Defaults to the latest one, or
+    /// for `diff-openapi` to the version of the `--base` document
+    #[arg(long)]
+    pub api_version: Option<String>,
+}
+
+/// Serialization of an exported OpenAPI document
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SpecFormat {
+    Json,
+    Yaml,
+}
+
+impl SpecFormat {
+    /// The format named by `--spec-format`, or else by the extension of `file`
+    fn select(format: Option<&str>, file: &Path) -> Result<Self, io::Error> {
+        let format = match format {
+            Some(format) => format,
+            None => file
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or_default(),
+        };
+        match format.to_ascii_lowercase().as_str() {
+            "json" => Ok(SpecFormat::Json),
+            "yaml" | "yml" => Ok(SpecFormat::Yaml),
+            other => Err(invalid_input(format!(
+                "Unsupported OpenAPI format '{}'; use json or yaml",
+                other
+            ))),
+        }
+    }
 }
 
 pub fn run(cli_args: CliArgs) -> Result<(), std::io::Error> {
     println!("Running CLI task: {}", cli_args.task);
-    // Add CLI-specific logic here
 
-    // Return Ok to signify success
+    match cli_args.task.as_str() {
+        "import-users" => actix_web::rt::System::new()
+            .block_on(run_import_users(cli_args.users))
+            .inspect_err(|e| eprintln!("import-users failed: {}", e)),
+        "export-users" => actix_web::rt::System::new()
+            .block_on(run_export_users(cli_args.users))
+            .inspect_err(|e| eprintln!("export-users failed: {}", e)),
+        "export-openapi" => run_export_openapi(cli_args.openapi)
+            .inspect_err(|e| eprintln!("export-openapi failed: {}", e)),
+        "diff-openapi" => run_diff_openapi(cli_args.openapi)
+            .inspect_err(|e| eprintln!("diff-openapi failed: {}", e)),
+        // Add CLI-specific logic here
+        _ => Ok(()),
+    }
+}
+
+async fn run_import_users(args: UserFileArgs) -> Result<(), std::io::Error> {
+    let file = args
+        .file
+        .ok_or_else(|| invalid_input("--file is required for import-users"))?;
+    let format = match args.format.as_deref() {
+        Some(format) => format,
+        None => file
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default(),
+    }
+    .parse::<ImportFormat>()
+    .map_err(invalid_input)?;
+    let options = ImportOptions {
+        columns: ColumnMap::parse(args.columns.as_deref().unwrap_or_default())
+            .map_err(invalid_input)?,
+        dry_run: args.dry_run,
+        skip_invalid: args.skip_invalid,
+        batch_size: args.batch_size.unwrap_or_default(),
+    };
+    let data = std::fs::read(&file)?;
+
+    let pool = connect().await?;
+    let report = import_users(&pool, &data, format, &options, |inserted, total| {
+        println!("Imported {}/{} users", inserted, total);
+    })
+    .await
+    .map_err(io::Error::other)?;
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report).map_err(io::Error::other)?
+    );
+    if report.invalid > 0 && !options.dry_run && !options.skip_invalid {
+        return Err(invalid_input(format!(
+            "{} invalid rows; nothing was imported",
+            report.invalid
+        )));
+    }
+    if report.failed > 0 {
+        return Err(io::Error::other(format!(
+            "{} rows were not imported because their batch failed",
+            report.failed
+        )));
+    }
+    Ok(())
+}
+
+async fn run_export_users(args: UserFileArgs) -> Result<(), std::io::Error> {
+    let file = args
+        .file
+        .ok_or_else(|| invalid_input("--file is required for export-users"))?;
+    let format = match args.format.as_deref() {
+        Some(format) => format,
+        None => file
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default(),
+    }
+    .parse::<ExportFormat>()
+    .map_err(invalid_input)?;
+    let columns = ExportColumn::parse_list(args.columns.as_deref().unwrap_or_default())
+        .map_err(invalid_input)?;
+    let mut query =
+        UserQuery::parse(args.filter.as_deref(), args.sort.as_deref()).map_err(invalid_input)?;
+    query.include_deleted = args.include_deleted;
+
+    let pool = connect().await?;
+    let mut output = io::BufWriter::new(std::fs::File::create(&file)?);
+    let mut chunks = Box::pin(export_users(pool, query, format, columns));
+    let mut rows = 0usize;
+    while let Some(chunk) = chunks.next().await {
+        output.write_all(&chunk.map_err(io::Error::other)?)?;
+        rows += 1;
+    }
+    output.flush()?;
+
+    // The CSV header is a chunk too
+    if format == ExportFormat::Csv {
+        rows = rows.saturating_sub(1);
+    }
+    println!("Exported {} users to {}", rows, file.display());
     Ok(())
 }
+
+fn run_export_openapi(args: OpenApiArgs) -> Result<(), std::io::Error> {
+    let file = args
+        .spec
+        .ok_or_else(|| invalid_input("--spec is required for export-openapi"))?;
+    let format = SpecFormat::select(args.spec_format.as_deref(), &file)?;
+    let version = match args.api_version.as_deref() {
+        Some(version) => parse_version(version)?,
+        None => ApiVersion::LATEST,
+    };
+
+    let doc = openapi(version);
+    let output = match format {
+        SpecFormat::Json => doc.to_pretty_json().map_err(io::Error::other)?,
+        SpecFormat::Yaml => serde_yaml::to_string(&doc).map_err(io::Error::other)?,
+    };
+    std::fs::write(&file, output)?;
+
+    println!(
+        "Exported the {} OpenAPI document to {}",
+        version,
+        file.display()
+    );
+    Ok(())
+}
+
+fn run_diff_openapi(args: OpenApiArgs) -> Result<(), std::io::Error> {
+    let base_file = args
+        .base
+        .ok_or_else(|| invalid_input("--base is required for diff-openapi"))?;
+    let base = read_spec(&base_file)?;
+
+    let (current, current_name) = match &args.spec {
+        Some(file) => (read_spec(file)?, file.display().to_string()),
+        None => {
+            let version = match args.api_version.as_deref() {
+                Some(version) => parse_version(version)?,
+                // A published document's major version is its API version
+                None => base
+                    .pointer("/info/version")
+                    .and_then(|v| v.as_str())
+                    .and_then(|v| ApiVersion::parse(v.split('.').next().unwrap_or_default()))
+                    .unwrap_or(ApiVersion::LATEST),
+            };
+            let doc = serde_json::to_value(openapi(version)).map_err(io::Error::other)?;
+            (doc, format!("the current {} API", version))
+        }
+    };
+
+    println!("Comparing {} with {}", base_file.display(), current_name);
+    let changes = openapi_diff::diff(&base, &current);
+    for change in &changes {
+        let label = match change.severity {
+            Severity::Breaking => "BREAKING",
+            Severity::NonBreaking => "non-breaking",
+        };
+        println!("  {:<13}{}", label, change);
+    }
+    let breaking = changes.iter().filter(|change| change.is_breaking()).count();
+    println!(
+        "{} breaking and {} non-breaking changes",
+        breaking,
+        changes.len() - breaking
+    );
+
+    if breaking > 0 {
+        return Err(io::Error::other(format!("{} breaking changes", breaking)));
+    }
+    Ok(())
+}
+
+/// An OpenAPI document from a JSON file, or a YAML one for other extensions
+fn read_spec(file: &Path) -> Result<serde_json::Value, std::io::Error> {
+    let data = std::fs::read(file)?;
+    let is_json = file
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+    if is_json {
+        return serde_json::from_slice(&data).map_err(invalid_input);
+    }
+    // Through a YAML value, whose keys such as response codes need not be
+    // strings
+    let doc: serde_yaml::Value = serde_yaml::from_slice(&data).map_err(invalid_input)?;
+    serde_json::to_value(doc).map_err(invalid_input)
+}
+
+fn parse_version(version: &str) -> Result<ApiVersion, std::io::Error> {
+    ApiVersion::parse(version)
+        .ok_or_else(|| invalid_input(format!("Unknown API version '{}'", version)))
+}
+
+async fn connect() -> Result<PgPool, std::io::Error> {
+    let config = Config::from_env();
+    PgPool::connect(&config.database_url)
+        .await
+        .map_err(io::Error::other)
+}
+
+fn invalid_input(error: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, error.to_string())
+}
@@ -16,13 +16,13 @@ enum Commands {
     /// Run the server
     Server,
     /// CLI-based functionality
-    Cli(cli::CliArgs),
+    Cli(Box<cli::CliArgs>),
 }
 
 pub fn run() -> Result<(), std::io::Error> {
     let args = Args::parse();
     match args.command {
         Commands::Server => server::run_rest(), // Ensure this returns a Result
-        Commands::Cli(cli_args) => cli::run(cli_args), // Ensure this returns a Result
+        Commands::Cli(cli_args) => cli::run(*cli_args), // Ensure this returns a Result
     }
 }
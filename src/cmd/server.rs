@@ -1,9 +1,22 @@
-use crate::core::rest::router;
+use crate::core::rest::{handler::extract, openapi, router, version::ApiVersion};
 use crate::core::{
-    domain::auth::jwt::JwtService,
-    rest::middleware::{error_handler::ErrorHandler, http_logger::HttpLogger},
+    domain::{
+        auth::{jwt::JwtService, revocation::TokenRevocationList},
+        users::purge::spawn_user_purge,
+    },
+    rest::middleware::{
+        client_cert::ClientCertAuth,
+        content_negotiation::ContentNegotiation,
+        error_handler::ErrorHandler,
+        http_logger::HttpLogger,
+        request_id::RequestId,
+        request_signing::{NonceCache, RequestSigning},
+    },
+};
+use crate::{
+    config::Config,
+    pkg::{i18n, logger::setup_logger, tls},
 };
-use crate::{config::Config, pkg::logger::setup_logger};
 use actix_web::{web, App, HttpServer};
 use sqlx::PgPool;
 
@@ -16,39 +29,98 @@ pub async fn run_rest() -> std::io::Result<()> {
         .await
         .expect("Database connection failed");
 
+    // Permanently remove users deleted longer ago than the retention period
+    spawn_user_purge(pool.clone(), config.user_purge.clone());
+
     // Setup JWT service
     let jwt_service = JwtService::new(&config.jwt_secret);
+    let revocations = TokenRevocationList::new();
+    let introspection_config = config.introspection.clone();
+    let scim_config = config.scim.clone();
+    let session_config = config.session.clone();
+    let concurrency_config = config.concurrency.clone();
+    let signing_config = config.signing.clone();
+    let body_limits = config.body_limits.clone();
+    let locale_config = config.locale.clone();
+    let api_version_config = config.api_version.clone();
+    let nonces = NonceCache::new();
+    let client_services = config.tls.client_services.clone();
+    let tls_config = if config.tls.enabled() {
+        Some(tls::load_server_config(&config.tls)?)
+    } else {
+        None
+    };
+    let scheme = if tls_config.is_some() {
+        "https"
+    } else {
+        "http"
+    };
 
     setup_logger(&config);
 
-    log::info!("Starting server at http://{}", config.rest_url);
+    log::info!("Starting server at {}://{}", scheme, config.rest_url);
+    if config.tls.client_ca_path.is_some() {
+        log::info!(
+            "Client certificates {}",
+            if config.tls.client_cert_required {
+                "required"
+            } else {
+                "accepted"
+            }
+        );
+    }
+    if i18n::parse(&config.locale.default_locale).is_none() {
+        log::warn!(
+            "DEFAULT_LOCALE {} has no catalog, falling back to {}",
+            config.locale.default_locale,
+            i18n::SOURCE_LOCALE
+        );
+    }
     log::info!(
         "JWT expiration set to {} hours",
         config.jwt_expiration_hours
     );
     log::info!(
-        "📚 API Documentation: http://{}/swagger-ui/",
+        "📚 API Documentation: {}://{}/swagger-ui/",
+        scheme,
         config.rest_url
     );
-    log::info!("🔗 Available endpoints:");
-    log::info!("  • GET  /users - List all users");
-    log::info!("  • POST /users - Create new user");
-    log::info!("  • POST /auth/register - Register new user");
-    log::info!("  • POST /auth/login - User login");
-    log::info!("  • GET  /auth/me - Get current user");
-    log::info!("  • PUT  /auth/password - Change password");
-    log::info!("  • POST /auth/admin/users - Admin create user");
-
-    HttpServer::new(move || {
+    log::info!(
+        "🔗 Available endpoints ({}; unversioned paths serve {} unless Api-Version is set):",
+        ApiVersion::LATEST,
+        ApiVersion::DEFAULT
+    );
+    for (method, path, summary) in openapi::endpoints(&openapi::openapi(ApiVersion::LATEST)) {
+        log::info!("  • {:<6} {} - {}", method, path, summary);
+    }
+
+    let server = HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(pool.clone())) // Share the database pool
             .app_data(web::Data::new(jwt_service.clone())) // Share the JWT service
+            .app_data(web::Data::new(revocations.clone())) // Share the revoked token list
+            .app_data(web::Data::new(introspection_config.clone())) // Introspection client credentials
+            .app_data(web::Data::new(scim_config.clone())) // SCIM bearer token
+            .app_data(web::Data::new(session_config.clone())) // Cookie session settings
+            .app_data(web::Data::new(concurrency_config.clone())) // If-Match requirement
+            .app_data(web::Data::new(locale_config.clone())) // Default language of error messages
+            .app_data(web::Data::new(api_version_config.clone())) // Sunset dates of deprecated routes
+            .wrap(ClientCertAuth::new(client_services.clone())) // Mutual TLS client certificates
+            .wrap(RequestSigning::new(signing_config.clone(), nonces.clone())) // HMAC signed service calls
             .wrap(ErrorHandler) // Error handling middleware
+            .wrap(ContentNegotiation) // JSON, MessagePack or CBOR responses per Accept
             .wrap(HttpLogger) // HTTP logging middleware
+            .wrap(RequestId) // X-Request-Id on responses, errors and logs
+            .configure(extract::configure(body_limits.clone())) // Extractor errors, body limits, 404 and 405
             .configure(router::config) // Configure routes
     })
-    .workers(2) // Set the number of workers
-    .bind(&config.rest_url)?
+    .on_connect(tls::on_connect) // Expose client certificates to requests
+    .workers(2); // Set the number of workers
+
+    match tls_config {
+        Some(tls_config) => server.bind_rustls_0_23(&config.rest_url, tls_config)?,
+        None => server.bind(&config.rest_url)?,
+    }
     .run()
     .await
 }
@@ -1,6 +1,10 @@
+use chrono::NaiveDate;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::env;
 
+use crate::core::{domain::users::model::UserRole, rest::version::ApiVersion};
+
 #[derive(Debug, Deserialize)]
 pub struct Config {
     pub database_url: String,
@@ -9,6 +13,184 @@ pub struct Config {
     pub log_name: String,
     pub jwt_secret: String,
     pub jwt_expiration_hours: i64,
+    pub introspection: IntrospectionConfig,
+    pub scim: ScimConfig,
+    pub session: SessionConfig,
+    pub signing: SigningConfig,
+    pub tls: TlsConfig,
+    pub user_purge: UserPurgeConfig,
+    pub concurrency: ConcurrencyConfig,
+    pub body_limits: BodyLimitConfig,
+    pub locale: LocaleConfig,
+    pub api_version: ApiVersionConfig,
+}
+
+/// Credentials accepted by the token introspection endpoint
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct IntrospectionConfig {
+    /// Static API keys sent in the `X-API-Key` header
+    pub api_keys: Vec<String>,
+    /// Client ID to client secret, sent with HTTP Basic authentication
+    pub clients: HashMap<String, String>,
+}
+
+/// Settings for the SCIM 2.0 provisioning API
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ScimConfig {
+    /// Bearer token the provisioning client must present; SCIM is disabled when unset
+    pub bearer_token: Option<String>,
+}
+
+/// Cookie based browser sessions
+#[derive(Debug, Clone, Deserialize)]
+pub struct SessionConfig {
+    /// Whether `/auth/login` sets a session cookie
+    pub enabled: bool,
+    /// Name of the HttpOnly cookie holding the session token
+    pub cookie_name: String,
+    /// Name of the cookie holding the CSRF token, readable by scripts
+    pub csrf_cookie_name: String,
+    /// Header that must echo the CSRF cookie on state-changing requests
+    pub csrf_header_name: String,
+    pub domain: Option<String>,
+    pub path: String,
+    pub secure: bool,
+    /// `Strict`, `Lax` or `None`
+    pub same_site: String,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cookie_name: "session".to_string(),
+            csrf_cookie_name: "csrf_token".to_string(),
+            csrf_header_name: "X-CSRF-Token".to_string(),
+            domain: None,
+            path: "/".to_string(),
+            secure: true,
+            same_site: "Lax".to_string(),
+        }
+    }
+}
+
+/// HMAC request signing for internal service callers
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SigningConfig {
+    /// Signing keys by key ID
+    pub keys: HashMap<String, SigningKey>,
+    /// Maximum clock skew accepted for a signature timestamp; nonces are
+    /// remembered for the same duration
+    pub max_skew_seconds: i64,
+    /// Headers every signature must cover, in addition to method, path and body
+    pub required_headers: Vec<String>,
+}
+
+/// A shared signing key and the service principal it authenticates
+#[derive(Debug, Clone, Deserialize)]
+pub struct SigningKey {
+    pub secret: String,
+    pub service: String,
+    pub role: UserRole,
+}
+
+/// TLS termination and client certificate authentication
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TlsConfig {
+    /// PEM certificate chain; the server speaks plain HTTP when unset
+    pub cert_path: Option<String>,
+    /// PEM private key matching `cert_path`
+    pub key_path: Option<String>,
+    /// PEM bundle of CAs trusted to issue client certificates; client
+    /// certificates are not requested when unset
+    pub client_ca_path: Option<String>,
+    /// Reject TLS handshakes without a client certificate
+    pub client_cert_required: bool,
+    /// Roles of service principals by certificate DNS name or common name
+    pub client_services: HashMap<String, UserRole>,
+}
+
+/// Optimistic concurrency control on user updates
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ConcurrencyConfig {
+    /// Reject updates and deletes without an `If-Match` header
+    pub if_match_required: bool,
+}
+
+/// Largest request bodies accepted; larger ones are answered with 413
+#[derive(Debug, Clone, Deserialize)]
+pub struct BodyLimitConfig {
+    /// JSON, MessagePack and CBOR request bodies
+    pub json_bytes: usize,
+    /// Files uploaded to `POST /users/import`
+    pub upload_bytes: usize,
+}
+
+impl Default for BodyLimitConfig {
+    fn default() -> Self {
+        Self {
+            json_bytes: 2 * 1024 * 1024,
+            upload_bytes: 10 * 1024 * 1024,
+        }
+    }
+}
+
+/// Language of error messages
+#[derive(Debug, Clone, Deserialize)]
+pub struct LocaleConfig {
+    /// Locale used when `Accept-Language` matches no catalog, such as `en`
+    /// or `id`
+    pub default_locale: String,
+}
+
+impl Default for LocaleConfig {
+    fn default() -> Self {
+        Self {
+            default_locale: "en".to_string(),
+        }
+    }
+}
+
+/// Lifecycle of API versions
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ApiVersionConfig {
+    /// Date after which v1 routes replaced in v2 may be removed, announced
+    /// in their `Sunset` header
+    pub v1_sunset: Option<NaiveDate>,
+}
+
+impl ApiVersionConfig {
+    /// The announced sunset of `version`, if any
+    pub fn sunset(&self, version: ApiVersion) -> Option<NaiveDate> {
+        match version {
+            ApiVersion::V1 => self.v1_sunset,
+            ApiVersion::V2 => None,
+        }
+    }
+}
+
+/// Permanent removal of soft deleted users
+#[derive(Debug, Clone, Deserialize)]
+pub struct UserPurgeConfig {
+    /// Days a deleted user can still be restored before it is purged
+    pub retention_days: i64,
+    /// Seconds between purge runs; `0` disables the background purge
+    pub interval_seconds: u64,
+}
+
+impl Default for UserPurgeConfig {
+    fn default() -> Self {
+        Self {
+            retention_days: 30,
+            interval_seconds: 3600,
+        }
+    }
+}
+
+impl TlsConfig {
+    pub fn enabled(&self) -> bool {
+        self.cert_path.is_some() && self.key_path.is_some()
+    }
 }
 
 impl Config {
@@ -25,6 +207,140 @@ impl Config {
                 .unwrap_or_else(|_| "24".to_string())
                 .parse()
                 .expect("JWT_EXPIRATION_HOURS must be a valid number"),
+            introspection: IntrospectionConfig {
+                api_keys: env_list("INTROSPECTION_API_KEYS"),
+                clients: env_list("INTROSPECTION_CLIENTS")
+                    .iter()
+                    .map(|pair| {
+                        let (id, secret) = pair
+                            .split_once(':')
+                            .expect("INTROSPECTION_CLIENTS entries must be client_id:secret");
+                        (id.to_string(), secret.to_string())
+                    })
+                    .collect(),
+            },
+            scim: ScimConfig {
+                bearer_token: env::var("SCIM_BEARER_TOKEN").ok().filter(|t| !t.is_empty()),
+            },
+            session: SessionConfig {
+                enabled: env_flag("SESSION_COOKIE_ENABLED", false),
+                cookie_name: env::var("SESSION_COOKIE_NAME")
+                    .unwrap_or_else(|_| "session".to_string()),
+                csrf_cookie_name: env::var("CSRF_COOKIE_NAME")
+                    .unwrap_or_else(|_| "csrf_token".to_string()),
+                csrf_header_name: env::var("CSRF_HEADER_NAME")
+                    .unwrap_or_else(|_| "X-CSRF-Token".to_string()),
+                domain: env::var("SESSION_COOKIE_DOMAIN")
+                    .ok()
+                    .filter(|d| !d.is_empty()),
+                path: env::var("SESSION_COOKIE_PATH").unwrap_or_else(|_| "/".to_string()),
+                secure: env_flag("SESSION_COOKIE_SECURE", true),
+                same_site: env::var("SESSION_COOKIE_SAMESITE")
+                    .unwrap_or_else(|_| "Lax".to_string()),
+            },
+            signing: SigningConfig {
+                keys: env_list("REQUEST_SIGNING_KEYS")
+                    .iter()
+                    .map(|entry| {
+                        let parts: Vec<&str> = entry.splitn(4, ':').collect();
+                        let [key_id, service, role, secret] = parts[..] else {
+                            panic!(
+                                "REQUEST_SIGNING_KEYS entries must be key_id:service:role:secret"
+                            );
+                        };
+                        let role = role
+                            .parse()
+                            .expect("REQUEST_SIGNING_KEYS role must be user, moderator or admin");
+                        (
+                            key_id.to_string(),
+                            SigningKey {
+                                secret: secret.to_string(),
+                                service: service.to_string(),
+                                role,
+                            },
+                        )
+                    })
+                    .collect(),
+                max_skew_seconds: env::var("REQUEST_SIGNING_MAX_SKEW")
+                    .unwrap_or_else(|_| "300".to_string())
+                    .parse()
+                    .expect("REQUEST_SIGNING_MAX_SKEW must be a valid number"),
+                required_headers: env_list("REQUEST_SIGNING_REQUIRED_HEADERS"),
+            },
+            tls: TlsConfig {
+                cert_path: env::var("TLS_CERT_PATH").ok().filter(|p| !p.is_empty()),
+                key_path: env::var("TLS_KEY_PATH").ok().filter(|p| !p.is_empty()),
+                client_ca_path: env::var("TLS_CLIENT_CA_PATH")
+                    .ok()
+                    .filter(|p| !p.is_empty()),
+                client_cert_required: env_flag("TLS_CLIENT_CERT_REQUIRED", false),
+                client_services: env_list("TLS_CLIENT_SERVICES")
+                    .iter()
+                    .map(|entry| {
+                        let (name, role) = entry
+                            .rsplit_once(':')
+                            .expect("TLS_CLIENT_SERVICES entries must be name:role");
+                        let role = role
+                            .parse()
+                            .expect("TLS_CLIENT_SERVICES role must be user, moderator or admin");
+                        (name.to_string(), role)
+                    })
+                    .collect(),
+            },
+            user_purge: UserPurgeConfig {
+                retention_days: env::var("USER_PURGE_RETENTION_DAYS")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()
+                    .expect("USER_PURGE_RETENTION_DAYS must be a valid number"),
+                interval_seconds: env::var("USER_PURGE_INTERVAL_SECONDS")
+                    .unwrap_or_else(|_| "3600".to_string())
+                    .parse()
+                    .expect("USER_PURGE_INTERVAL_SECONDS must be a valid number"),
+            },
+            concurrency: ConcurrencyConfig {
+                if_match_required: env_flag("IF_MATCH_REQUIRED", true),
+            },
+            body_limits: BodyLimitConfig {
+                json_bytes: env::var("JSON_BODY_LIMIT_BYTES")
+                    .unwrap_or_else(|_| "2097152".to_string())
+                    .parse()
+                    .expect("JSON_BODY_LIMIT_BYTES must be a valid number"),
+                upload_bytes: env::var("UPLOAD_BODY_LIMIT_BYTES")
+                    .unwrap_or_else(|_| "10485760".to_string())
+                    .parse()
+                    .expect("UPLOAD_BODY_LIMIT_BYTES must be a valid number"),
+            },
+            locale: LocaleConfig {
+                default_locale: env::var("DEFAULT_LOCALE").unwrap_or_else(|_| "en".to_string()),
+            },
+            api_version: ApiVersionConfig {
+                v1_sunset: env::var("API_V1_SUNSET")
+                    .ok()
+                    .filter(|d| !d.is_empty())
+                    .map(|d| {
+                        d.parse()
+                            .expect("API_V1_SUNSET must be a date such as 2027-04-30")
+                    }),
+            },
         }
     }
 }
+
+/// Read a comma separated environment variable, ignoring empty entries
+fn env_list(key: &str) -> Vec<String> {
+    env::var(key)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Read a boolean environment variable (`true`/`false`, `1`/`0`)
+fn env_flag(key: &str, default: bool) -> bool {
+    match env::var(key) {
+        Ok(value) => matches!(value.to_lowercase().as_str(), "true" | "1" | "yes"),
+        Err(_) => default,
+    }
+}
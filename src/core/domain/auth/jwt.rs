@@ -11,6 +11,8 @@ pub struct Claims {
     pub role: String,
     pub exp: usize, // Expiration time
     pub iat: usize, // Issued at
+    #[serde(default)]
+    pub jti: String, // Token ID, used for revocation
 }
 
 #[derive(Clone)]
@@ -43,6 +45,7 @@ impl JwtService {
             role: role.to_string(),
             exp: expires_at.timestamp() as usize,
             iat: now.timestamp() as usize,
+            jti: Uuid::new_v4().to_string(),
         };
 
         encode(&Header::default(), &claims, &self.encoding_key).map_err(|_| AppError::Internal)
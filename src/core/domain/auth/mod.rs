@@ -1,2 +1,3 @@
 pub mod jwt;
+pub mod revocation;
 pub mod service;
@@ -0,0 +1,34 @@
+use chrono::Utc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+/// In-memory list of revoked token IDs (`jti` claims).
+///
+/// Entries are kept until the token would have expired anyway, after which
+/// they are pruned on the next write. The list belongs to one server process,
+/// so other instances and restarts do not see its revocations.
+#[derive(Clone, Default)]
+pub struct TokenRevocationList {
+    revoked: Arc<RwLock<HashMap<String, usize>>>,
+}
+
+impl TokenRevocationList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Revoke a token by its ID until its expiration time
+    pub fn revoke(&self, jti: &str, exp: usize) {
+        let now = Utc::now().timestamp() as usize;
+        let mut revoked = self.revoked.write().unwrap();
+        revoked.retain(|_, expires_at| *expires_at > now);
+        revoked.insert(jti.to_string(), exp);
+    }
+
+    /// Check whether a token ID has been revoked
+    pub fn is_revoked(&self, jti: &str) -> bool {
+        self.revoked.read().unwrap().contains_key(jti)
+    }
+}
@@ -1,7 +1,6 @@
 use bcrypt::{hash, verify, DEFAULT_COST};
 use sqlx::PgPool;
 use uuid::Uuid;
-use validator::Validate;
 
 use crate::core::domain::{
     auth::jwt::JwtService,
@@ -9,6 +8,29 @@ use crate::core::domain::{
     users::model::{AuthResponse, CreateUserRequest, LoginRequest, PublicUser, User, UserRole},
 };
 
+/// Hash `passwords`, in order, on the blocking thread pool so bcrypt does
+/// not stall the async workers. Large lists are split across one task per
+/// available core.
+pub async fn hash_passwords(passwords: Vec<String>) -> Result<Vec<String>> {
+    let workers = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = passwords.len().div_ceil(workers).max(1);
+    let tasks = passwords.chunks(chunk_size).map(|chunk| {
+        let chunk = chunk.to_vec();
+        tokio::task::spawn_blocking(move || {
+            chunk
+                .iter()
+                .map(|password| hash(password, DEFAULT_COST).map_err(AppError::from))
+                .collect::<Result<Vec<String>>>()
+        })
+    });
+
+    let mut hashes = Vec::with_capacity(passwords.len());
+    for chunk in futures::future::join_all(tasks).await {
+        hashes.extend(chunk.map_err(|_| AppError::Internal)??);
+    }
+    Ok(hashes)
+}
+
 pub struct AuthService {
     jwt_service: JwtService,
 }
@@ -20,6 +42,11 @@ impl AuthService {
         }
     }
 
+    /// Create an auth service that shares an already configured JWT service
+    pub fn with_jwt_service(jwt_service: JwtService) -> Self {
+        Self { jwt_service }
+    }
+
     pub fn hash_password(&self, password: &str) -> Result<String> {
         hash(password, DEFAULT_COST).map_err(|_| AppError::Internal)
     }
@@ -33,9 +60,6 @@ impl AuthService {
         pool: &PgPool,
         request: CreateUserRequest,
     ) -> Result<AuthResponse> {
-        // Validate input
-        request.validate()?;
-
         // Hash password
         let password_hash = self.hash_password(&request.password)?;
 
@@ -43,8 +67,10 @@ impl AuthService {
         let role = request.role.unwrap_or_else(|| UserRole::User.to_string());
 
         // Validate role
-        role.parse::<UserRole>()
-            .map_err(|e| AppError::Validation { message: e })?;
+        role.parse::<UserRole>().map_err(|e| AppError::Validation {
+            message: e,
+            errors: Vec::new(),
+        })?;
 
         // Create user in database
         let user_id = Uuid::new_v4();
@@ -79,20 +105,19 @@ impl AuthService {
     }
 
     pub async fn login_user(&self, pool: &PgPool, request: LoginRequest) -> Result<AuthResponse> {
-        // Validate input
-        request.validate()?;
-
         // Find user by email
-        let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = $1")
-            .bind(&request.email)
-            .fetch_optional(pool)
-            .await?
-            .ok_or_else(|| AppError::Authentication {
-                message: "Invalid credentials".to_string(),
-            })?;
-
-        // Verify password
-        if !self.verify_password(&request.password, &user.password_hash)? {
+        let user = sqlx::query_as::<_, User>(
+            "SELECT * FROM users WHERE lower(email) = lower($1) AND deleted_at IS NULL",
+        )
+        .bind(&request.email)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::Authentication {
+            message: "Invalid credentials".to_string(),
+        })?;
+
+        // Verify password; users without one cannot sign in with any
+        if !user.has_password() || !self.verify_password(&request.password, &user.password_hash)? {
             return Err(AppError::Authentication {
                 message: "Invalid credentials".to_string(),
             });
@@ -114,13 +139,14 @@ impl AuthService {
     }
 
     pub async fn get_user_by_id(&self, pool: &PgPool, user_id: Uuid) -> Result<PublicUser> {
-        let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-            .bind(user_id)
-            .fetch_optional(pool)
-            .await?
-            .ok_or_else(|| AppError::NotFound {
-                resource: "User".to_string(),
-            })?;
+        let user =
+            sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1 AND deleted_at IS NULL")
+                .bind(user_id)
+                .fetch_optional(pool)
+                .await?
+                .ok_or_else(|| AppError::NotFound {
+                    resource: "User".to_string(),
+                })?;
 
         Ok(user.into())
     }
@@ -1,5 +1,6 @@
-use actix_web::{HttpResponse, ResponseError};
+use actix_web::http::StatusCode;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use thiserror::Error;
 use utoipa::ToSchema;
 
@@ -9,7 +10,12 @@ pub enum AppError {
     Database(#[from] sqlx::Error),
 
     #[error("Validation error: {message}")]
-    Validation { message: String },
+    Validation {
+        message: String,
+        /// Failures of individual fields, empty when the request as a whole
+        /// is invalid
+        errors: Vec<FieldError>,
+    },
 
     #[error("Authentication error: {message}")]
     Authentication { message: String },
@@ -23,117 +29,242 @@ pub enum AppError {
     #[error("Conflict: {message}")]
     Conflict { message: String },
 
+    #[error("Precondition failed: {message}")]
+    PreconditionFailed { message: String },
+
+    #[error("Precondition required: {message}")]
+    PreconditionRequired { message: String },
+
+    #[error("Failed dependency: {message}")]
+    FailedDependency { message: String },
+
+    #[error("Method not allowed: {message}")]
+    MethodNotAllowed { message: String },
+
+    #[error("Payload too large: {message}")]
+    PayloadTooLarge { message: String },
+
+    #[error("Not acceptable: {message}")]
+    NotAcceptable { message: String },
+
+    #[error("Unsupported media type: {message}")]
+    UnsupportedMediaType { message: String },
+
     #[error("Internal server error")]
     Internal,
 }
 
-#[derive(Serialize, Deserialize, ToSchema)]
-pub struct ErrorResponse {
-    pub error: String,
-    pub message: String,
-    pub meta: MetaInfo,
+/// Stable, machine-readable error codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    ValidationError,
+    AuthenticationError,
+    AuthorizationError,
+    NotFound,
+    Conflict,
+    PreconditionFailed,
+    PreconditionRequired,
+    FailedDependency,
+    MethodNotAllowed,
+    PayloadTooLarge,
+    NotAcceptable,
+    UnsupportedMediaType,
+    DatabaseError,
+    InternalError,
 }
 
-#[derive(Serialize, Deserialize, ToSchema)]
-pub struct MetaInfo {
-    pub version: String,
-    pub app: String,
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::ValidationError => "validation_error",
+            ErrorCode::AuthenticationError => "authentication_error",
+            ErrorCode::AuthorizationError => "authorization_error",
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::Conflict => "conflict",
+            ErrorCode::PreconditionFailed => "precondition_failed",
+            ErrorCode::PreconditionRequired => "precondition_required",
+            ErrorCode::FailedDependency => "failed_dependency",
+            ErrorCode::MethodNotAllowed => "method_not_allowed",
+            ErrorCode::PayloadTooLarge => "payload_too_large",
+            ErrorCode::NotAcceptable => "not_acceptable",
+            ErrorCode::UnsupportedMediaType => "unsupported_media_type",
+            ErrorCode::DatabaseError => "database_error",
+            ErrorCode::InternalError => "internal_error",
+        }
+    }
+
+    /// RFC 7807 problem type identifying this code
+    pub fn problem_type(self) -> String {
+        format!("urn:afaf-rest-rust:error:{}", self.as_str())
+    }
 }
 
-impl ResponseError for AppError {
-    fn error_response(&self) -> HttpResponse {
-        let meta = MetaInfo {
-            version: "1.0.0".to_string(),
-            app: "afaf-rest-rust".to_string(),
-        };
+/// A validation failure of a single field
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FieldError {
+    /// Path of the field; nested fields are joined with `.` and list items
+    /// are indexed as in `operations[2].email`
+    #[schema(example = "email")]
+    pub field: String,
+    /// Rule that failed, such as `length`, `email` or `range`
+    #[schema(example = "email")]
+    pub code: String,
+    #[schema(example = "Invalid email format")]
+    pub message: String,
+    /// Arguments of the rule, such as `min` and `max` for `length`
+    #[schema(value_type = Object)]
+    pub params: Map<String, Value>,
+}
 
-        let (error_type, message, mut status) = match self {
-            AppError::Database(e) => {
-                log::error!("Database error: {:?}", e);
-                match e {
-                    sqlx::Error::RowNotFound => {
-                        ("not_found", "Resource not found", HttpResponse::NotFound())
-                    }
-                    sqlx::Error::Database(db_err) => {
-                        if db_err.constraint().is_some() {
-                            (
-                                "conflict",
-                                "Resource already exists",
-                                HttpResponse::Conflict(),
-                            )
-                        } else {
-                            (
-                                "database_error",
-                                "Database operation failed",
-                                HttpResponse::InternalServerError(),
-                            )
-                        }
-                    }
-                    _ => (
-                        "database_error",
-                        "Database operation failed",
-                        HttpResponse::InternalServerError(),
-                    ),
-                }
-            }
-            AppError::Validation { message } => (
-                "validation_error",
+impl AppError {
+    /// Error code, English message and status code of this error
+    pub fn parts(&self) -> (ErrorCode, &str, StatusCode) {
+        match self {
+            AppError::Database(e) => match e {
+                sqlx::Error::RowNotFound => (
+                    ErrorCode::NotFound,
+                    "Resource not found",
+                    StatusCode::NOT_FOUND,
+                ),
+                sqlx::Error::Database(db_err) if db_err.constraint().is_some() => (
+                    ErrorCode::Conflict,
+                    "Resource already exists",
+                    StatusCode::CONFLICT,
+                ),
+                _ => (
+                    ErrorCode::DatabaseError,
+                    "Database operation failed",
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                ),
+            },
+            AppError::Validation { message, .. } => (
+                ErrorCode::ValidationError,
                 message.as_str(),
-                HttpResponse::BadRequest(),
+                StatusCode::BAD_REQUEST,
             ),
             AppError::Authentication { message } => (
-                "authentication_error",
+                ErrorCode::AuthenticationError,
                 message.as_str(),
-                HttpResponse::Unauthorized(),
+                StatusCode::UNAUTHORIZED,
             ),
             AppError::Authorization { message } => (
-                "authorization_error",
+                ErrorCode::AuthorizationError,
                 message.as_str(),
-                HttpResponse::Forbidden(),
+                StatusCode::FORBIDDEN,
+            ),
+            AppError::NotFound { resource: _ } => (
+                ErrorCode::NotFound,
+                "Resource not found",
+                StatusCode::NOT_FOUND,
             ),
-            AppError::NotFound { resource: _ } => {
-                ("not_found", "Resource not found", HttpResponse::NotFound())
-            }
             AppError::Conflict { message } => {
-                ("conflict", message.as_str(), HttpResponse::Conflict())
+                (ErrorCode::Conflict, message.as_str(), StatusCode::CONFLICT)
             }
+            AppError::PreconditionFailed { message } => (
+                ErrorCode::PreconditionFailed,
+                message.as_str(),
+                StatusCode::PRECONDITION_FAILED,
+            ),
+            AppError::PreconditionRequired { message } => (
+                ErrorCode::PreconditionRequired,
+                message.as_str(),
+                StatusCode::PRECONDITION_REQUIRED,
+            ),
+            AppError::FailedDependency { message } => (
+                ErrorCode::FailedDependency,
+                message.as_str(),
+                StatusCode::FAILED_DEPENDENCY,
+            ),
+            AppError::MethodNotAllowed { message } => (
+                ErrorCode::MethodNotAllowed,
+                message.as_str(),
+                StatusCode::METHOD_NOT_ALLOWED,
+            ),
+            AppError::PayloadTooLarge { message } => (
+                ErrorCode::PayloadTooLarge,
+                message.as_str(),
+                StatusCode::PAYLOAD_TOO_LARGE,
+            ),
+            AppError::NotAcceptable { message } => (
+                ErrorCode::NotAcceptable,
+                message.as_str(),
+                StatusCode::NOT_ACCEPTABLE,
+            ),
+            AppError::UnsupportedMediaType { message } => (
+                ErrorCode::UnsupportedMediaType,
+                message.as_str(),
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ),
             AppError::Internal => (
-                "internal_error",
+                ErrorCode::InternalError,
                 "Internal server error",
-                HttpResponse::InternalServerError(),
+                StatusCode::INTERNAL_SERVER_ERROR,
             ),
-        };
-
-        status.json(ErrorResponse {
-            error: error_type.to_string(),
-            message: message.to_string(),
-            meta,
-        })
+        }
     }
 }
 
 impl From<validator::ValidationErrors> for AppError {
     fn from(errors: validator::ValidationErrors) -> Self {
-        let message = errors
-            .field_errors()
+        let mut field_errors = Vec::new();
+        collect_field_errors(&errors, "", &mut field_errors);
+        field_errors.sort_by(|a, b| a.field.cmp(&b.field));
+
+        let message = field_errors
             .iter()
-            .map(|(field, errors)| {
-                let field_errors: Vec<String> = errors
-                    .iter()
-                    .map(|error| {
-                        error
-                            .message
-                            .as_ref()
-                            .map(|msg| msg.to_string())
-                            .unwrap_or_else(|| format!("Invalid {}", field))
-                    })
-                    .collect();
-                format!("{}: {}", field, field_errors.join(", "))
-            })
+            .map(|error| format!("{}: {}", error.field, error.message))
             .collect::<Vec<String>>()
             .join("; ");
 
-        AppError::Validation { message }
+        AppError::Validation {
+            message,
+            errors: field_errors,
+        }
+    }
+}
+
+/// Flatten `errors` into `out`, prefixing field names with `path`
+fn collect_field_errors(
+    errors: &validator::ValidationErrors,
+    path: &str,
+    out: &mut Vec<FieldError>,
+) {
+    use validator::ValidationErrorsKind;
+
+    for (field, kind) in errors.errors() {
+        let field = match path {
+            "" => field.to_string(),
+            _ => format!("{}.{}", path, field),
+        };
+        match kind {
+            ValidationErrorsKind::Field(errors) => {
+                out.extend(errors.iter().map(|error| {
+                    FieldError {
+                        field: field.clone(),
+                        code: error.code.to_string(),
+                        message: error
+                            .message
+                            .as_ref()
+                            .map(|msg| msg.to_string())
+                            .unwrap_or_else(|| format!("Invalid {}", field)),
+                        // The rejected value is left out, it may be a password
+                        params: error
+                            .params
+                            .iter()
+                            .filter(|(name, _)| *name != "value")
+                            .map(|(name, value)| (name.to_string(), value.clone()))
+                            .collect(),
+                    }
+                }))
+            }
+            ValidationErrorsKind::Struct(errors) => collect_field_errors(errors, &field, out),
+            ValidationErrorsKind::List(items) => {
+                for (index, errors) in items {
+                    collect_field_errors(errors, &format!("{}[{}]", field, index), out);
+                }
+            }
+        }
     }
 }
 
@@ -1,3 +1,4 @@
 pub mod auth;
 pub mod error;
 pub mod users;
+pub mod validation;
@@ -0,0 +1,331 @@
+use actix_web::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use sqlx::{Connection, PgPool, Postgres, Transaction};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::core::domain::{
+    auth::service::hash_passwords,
+    error::AppError,
+    users::{
+        model::{CreateUserRequest, PublicUser, UpdateRoleRequest, UpdateUserRequest, UserRole},
+        repository::{email_taken, map_email_conflict, UserTxRepository},
+    },
+    validation::{normalize_email, normalize_name, normalize_role, ValidatedPayload},
+};
+
+/// Most operations accepted in one bulk request
+pub const MAX_BULK_OPERATIONS: usize = 100;
+
+/// How failures in a bulk request are handled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkMode {
+    /// Apply every operation or none of them
+    #[default]
+    Atomic,
+    /// Apply the operations that succeed and report the others
+    BestEffort,
+}
+
+/// A single operation of a bulk request
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BulkOperation {
+    /// Create a user
+    Create(CreateUserRequest),
+    /// Change a user's name or email
+    Update {
+        id: Uuid,
+        /// Expected version of the user, as for `If-Match`
+        version: Option<i64>,
+        name: Option<String>,
+        email: Option<String>,
+    },
+    /// Change a user's role
+    ChangeRole {
+        id: Uuid,
+        /// Expected version of the user, as for `If-Match`
+        version: Option<i64>,
+        #[schema(example = "moderator")]
+        role: String,
+    },
+    /// Soft delete a user
+    Delete {
+        id: Uuid,
+        /// Expected version of the user, as for `If-Match`
+        version: Option<i64>,
+    },
+}
+
+/// Request payload for bulk user operations. Operations are validated one
+/// by one when they run, so that each failure is reported in its result.
+#[derive(Debug, Deserialize, ToSchema, Validate)]
+pub struct BulkRequest {
+    #[serde(default)]
+    pub mode: BulkMode,
+    pub operations: Vec<BulkOperation>,
+}
+
+/// Outcome of one operation, in request order: the status it would have
+/// had as a single request and the created or updated user, or its error
+pub type BulkItemOutcome = Result<(StatusCode, Option<PublicUser>), AppError>;
+
+/// Outcomes of a bulk request
+#[derive(Debug)]
+pub struct BulkOutcome {
+    pub mode: BulkMode,
+    /// Whether any changes were saved
+    pub committed: bool,
+    pub results: Vec<BulkItemOutcome>,
+}
+
+impl ValidatedPayload for BulkRequest {
+    fn normalize(&mut self) {
+        for operation in &mut self.operations {
+            match operation {
+                BulkOperation::Create(user) => user.normalize(),
+                BulkOperation::Update { name, email, .. } => {
+                    if let Some(name) = name {
+                        normalize_name(name);
+                    }
+                    if let Some(email) = email {
+                        normalize_email(email);
+                    }
+                }
+                BulkOperation::ChangeRole { role, .. } => normalize_role(role),
+                BulkOperation::Delete { .. } => {}
+            }
+        }
+    }
+
+    fn validate_payload(&self) -> Result<(), AppError> {
+        if self.operations.is_empty() {
+            return Err(AppError::Validation {
+                message: "At least one operation is required".to_string(),
+                errors: Vec::new(),
+            });
+        }
+        if self.operations.len() > MAX_BULK_OPERATIONS {
+            return Err(AppError::Validation {
+                message: format!("At most {} operations are allowed", MAX_BULK_OPERATIONS),
+                errors: Vec::new(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Run the operations of a bulk request on behalf of `actor_id`.
+///
+/// Every operation runs in its own savepoint inside one transaction, so a
+/// failed operation leaves no partial changes. In atomic mode the first
+/// failure rolls back the whole transaction and the remaining operations are
+/// not attempted; in best-effort mode the successful operations are kept.
+/// With `version_required`, updates and deletes must carry a `version`.
+pub async fn run(
+    pool: &PgPool,
+    actor_id: Option<Uuid>,
+    request: BulkRequest,
+    version_required: bool,
+) -> Result<BulkOutcome, AppError> {
+    request.validate_payload()?;
+    let mode = request.mode;
+
+    // Hash before opening the transaction so it is not held open while
+    // bcrypt runs
+    let passwords = request
+        .operations
+        .iter()
+        .filter_map(|operation| match operation {
+            BulkOperation::Create(user) => Some(user.password.clone()),
+            _ => None,
+        })
+        .collect();
+    let mut hashes = hash_passwords(passwords).await?.into_iter();
+
+    let mut tx = pool.begin().await?;
+    let mut results = Vec::with_capacity(request.operations.len());
+    let mut failed = false;
+
+    for operation in request.operations {
+        let password_hash = match operation {
+            BulkOperation::Create(_) => hashes.next(),
+            _ => None,
+        };
+        if failed && mode == BulkMode::Atomic {
+            results.push(Err(rolled_back(
+                "Not attempted because another operation failed",
+            )));
+            continue;
+        }
+
+        let mut savepoint = Connection::begin(&mut *tx).await?;
+        match apply(
+            &mut savepoint,
+            actor_id,
+            operation,
+            password_hash,
+            version_required,
+        )
+        .await
+        {
+            Ok(result) => {
+                savepoint.commit().await?;
+                results.push(Ok(result));
+            }
+            Err(e) => {
+                savepoint.rollback().await?;
+                failed = true;
+                results.push(Err(e));
+            }
+        }
+    }
+
+    let committed = !(failed && mode == BulkMode::Atomic);
+    if committed {
+        tx.commit().await?;
+    } else {
+        tx.rollback().await?;
+        for result in results.iter_mut().filter(|r| r.is_ok()) {
+            *result = Err(rolled_back("Rolled back because another operation failed"));
+        }
+    }
+
+    Ok(BulkOutcome {
+        mode,
+        committed,
+        results,
+    })
+}
+
+fn rolled_back(message: &str) -> AppError {
+    AppError::FailedDependency {
+        message: message.to_string(),
+    }
+}
+
+/// Apply one operation; `password_hash` is the hashed password of a
+/// `Create`
+async fn apply(
+    tx: &mut Transaction<'_, Postgres>,
+    actor_id: Option<Uuid>,
+    operation: BulkOperation,
+    password_hash: Option<String>,
+    version_required: bool,
+) -> Result<(StatusCode, Option<PublicUser>), AppError> {
+    let mut repo = UserTxRepository { conn: &mut *tx };
+
+    let version = match &operation {
+        BulkOperation::Create(_) => None,
+        BulkOperation::Update { version, .. }
+        | BulkOperation::ChangeRole { version, .. }
+        | BulkOperation::Delete { version, .. } => *version,
+    };
+    if version.is_none() && version_required && !matches!(operation, BulkOperation::Create(_)) {
+        return Err(AppError::PreconditionRequired {
+            message: "version is required".to_string(),
+        });
+    }
+
+    match operation {
+        BulkOperation::Create(user) => {
+            user.validate_payload()?;
+            let role = user
+                .role
+                .as_deref()
+                .unwrap_or("user")
+                .parse::<UserRole>()
+                .map_err(|e| AppError::Validation {
+                    message: e,
+                    errors: Vec::new(),
+                })?;
+            if repo.email_exists(&user.email).await? {
+                return Err(email_taken());
+            }
+            let password_hash = password_hash.ok_or(AppError::Internal)?;
+            let created = repo
+                .create_user_with_password(&user.name, &user.email, &password_hash, &role)
+                .await
+                .map_err(map_email_conflict)?;
+            Ok((StatusCode::CREATED, Some(created.into())))
+        }
+        BulkOperation::Update {
+            id, name, email, ..
+        } => {
+            let changes = UpdateUserRequest { name, email };
+            changes.validate_payload()?;
+            if changes.name.is_none() && changes.email.is_none() {
+                return Err(AppError::Validation {
+                    message: "At least one of name or email is required".to_string(),
+                    errors: Vec::new(),
+                });
+            }
+            let current = find(&mut repo, id).await?;
+            if let Some(email) = &changes.email {
+                if *email != current.email && repo.email_exists(email).await? {
+                    return Err(email_taken());
+                }
+            }
+            let user = repo
+                .update_user(
+                    id,
+                    version,
+                    changes.name.as_deref(),
+                    changes.email.as_deref(),
+                )
+                .await
+                .map_err(map_email_conflict)?
+                .ok_or_else(modified)?;
+            Ok((StatusCode::OK, Some(user.into())))
+        }
+        BulkOperation::ChangeRole { id, role, .. } => {
+            let request = UpdateRoleRequest { role };
+            request.validate_payload()?;
+            let role = request
+                .role
+                .parse::<UserRole>()
+                .map_err(|e| AppError::Validation {
+                    message: e,
+                    errors: Vec::new(),
+                })?;
+            if actor_id == Some(id) && role != UserRole::Admin {
+                return Err(AppError::Validation {
+                    message: "Admins cannot remove their own admin role".to_string(),
+                    errors: Vec::new(),
+                });
+            }
+            find(&mut repo, id).await?;
+            let user = repo
+                .update_user_role(id, version, &role)
+                .await?
+                .ok_or_else(modified)?;
+            Ok((StatusCode::OK, Some(user.into())))
+        }
+        BulkOperation::Delete { id, .. } => {
+            find(&mut repo, id).await?;
+            if !repo.delete_user(id, version).await? {
+                return Err(modified());
+            }
+            Ok((StatusCode::NO_CONTENT, None))
+        }
+    }
+}
+
+async fn find(repo: &mut UserTxRepository<'_>, id: Uuid) -> Result<PublicUser, AppError> {
+    repo.find_by_id(id)
+        .await?
+        .map(PublicUser::from)
+        .ok_or_else(|| AppError::NotFound {
+            resource: "User".to_string(),
+        })
+}
+
+/// The user exists, so a failed compare-and-swap means its version changed
+fn modified() -> AppError {
+    AppError::PreconditionFailed {
+        message: "User has been modified; fetch it again and retry with its new version"
+            .to_string(),
+    }
+}
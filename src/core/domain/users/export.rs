@@ -0,0 +1,290 @@
+use actix_web::web::Bytes;
+use chrono::SecondsFormat;
+use futures::{stream, Stream, TryStreamExt};
+use serde::{ser::SerializeMap, Serialize, Serializer};
+use serde_json::Value;
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+
+use crate::core::domain::{
+    error::AppError,
+    users::{model::User, query::UserQuery, repository::UserRepository},
+};
+
+/// Encoded chunks buffered ahead of a slow reader
+const CHANNEL_CAPACITY: usize = 64;
+
+/// Supported export formats
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    /// Comma separated values with a header row
+    Csv,
+    /// One JSON object per line, served as `application/jsonl`
+    Jsonl,
+    /// Same lines as `Jsonl`, served as `application/x-ndjson`
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Jsonl => "application/jsonl",
+            Self::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Jsonl => "jsonl",
+            Self::Ndjson => "ndjson",
+        }
+    }
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "jsonl" => Ok(Self::Jsonl),
+            "ndjson" => Ok(Self::Ndjson),
+            _ => Err(AppError::Validation {
+                message: format!("Unsupported export format: {}", s),
+                errors: Vec::new(),
+            }),
+        }
+    }
+}
+
+/// User fields that can be exported
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportColumn {
+    Id,
+    Name,
+    Email,
+    Role,
+    CreatedAt,
+    UpdatedAt,
+    Version,
+    DeletedAt,
+}
+
+impl ExportColumn {
+    /// Columns exported when none are selected: the fields of `PublicUser`
+    pub const DEFAULT: [Self; 7] = [
+        Self::Id,
+        Self::Name,
+        Self::Email,
+        Self::Role,
+        Self::CreatedAt,
+        Self::UpdatedAt,
+        Self::Version,
+    ];
+
+    const ALL: [Self; 8] = [
+        Self::Id,
+        Self::Name,
+        Self::Email,
+        Self::Role,
+        Self::CreatedAt,
+        Self::UpdatedAt,
+        Self::Version,
+        Self::DeletedAt,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::Name => "name",
+            Self::Email => "email",
+            Self::Role => "role",
+            Self::CreatedAt => "created_at",
+            Self::UpdatedAt => "updated_at",
+            Self::Version => "version",
+            Self::DeletedAt => "deleted_at",
+        }
+    }
+
+    /// Parse a comma separated column list; empty selects [`Self::DEFAULT`]
+    pub fn parse_list(spec: &str) -> Result<Vec<Self>, AppError> {
+        let mut columns = Vec::new();
+        for name in spec.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            let column = Self::ALL
+                .into_iter()
+                .find(|c| c.name() == name)
+                .ok_or_else(|| AppError::Validation {
+                    message: format!(
+                        "Unknown column '{}'; expected one of {}",
+                        name,
+                        Self::ALL.map(Self::name).join(", ")
+                    ),
+                    errors: Vec::new(),
+                })?;
+            if !columns.contains(&column) {
+                columns.push(column);
+            }
+        }
+        if columns.is_empty() {
+            columns = Self::DEFAULT.to_vec();
+        }
+        Ok(columns)
+    }
+
+    fn value(self, user: &User) -> Value {
+        let timestamp = |t: &chrono::DateTime<chrono::Utc>| {
+            Value::String(t.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+        };
+        match self {
+            Self::Id => Value::String(user.id.to_string()),
+            Self::Name => Value::String(user.name.clone()),
+            Self::Email => Value::String(user.email.clone()),
+            Self::Role => Value::String(user.role.clone()),
+            Self::CreatedAt => timestamp(&user.created_at),
+            Self::UpdatedAt => timestamp(&user.updated_at),
+            Self::Version => Value::from(user.version),
+            Self::DeletedAt => user.deleted_at.as_ref().map_or(Value::Null, timestamp),
+        }
+    }
+
+    /// CSV cell: strings unquoted, null as empty
+    fn cell(self, user: &User) -> String {
+        match self.value(user) {
+            Value::String(s) => s,
+            Value::Null => String::new(),
+            other => other.to_string(),
+        }
+    }
+}
+
+/// Selected columns of one user, serialized as a JSON object in column order
+struct ExportRow<'a> {
+    columns: &'a [ExportColumn],
+    user: &'a User,
+}
+
+impl Serialize for ExportRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.columns.len()))?;
+        for column in self.columns {
+            map.serialize_entry(column.name(), &column.value(self.user))?;
+        }
+        map.end()
+    }
+}
+
+/// Export the users matching `query` as a stream of encoded chunks.
+///
+/// Rows are read from a database cursor by a background task and encoded one
+/// at a time; the bounded channel between them keeps memory constant however
+/// many users match. Dropping the stream stops the export.
+pub fn export_users(
+    pool: PgPool,
+    mut query: UserQuery,
+    format: ExportFormat,
+    columns: Vec<ExportColumn>,
+) -> impl Stream<Item = Result<Bytes, AppError>> {
+    let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+    query.select = Some(columns.iter().map(|c| c.name().to_string()).collect());
+
+    actix_web::rt::spawn(async move {
+        if format == ExportFormat::Csv {
+            let header = columns.iter().map(|c| c.name().to_string()).collect();
+            if sender.send(csv_line(header)).await.is_err() {
+                return;
+            }
+        }
+
+        let mut builder = UserRepository::select_by_query(&query);
+        let mut rows = builder.build_query_as::<User>().fetch(&pool);
+        loop {
+            let chunk = match rows.try_next().await {
+                Ok(Some(user)) => encode(format, &columns, &user),
+                Ok(None) => return,
+                Err(e) => {
+                    log::error!("User export failed: {}", e);
+                    Err(AppError::from(e))
+                }
+            };
+            let failed = chunk.is_err();
+            if sender.send(chunk).await.is_err() || failed {
+                return;
+            }
+        }
+    });
+
+    stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|chunk| (chunk, receiver))
+    })
+}
+
+fn encode(format: ExportFormat, columns: &[ExportColumn], user: &User) -> Result<Bytes, AppError> {
+    match format {
+        ExportFormat::Csv => csv_line(columns.iter().map(|c| c.cell(user)).collect()),
+        ExportFormat::Jsonl | ExportFormat::Ndjson => {
+            let mut line =
+                serde_json::to_vec(&ExportRow { columns, user }).map_err(|_| AppError::Internal)?;
+            line.push(b'\n');
+            Ok(Bytes::from(line))
+        }
+    }
+}
+
+fn csv_line(cells: Vec<String>) -> Result<Bytes, AppError> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record(&cells)
+        .map_err(|_| AppError::Internal)?;
+    writer
+        .into_inner()
+        .map(Bytes::from)
+        .map_err(|_| AppError::Internal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use uuid::Uuid;
+
+    fn user() -> User {
+        let at = Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap();
+        User {
+            id: Uuid::nil(),
+            name: "Doe, Jane \"JD\"".to_string(),
+            email: "jane@example.com".to_string(),
+            password_hash: "secret".to_string(),
+            role: "admin".to_string(),
+            created_at: at,
+            updated_at: at,
+            deleted_at: None,
+            version: 3,
+        }
+    }
+
+    #[test]
+    fn test_parse_columns() {
+        assert_eq!(ExportColumn::parse_list("").unwrap(), ExportColumn::DEFAULT);
+        assert_eq!(
+            ExportColumn::parse_list("email, id,email").unwrap(),
+            vec![ExportColumn::Email, ExportColumn::Id]
+        );
+        assert!(ExportColumn::parse_list("password_hash").is_err());
+    }
+
+    #[test]
+    fn test_encode_rows() {
+        let columns = ExportColumn::parse_list("name,version,deleted_at").unwrap();
+
+        let line = encode(ExportFormat::Csv, &columns, &user()).unwrap();
+        assert_eq!(line, "\"Doe, Jane \"\"JD\"\"\",3,\n");
+
+        let line = encode(ExportFormat::Ndjson, &columns, &user()).unwrap();
+        assert_eq!(
+            line,
+            "{\"name\":\"Doe, Jane \\\"JD\\\"\",\"version\":3,\"deleted_at\":null}\n"
+        );
+    }
+}
@@ -0,0 +1,439 @@
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use utoipa::ToSchema;
+
+use crate::core::domain::{
+    auth::service::hash_passwords,
+    error::AppError,
+    users::{
+        model::{CreateUserRequest, UserRole},
+        repository::{UserRepository, UserTxRepository},
+    },
+    validation::ValidatedPayload,
+};
+
+/// Rows inserted per transaction unless configured otherwise
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// Fields of `CreateUserRequest` that columns can be mapped to
+const FIELDS: [&str; 4] = ["name", "email", "password", "role"];
+
+/// Supported import file formats
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    /// Comma separated values with a header row
+    Csv,
+    /// One JSON object per line
+    Jsonl,
+}
+
+impl std::str::FromStr for ImportFormat {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" | "text/csv" => Ok(ImportFormat::Csv),
+            "jsonl" | "ndjson" | "application/jsonl" | "application/x-ndjson" => {
+                Ok(ImportFormat::Jsonl)
+            }
+            _ => Err(AppError::Validation {
+                message: format!("Unsupported import format: {}", s),
+                errors: Vec::new(),
+            }),
+        }
+    }
+}
+
+/// Source column (or JSON key) to `CreateUserRequest` field; columns that are
+/// not mapped are matched by name
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColumnMap(HashMap<String, String>);
+
+impl ColumnMap {
+    /// Parse `source:field` pairs separated by commas, e.g. `mail:email`
+    pub fn parse(spec: &str) -> Result<Self, AppError> {
+        let mut columns = HashMap::new();
+        for pair in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (source, field) = pair.split_once(':').ok_or_else(|| AppError::Validation {
+                message: format!("Column mapping '{}' must be source:field", pair),
+                errors: Vec::new(),
+            })?;
+            let field = field.trim();
+            if !FIELDS.contains(&field) {
+                return Err(AppError::Validation {
+                    message: format!(
+                        "Unknown field '{}'; expected one of {}",
+                        field,
+                        FIELDS.join(", ")
+                    ),
+                    errors: Vec::new(),
+                });
+            }
+            columns.insert(source.trim().to_string(), field.to_string());
+        }
+        Ok(Self(columns))
+    }
+
+    fn field<'a>(&'a self, column: &'a str) -> &'a str {
+        self.0.get(column).map(String::as_str).unwrap_or(column)
+    }
+}
+
+/// How an import is run
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    pub columns: ColumnMap,
+    /// Validate and report without inserting
+    pub dry_run: bool,
+    /// Insert the valid rows even if other rows are invalid
+    pub skip_invalid: bool,
+    /// Rows per transaction; `0` uses [`DEFAULT_BATCH_SIZE`]
+    pub batch_size: usize,
+}
+
+/// Problems with one row of the import
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportRowError {
+    /// Line of the row in the file, starting at 1
+    pub line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    pub errors: Vec<String>,
+}
+
+/// Outcome of an import
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportReport {
+    pub format: ImportFormat,
+    pub dry_run: bool,
+    /// Rows read from the file
+    pub total: usize,
+    pub valid: usize,
+    pub invalid: usize,
+    /// Users created; zero for dry runs and rejected imports
+    pub inserted: usize,
+    /// Valid rows not inserted because their batch failed; they are listed
+    /// in `errors`
+    pub failed: usize,
+    pub errors: Vec<ImportRowError>,
+}
+
+/// A parsed row that passed validation
+struct ValidRow {
+    user: CreateUserRequest,
+    role: UserRole,
+}
+
+/// Parse, validate and (unless `dry_run`) insert users from `data`.
+///
+/// Every row is checked with the `CreateUserRequest` rules, and emails are
+/// checked for duplicates within the file and against existing users. If
+/// any row is invalid nothing is inserted unless `skip_invalid` is set.
+/// Valid rows are inserted in transactions of `batch_size` rows, calling
+/// `on_progress(inserted, valid)` after each batch. A batch that fails is
+/// rolled back and its rows are reported as errors; the other batches are
+/// still inserted.
+pub async fn import_users(
+    pool: &PgPool,
+    data: &[u8],
+    format: ImportFormat,
+    options: &ImportOptions,
+    mut on_progress: impl FnMut(usize, usize),
+) -> Result<ImportReport, AppError> {
+    let rows = match format {
+        ImportFormat::Csv => parse_csv(data, &options.columns)?,
+        ImportFormat::Jsonl => parse_jsonl(data, &options.columns),
+    };
+    let total = rows.len();
+
+    let mut errors = Vec::new();
+    let mut valid = Vec::new();
+    let mut seen = HashSet::new();
+    for (line, row) in rows {
+        match validate_row(row) {
+            Ok(row) if !seen.insert(row.user.email.clone()) => errors.push(ImportRowError {
+                line,
+                email: Some(row.user.email),
+                errors: vec!["email: Duplicate email in import".to_string()],
+            }),
+            Ok(row) => valid.push((line, row)),
+            Err(error) => errors.push(ImportRowError { line, ..error }),
+        }
+    }
+
+    let emails: Vec<String> = valid
+        .iter()
+        .map(|(_, row)| row.user.email.clone())
+        .collect();
+    let existing: HashSet<String> = UserRepository { pool }
+        .existing_emails(&emails)
+        .await?
+        .into_iter()
+        .collect();
+    let (valid, taken): (Vec<_>, Vec<_>) = valid
+        .into_iter()
+        .partition(|(_, row)| !existing.contains(&row.user.email));
+    errors.extend(taken.into_iter().map(|(line, row)| ImportRowError {
+        line,
+        email: Some(row.user.email),
+        errors: vec!["email: Email already exists".to_string()],
+    }));
+    errors.sort_by_key(|error| error.line);
+
+    let mut report = ImportReport {
+        format,
+        dry_run: options.dry_run,
+        total,
+        valid: valid.len(),
+        invalid: errors.len(),
+        inserted: 0,
+        failed: 0,
+        errors,
+    };
+    if options.dry_run || (report.invalid > 0 && !options.skip_invalid) {
+        return Ok(report);
+    }
+
+    let batch_size = match options.batch_size {
+        0 => DEFAULT_BATCH_SIZE,
+        size => size,
+    };
+    for batch in valid.chunks(batch_size) {
+        // A failed batch is rolled back and reported; the batches before it
+        // stay committed and the later ones are still attempted
+        if let Err(e) = insert_batch(pool, batch).await {
+            log::error!("Import batch failed: {:?}", e);
+            let (_, message, _) = e.parts();
+            report.failed += batch.len();
+            report
+                .errors
+                .extend(batch.iter().map(|(line, row)| ImportRowError {
+                    line: *line,
+                    email: Some(row.user.email.clone()),
+                    errors: vec![format!(
+                        "Not inserted because its batch failed: {}",
+                        message
+                    )],
+                }));
+            continue;
+        }
+        report.inserted += batch.len();
+        on_progress(report.inserted, report.valid);
+    }
+    report.errors.sort_by_key(|error| error.line);
+
+    Ok(report)
+}
+
+/// Insert `batch` in one transaction
+async fn insert_batch(pool: &PgPool, batch: &[(usize, ValidRow)]) -> Result<(), AppError> {
+    // Hash before opening the transaction so it is not held open while
+    // bcrypt runs
+    let hashes = hash_passwords(
+        batch
+            .iter()
+            .map(|(_, row)| row.user.password.clone())
+            .collect(),
+    )
+    .await?;
+    let mut tx = pool.begin().await?;
+    let mut repo = UserTxRepository { conn: &mut tx };
+    for ((_, row), password_hash) in batch.iter().zip(&hashes) {
+        repo.create_user_with_password(&row.user.name, &row.user.email, password_hash, &row.role)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Fields of one row by target field name, or the reason it is unreadable
+type RawRow = Result<HashMap<String, String>, String>;
+
+fn parse_csv(data: &[u8], columns: &ColumnMap) -> Result<Vec<(usize, RawRow)>, AppError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(data);
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| AppError::Validation {
+            message: format!("Invalid CSV header: {}", e),
+            errors: Vec::new(),
+        })?
+        .iter()
+        .map(|column| columns.field(column).to_string())
+        .collect();
+
+    Ok(reader
+        .records()
+        .enumerate()
+        .map(|(index, record)| {
+            let line = record
+                .as_ref()
+                .ok()
+                .and_then(|r| r.position())
+                .map(|p| p.line() as usize)
+                .unwrap_or(index + 2);
+            let row = record.map_err(|e| e.to_string()).map(|record| {
+                headers
+                    .iter()
+                    .zip(record.iter())
+                    .filter(|(_, value)| !value.is_empty())
+                    .map(|(field, value)| (field.clone(), value.to_string()))
+                    .collect()
+            });
+            (line, row)
+        })
+        .collect())
+}
+
+fn parse_jsonl(data: &[u8], columns: &ColumnMap) -> Vec<(usize, RawRow)> {
+    String::from_utf8_lossy(data)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            let row = match serde_json::from_str::<Value>(line) {
+                Ok(Value::Object(object)) => Ok(object
+                    .iter()
+                    .filter_map(|(key, value)| {
+                        let value = match value {
+                            Value::String(s) => s.clone(),
+                            Value::Null => return None,
+                            other => other.to_string(),
+                        };
+                        Some((columns.field(key).to_string(), value))
+                    })
+                    .collect()),
+                Ok(_) => Err("Line is not a JSON object".to_string()),
+                Err(e) => Err(format!("Invalid JSON: {}", e)),
+            };
+            (index + 1, row)
+        })
+        .collect()
+}
+
+fn validate_row(row: RawRow) -> Result<ValidRow, ImportRowError> {
+    let invalid = |email: Option<String>, errors: Vec<String>| ImportRowError {
+        line: 0,
+        email,
+        errors,
+    };
+    let mut fields = row.map_err(|e| invalid(None, vec![e]))?;
+    let email = fields.get("email").cloned();
+
+    let mut errors: Vec<String> = ["name", "email", "password"]
+        .iter()
+        .filter(|field| !fields.contains_key(**field))
+        .map(|field| format!("{}: Missing value", field))
+        .collect();
+    if !errors.is_empty() {
+        return Err(invalid(email, errors));
+    }
+
+    let mut user = CreateUserRequest {
+        name: fields.remove("name").unwrap_or_default(),
+        email: fields.remove("email").unwrap_or_default(),
+        password: fields.remove("password").unwrap_or_default(),
+        role: fields.remove("role"),
+    };
+    user.normalize();
+    if let Err(AppError::Validation {
+        errors: field_errors,
+        ..
+    }) = user.validate_payload()
+    {
+        errors.extend(
+            field_errors
+                .iter()
+                .map(|error| format!("{}: {}", error.field, error.message)),
+        );
+    }
+    let role = user.role.as_deref().unwrap_or("user").parse::<UserRole>();
+    if let Err(e) = &role {
+        errors.push(format!("role: {}", e));
+    }
+
+    match role {
+        Ok(role) if errors.is_empty() => Ok(ValidRow { user, role }),
+        _ => Err(invalid(email, errors)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(rows: &[(usize, RawRow)], index: usize) -> &HashMap<String, String> {
+        rows[index].1.as_ref().unwrap()
+    }
+
+    #[test]
+    fn test_parse_csv_with_column_map() {
+        let columns = ColumnMap::parse("full_name:name, mail:email").unwrap();
+        let data = b"full_name,mail,password,role\nAda, ada@example.com ,password123,\n\"Lovelace, A\",al@example.com,password123,admin\n";
+        let rows = parse_csv(data, &columns).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].0, 2);
+        assert_eq!(fields(&rows, 0)["email"], "ada@example.com");
+        assert!(!fields(&rows, 0).contains_key("role"));
+        assert_eq!(fields(&rows, 1)["name"], "Lovelace, A");
+        assert_eq!(fields(&rows, 1)["role"], "admin");
+
+        assert!(ColumnMap::parse("mail").is_err());
+        assert!(ColumnMap::parse("mail:phone").is_err());
+    }
+
+    #[test]
+    fn test_parse_jsonl() {
+        let data = b"{\"name\":\"Ada\",\"mail\":\"ada@example.com\",\"role\":null}\n\n[1]\n{oops\n";
+        let rows = parse_jsonl(data, &ColumnMap::parse("mail:email").unwrap());
+
+        assert_eq!(rows.len(), 3);
+        assert_eq!(fields(&rows, 0)["email"], "ada@example.com");
+        assert!(!fields(&rows, 0).contains_key("role"));
+        assert_eq!(rows[1].0, 3);
+        assert!(rows[1].1.is_err());
+        assert!(rows[2].1.is_err());
+    }
+
+    #[test]
+    fn test_validate_row() {
+        let row = |pairs: &[(&str, &str)]| -> RawRow {
+            Ok(pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect())
+        };
+
+        let valid = validate_row(row(&[
+            ("name", "Ada"),
+            ("email", "ada@example.com"),
+            ("password", "password123"),
+            ("role", "Moderator"),
+        ]))
+        .ok()
+        .unwrap();
+        assert_eq!(valid.role, UserRole::Moderator);
+
+        let error = validate_row(row(&[("name", "Ada"), ("email", "ada@example.com")]))
+            .err()
+            .unwrap();
+        assert_eq!(error.errors, vec!["password: Missing value"]);
+        assert_eq!(error.email.as_deref(), Some("ada@example.com"));
+
+        let error = validate_row(row(&[
+            ("name", "Ada"),
+            ("email", "not-an-email"),
+            ("password", "short"),
+            ("role", "owner"),
+        ]))
+        .err()
+        .unwrap();
+        assert_eq!(error.errors.len(), 3);
+    }
+}
@@ -1,2 +1,8 @@
+pub mod bulk;
+pub mod export;
+pub mod import;
 pub mod model;
+pub mod purge;
+pub mod query;
 pub mod repository;
+pub mod search;
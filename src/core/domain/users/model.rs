@@ -3,7 +3,11 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use utoipa::ToSchema;
 use uuid::Uuid;
-use validator::Validate;
+use validator::{Validate, ValidationError};
+
+use crate::core::domain::validation::{
+    normalize_email, normalize_name, normalize_role, ValidatedPayload,
+};
 
 /// User role enumeration
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema, Default)]
@@ -41,6 +45,39 @@ impl std::str::FromStr for UserRole {
     }
 }
 
+impl UserRole {
+    /// What users with this role may do
+    pub fn description(&self) -> &'static str {
+        match self {
+            UserRole::User => "Regular user with basic permissions",
+            UserRole::Admin => "Administrator with full permissions",
+            UserRole::Moderator => "Moderator with elevated permissions",
+        }
+    }
+}
+
+/// A role embedded in place of its name with `expand=role`
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RoleDetails {
+    #[schema(example = "moderator")]
+    pub name: UserRole,
+    #[schema(example = "Moderator with elevated permissions")]
+    pub description: String,
+}
+
+impl From<UserRole> for RoleDetails {
+    fn from(role: UserRole) -> Self {
+        RoleDetails {
+            description: role.description().to_string(),
+            name: role,
+        }
+    }
+}
+
+/// `password_hash` of users without a password, such as users provisioned
+/// through SCIM, who cannot sign in with one. Never a valid bcrypt hash.
+pub const UNUSABLE_PASSWORD: &str = "!";
+
 /// Represents a user in the system
 #[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct User {
@@ -65,6 +102,13 @@ pub struct User {
     /// Last update timestamp
     #[schema(example = "2023-01-01T00:00:00Z")]
     pub updated_at: DateTime<Utc>,
+    /// Soft deletion timestamp; absent for active users
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "2023-01-02T00:00:00Z")]
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Incremented on every update; exposed as the `ETag`
+    #[schema(example = 1)]
+    pub version: i64,
 }
 
 impl User {
@@ -73,6 +117,11 @@ impl User {
         self.role.parse()
     }
 
+    /// Whether the user can sign in with a password
+    pub fn has_password(&self) -> bool {
+        self.password_hash != UNUSABLE_PASSWORD && !self.password_hash.is_empty()
+    }
+
     /// Check if user has a specific role
     pub fn has_role(&self, role: &UserRole) -> bool {
         match self.get_role() {
@@ -113,6 +162,14 @@ pub struct PublicUser {
     /// Last update timestamp
     #[schema(example = "2023-01-01T00:00:00Z")]
     pub updated_at: DateTime<Utc>,
+    /// Soft deletion timestamp; only present for deleted users, which are
+    /// listed with `include_deleted=true`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "2023-01-02T00:00:00Z")]
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Incremented on every update; send it back in `If-Match` to update
+    #[schema(example = 1)]
+    pub version: i64,
 }
 
 impl From<User> for PublicUser {
@@ -125,10 +182,42 @@ impl From<User> for PublicUser {
             role,
             created_at: user.created_at,
             updated_at: user.updated_at,
+            deleted_at: user.deleted_at,
+            version: user.version,
         }
     }
 }
 
+/// A user matching a search, with its relevance
+#[derive(Debug, FromRow)]
+pub struct UserSearchHit {
+    #[sqlx(flatten)]
+    pub user: User,
+    /// Full-text rank plus trigram similarity
+    pub rank: f32,
+}
+
+/// A search result for API responses
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UserSearchResult {
+    #[serde(flatten)]
+    pub user: PublicUser,
+    /// Relevance of the match; results are sorted by it, highest first
+    #[schema(example = 0.75)]
+    pub rank: f32,
+    /// Matched fields with search terms wrapped in `<mark>`
+    pub highlights: SearchHighlights,
+}
+
+/// Highlighted user fields; all other text is HTML escaped
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SearchHighlights {
+    #[schema(example = "<mark>Joh</mark>n Doe")]
+    pub name: String,
+    #[schema(example = "<mark>joh</mark>n.doe@example.com")]
+    pub email: String,
+}
+
 /// Request payload for creating a new user
 #[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct CreateUserRequest {
@@ -160,6 +249,70 @@ pub struct CreateUserRequest {
     pub role: Option<String>,
 }
 
+/// Request payload for updating a user's profile
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct UpdateUserRequest {
+    /// New full name of the user
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "Name must be between 1 and 255 characters"
+    ))]
+    #[schema(example = "John Doe")]
+    pub name: Option<String>,
+
+    /// New email address of the user
+    #[validate(email(message = "Invalid email format"))]
+    #[schema(example = "john.doe@example.com")]
+    pub email: Option<String>,
+}
+
+/// Request payload for changing a user's role
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct UpdateRoleRequest {
+    /// New role for the user (user, admin, moderator)
+    #[validate(custom(
+        function = "role_name",
+        message = "Role must be one of user, admin or moderator"
+    ))]
+    #[schema(example = "moderator")]
+    pub role: String,
+}
+
+/// `validator` rule for the name of a [`UserRole`]
+fn role_name(role: &str) -> Result<(), ValidationError> {
+    role.parse::<UserRole>()
+        .map(|_| ())
+        .map_err(|_| ValidationError::new("role"))
+}
+
+impl ValidatedPayload for CreateUserRequest {
+    fn normalize(&mut self) {
+        normalize_name(&mut self.name);
+        normalize_email(&mut self.email);
+        if let Some(role) = &mut self.role {
+            normalize_role(role);
+        }
+    }
+}
+
+impl ValidatedPayload for UpdateUserRequest {
+    fn normalize(&mut self) {
+        if let Some(name) = &mut self.name {
+            normalize_name(name);
+        }
+        if let Some(email) = &mut self.email {
+            normalize_email(email);
+        }
+    }
+}
+
+impl ValidatedPayload for UpdateRoleRequest {
+    fn normalize(&mut self) {
+        normalize_role(&mut self.role);
+    }
+}
+
 /// Request payload for user login
 #[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct LoginRequest {
@@ -174,6 +327,12 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+impl ValidatedPayload for LoginRequest {
+    fn normalize(&mut self) {
+        normalize_email(&mut self.email);
+    }
+}
+
 /// Response payload for successful authentication
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct AuthResponse {
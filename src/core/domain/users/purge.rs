@@ -0,0 +1,33 @@
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+
+use crate::{config::UserPurgeConfig, core::domain::users::repository::UserRepository};
+
+/// Permanently delete users soft deleted more than `retention_days` ago,
+/// returning how many were removed
+pub async fn purge_deleted_users(pool: &PgPool, retention_days: i64) -> Result<u64, sqlx::Error> {
+    let deleted_before = Utc::now() - Duration::days(retention_days);
+    UserRepository { pool }.purge_deleted(deleted_before).await
+}
+
+/// Run [`purge_deleted_users`] every `interval_seconds` on the current
+/// runtime; does nothing when the interval is zero
+pub fn spawn_user_purge(pool: PgPool, config: UserPurgeConfig) {
+    if config.interval_seconds == 0 {
+        log::info!("User purge disabled");
+        return;
+    }
+
+    actix_web::rt::spawn(async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(config.interval_seconds));
+        loop {
+            interval.tick().await;
+            match purge_deleted_users(&pool, config.retention_days).await {
+                Ok(0) => {}
+                Ok(purged) => log::info!("Purged {} deleted users", purged),
+                Err(e) => log::error!("User purge failed: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,748 @@
+//! Filter and sort expressions for user listings.
+//!
+//! ```text
+//! filter=role eq 'moderator' and created_at ge 2026-01-01
+//! sort=-created_at,name
+//! ```
+//!
+//! Filters support `eq`, `ne`, `gt`, `ge`, `lt`, `le`, `co` (contains) and
+//! `sw` (starts with), combined with `and`, `or`, `not` and parentheses.
+//! Expressions are parsed into a typed AST against a whitelist of fields and
+//! compiled to SQL with bound parameters only.
+
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use sqlx::{Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::core::domain::{
+    error::AppError,
+    users::model::{User, UserRole},
+};
+
+/// Longest filter expression accepted
+pub const MAX_FILTER_LENGTH: usize = 1024;
+/// Most comparisons a filter may contain
+pub const MAX_FILTER_TERMS: usize = 16;
+/// Most fields a listing may be sorted by
+pub const MAX_SORT_KEYS: usize = 3;
+
+/// Columns of [`User`] with the empty value loaded in their place when they
+/// are not selected
+const USER_COLUMNS: [(&str, &str); 9] = [
+    ("id", "id"),
+    ("name", "''"),
+    ("email", "''"),
+    ("password_hash", "''"),
+    ("role", "''"),
+    ("created_at", "'epoch'::timestamptz"),
+    ("updated_at", "'epoch'::timestamptz"),
+    ("deleted_at", "NULL::timestamptz"),
+    ("version", "0::bigint"),
+];
+
+/// User fields that can be filtered and sorted on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UserField {
+    Id,
+    Name,
+    Email,
+    Role,
+    CreatedAt,
+    UpdatedAt,
+}
+
+impl UserField {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "id" => Some(Self::Id),
+            "name" => Some(Self::Name),
+            "email" => Some(Self::Email),
+            "role" => Some(Self::Role),
+            "created_at" => Some(Self::CreatedAt),
+            "updated_at" => Some(Self::UpdatedAt),
+            _ => None,
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::Name => "name",
+            Self::Email => "email",
+            Self::Role => "role",
+            Self::CreatedAt => "created_at",
+            Self::UpdatedAt => "updated_at",
+        }
+    }
+
+    fn is_text(self) -> bool {
+        matches!(self, Self::Name | Self::Email)
+    }
+
+    /// Parse a literal for this field into a typed value
+    fn value(self, raw: &str) -> Result<FilterValue, AppError> {
+        let invalid =
+            |kind: &str| invalid(&format!("Invalid {} for {}: {}", kind, self.column(), raw));
+        match self {
+            Self::Id => Uuid::parse_str(raw)
+                .map(FilterValue::Uuid)
+                .map_err(|_| invalid("UUID")),
+            Self::Name | Self::Email => Ok(FilterValue::Text(raw.to_string())),
+            Self::Role => raw
+                .parse::<UserRole>()
+                .map(|role| FilterValue::Text(role.to_string()))
+                .map_err(|_| invalid("role")),
+            Self::CreatedAt | Self::UpdatedAt => parse_timestamp(raw)
+                .map(FilterValue::Timestamp)
+                .ok_or_else(|| invalid("timestamp")),
+        }
+    }
+
+    /// This field's value in a user, in the literal form accepted by [`Self::value`]
+    fn value_of(self, user: &User) -> String {
+        match self {
+            Self::Id => user.id.to_string(),
+            Self::Name => user.name.clone(),
+            Self::Email => user.email.clone(),
+            Self::Role => user.role.clone(),
+            Self::CreatedAt => user.created_at.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            Self::UpdatedAt => user.updated_at.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        }
+    }
+}
+
+/// Comparison operators
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Contains,
+    StartsWith,
+}
+
+impl Operator {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "eq" => Some(Self::Eq),
+            "ne" => Some(Self::Ne),
+            "gt" => Some(Self::Gt),
+            "ge" => Some(Self::Ge),
+            "lt" => Some(Self::Lt),
+            "le" => Some(Self::Le),
+            "co" => Some(Self::Contains),
+            "sw" => Some(Self::StartsWith),
+            _ => None,
+        }
+    }
+
+    fn sql(self) -> &'static str {
+        match self {
+            Self::Eq => " = ",
+            Self::Ne => " <> ",
+            Self::Gt => " > ",
+            Self::Ge => " >= ",
+            Self::Lt => " < ",
+            Self::Le => " <= ",
+            Self::Contains | Self::StartsWith => " ILIKE ",
+        }
+    }
+}
+
+/// A typed filter literal
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterValue {
+    Text(String),
+    Uuid(Uuid),
+    Timestamp(DateTime<Utc>),
+}
+
+/// Filter expression tree
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    Compare {
+        field: UserField,
+        op: Operator,
+        value: FilterValue,
+    },
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+}
+
+/// A sort key; ascending unless prefixed with `-`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SortKey {
+    pub field: UserField,
+    pub descending: bool,
+}
+
+/// Validated filter and sort order for a user listing
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserQuery {
+    pub filter: Option<Filter>,
+    pub sort: Vec<SortKey>,
+    /// Also match soft deleted users
+    pub include_deleted: bool,
+    /// Columns the caller will read; the others are loaded as empty values.
+    /// `None` loads every column
+    pub select: Option<Vec<String>>,
+}
+
+impl Default for UserQuery {
+    fn default() -> Self {
+        Self {
+            filter: None,
+            sort: vec![SortKey {
+                field: UserField::CreatedAt,
+                descending: false,
+            }],
+            include_deleted: false,
+            select: None,
+        }
+    }
+}
+
+impl UserQuery {
+    pub fn parse(filter: Option<&str>, sort: Option<&str>) -> Result<Self, AppError> {
+        let mut query = Self::default();
+        if let Some(filter) = filter.map(str::trim).filter(|f| !f.is_empty()) {
+            query.filter = Some(Parser::parse(filter)?);
+        }
+        if let Some(sort) = sort.map(str::trim).filter(|s| !s.is_empty()) {
+            query.sort = parse_sort(sort)?;
+        }
+        Ok(query)
+    }
+
+    /// Sort keys followed by the ID tie-breaker, giving a total order
+    fn order(&self) -> Vec<SortKey> {
+        let mut order = self.sort.clone();
+        if !order.iter().any(|key| key.field == UserField::Id) {
+            order.push(SortKey {
+                field: UserField::Id,
+                descending: false,
+            });
+        }
+        order
+    }
+
+    /// Keyset position of a user under this query's order, for cursors
+    pub fn position_of(&self, user: &User) -> Vec<String> {
+        self.order()
+            .iter()
+            .map(|key| key.field.value_of(user))
+            .collect()
+    }
+
+    /// Validate a keyset position taken from a cursor
+    pub fn parse_position(&self, position: &[String]) -> Result<Vec<FilterValue>, AppError> {
+        let order = self.order();
+        if position.len() != order.len() {
+            return Err(invalid("Cursor does not match the sort order"));
+        }
+        order
+            .iter()
+            .zip(position)
+            .map(|(key, raw)| key.field.value(raw))
+            .collect()
+    }
+
+    /// Append `SELECT ... FROM users` with the columns of [`User`].
+    ///
+    /// With `select`, columns that are not selected, not sorted on and not the
+    /// ID are replaced by empty values of the same type, so clients asking for
+    /// a few fields do not pay for loading the others.
+    pub fn push_select(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        let order = self.order();
+        let needed = |column: &str| match &self.select {
+            None => true,
+            Some(select) => {
+                column == "id"
+                    || select.iter().any(|c| c == column)
+                    || order.iter().any(|key| key.field.column() == column)
+            }
+        };
+        let columns: Vec<String> = USER_COLUMNS
+            .iter()
+            .map(|(column, empty)| {
+                if needed(column) {
+                    column.to_string()
+                } else {
+                    format!("{} AS {}", empty, column)
+                }
+            })
+            .collect();
+        builder.push("SELECT ");
+        builder.push(columns.join(", "));
+        builder.push(" FROM users");
+    }
+
+    /// Append `WHERE ...` excluding deleted users, for the filter and, when
+    /// given, the keyset position
+    pub fn push_where(
+        &self,
+        builder: &mut QueryBuilder<'_, Postgres>,
+        after: Option<&[FilterValue]>,
+    ) {
+        let mut keyword = " WHERE ";
+        if !self.include_deleted {
+            builder.push(keyword).push("deleted_at IS NULL");
+            keyword = " AND ";
+        }
+        if let Some(filter) = &self.filter {
+            builder.push(keyword).push("(");
+            push_filter(builder, filter);
+            builder.push(")");
+            keyword = " AND ";
+        }
+        if let Some(after) = after {
+            builder.push(keyword).push("(");
+            self.push_keyset(builder, after);
+            builder.push(")");
+        }
+    }
+
+    /// Append `ORDER BY ...`
+    pub fn push_order_by(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        builder.push(" ORDER BY ");
+        for (i, key) in self.order().iter().enumerate() {
+            if i > 0 {
+                builder.push(", ");
+            }
+            builder.push(key.field.column());
+            builder.push(if key.descending { " DESC" } else { " ASC" });
+        }
+    }
+
+    /// Rows strictly after `after`:
+    /// `(k1 > v1) OR (k1 = v1 AND k2 > v2) OR ...`, flipped for descending keys
+    fn push_keyset(&self, builder: &mut QueryBuilder<'_, Postgres>, after: &[FilterValue]) {
+        let order = self.order();
+        for i in 0..order.len() {
+            if i > 0 {
+                builder.push(" OR ");
+            }
+            builder.push("(");
+            for (key, value) in order.iter().zip(after).take(i) {
+                push_comparison(builder, key.field, " = ", value);
+                builder.push(" AND ");
+            }
+            let op = if order[i].descending { " < " } else { " > " };
+            push_comparison(builder, order[i].field, op, &after[i]);
+            builder.push(")");
+        }
+    }
+}
+
+fn invalid(message: &str) -> AppError {
+    AppError::Validation {
+        message: message.to_string(),
+        errors: Vec::new(),
+    }
+}
+
+/// Accept RFC 3339 timestamps or plain dates (midnight UTC)
+fn parse_timestamp(raw: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(raw)
+        .map(|ts| ts.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map(|ts| ts.and_utc())
+        })
+}
+
+fn parse_sort(sort: &str) -> Result<Vec<SortKey>, AppError> {
+    let mut keys: Vec<SortKey> = Vec::new();
+    for part in sort.split(',').map(str::trim) {
+        let (name, descending) = match part.strip_prefix('-') {
+            Some(name) => (name, true),
+            None => (part.strip_prefix('+').unwrap_or(part), false),
+        };
+        let field =
+            UserField::parse(name).ok_or_else(|| invalid(&format!("Cannot sort by {}", name)))?;
+        if keys.iter().any(|key| key.field == field) {
+            return Err(invalid(&format!("Duplicate sort field {}", name)));
+        }
+        keys.push(SortKey { field, descending });
+    }
+    if keys.len() > MAX_SORT_KEYS {
+        return Err(invalid(&format!(
+            "At most {} sort fields are allowed",
+            MAX_SORT_KEYS
+        )));
+    }
+    Ok(keys)
+}
+
+fn push_filter(builder: &mut QueryBuilder<'_, Postgres>, filter: &Filter) {
+    match filter {
+        Filter::Compare { field, op, value } => match (op, value) {
+            (Operator::Contains, FilterValue::Text(text)) => {
+                builder.push(field.column());
+                builder.push(op.sql());
+                builder.push_bind(format!("%{}%", escape_like(text)));
+            }
+            (Operator::StartsWith, FilterValue::Text(text)) => {
+                builder.push(field.column());
+                builder.push(op.sql());
+                builder.push_bind(format!("{}%", escape_like(text)));
+            }
+            _ => push_comparison(builder, *field, op.sql(), value),
+        },
+        Filter::And(left, right) | Filter::Or(left, right) => {
+            let joiner = if matches!(filter, Filter::And(..)) {
+                " AND "
+            } else {
+                " OR "
+            };
+            builder.push("(");
+            push_filter(builder, left);
+            builder.push(joiner);
+            push_filter(builder, right);
+            builder.push(")");
+        }
+        Filter::Not(inner) => {
+            builder.push("NOT (");
+            push_filter(builder, inner);
+            builder.push(")");
+        }
+    }
+}
+
+fn push_comparison(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    field: UserField,
+    op: &str,
+    value: &FilterValue,
+) {
+    builder.push(field.column());
+    builder.push(op);
+    match value {
+        FilterValue::Text(text) => builder.push_bind(text.clone()),
+        FilterValue::Uuid(id) => builder.push_bind(*id),
+        FilterValue::Timestamp(ts) => builder.push_bind(*ts),
+    };
+}
+
+/// Escape `LIKE` wildcards so user input only matches literally
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Literal(String),
+    Open,
+    Close,
+}
+
+/// Recursive descent parser:
+///
+/// ```text
+/// or         := and ("or" and)*
+/// and        := unary ("and" unary)*
+/// unary      := "not" unary | "(" or ")" | comparison
+/// comparison := field operator value
+/// ```
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    terms: usize,
+}
+
+impl Parser {
+    fn parse(input: &str) -> Result<Filter, AppError> {
+        if input.len() > MAX_FILTER_LENGTH {
+            return Err(invalid(&format!(
+                "Filter must be at most {} characters",
+                MAX_FILTER_LENGTH
+            )));
+        }
+        let mut parser = Self {
+            tokens: tokenize(input)?,
+            position: 0,
+            terms: 0,
+        };
+        let filter = parser.or()?;
+        match parser.next() {
+            None => Ok(filter),
+            Some(token) => Err(invalid(&format!("Unexpected {:?} in filter", token))),
+        }
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.tokens.get(self.position), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Filter, AppError> {
+        let mut filter = self.and()?;
+        while self.peek_keyword("or") {
+            self.position += 1;
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, AppError> {
+        let mut filter = self.unary()?;
+        while self.peek_keyword("and") {
+            self.position += 1;
+            filter = Filter::And(Box::new(filter), Box::new(self.unary()?));
+        }
+        Ok(filter)
+    }
+
+    fn unary(&mut self) -> Result<Filter, AppError> {
+        if self.peek_keyword("not") {
+            self.position += 1;
+            return Ok(Filter::Not(Box::new(self.unary()?)));
+        }
+        if self.tokens.get(self.position) == Some(&Token::Open) {
+            self.position += 1;
+            let filter = self.or()?;
+            return match self.next() {
+                Some(Token::Close) => Ok(filter),
+                _ => Err(invalid("Missing closing parenthesis in filter")),
+            };
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Filter, AppError> {
+        let field = match self.next() {
+            Some(Token::Word(name)) => UserField::parse(&name)
+                .ok_or_else(|| invalid(&format!("Cannot filter by {}", name)))?,
+            _ => return Err(invalid("Expected a field name in filter")),
+        };
+        let op = match self.next() {
+            Some(Token::Word(name)) => Operator::parse(&name)
+                .ok_or_else(|| invalid(&format!("Unknown operator {}", name)))?,
+            _ => return Err(invalid("Expected an operator in filter")),
+        };
+        let raw = match self.next() {
+            Some(Token::Word(value)) | Some(Token::Literal(value)) => value,
+            _ => return Err(invalid("Expected a value in filter")),
+        };
+
+        let allowed = match op {
+            Operator::Eq | Operator::Ne => true,
+            Operator::Contains | Operator::StartsWith => field.is_text(),
+            _ => !matches!(field, UserField::Id | UserField::Role),
+        };
+        if !allowed {
+            return Err(invalid(&format!(
+                "Operator {:?} is not supported for {}",
+                op,
+                field.column()
+            )));
+        }
+
+        self.terms += 1;
+        if self.terms > MAX_FILTER_TERMS {
+            return Err(invalid(&format!(
+                "Filter must have at most {} comparisons",
+                MAX_FILTER_TERMS
+            )));
+        }
+
+        Ok(Filter::Compare {
+            field,
+            op,
+            value: field.value(&raw)?,
+        })
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, AppError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '\'' | '"' => {
+                chars.next();
+                let mut literal = String::new();
+                loop {
+                    match chars.next() {
+                        // A doubled quote is an escaped quote
+                        Some(q) if q == c && chars.peek() == Some(&c) => {
+                            chars.next();
+                            literal.push(q);
+                        }
+                        Some(q) if q == c => break,
+                        Some(other) => literal.push(other),
+                        None => return Err(invalid("Unterminated string in filter")),
+                    }
+                }
+                tokens.push(Token::Literal(literal));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sql(query: &UserQuery) -> String {
+        let mut builder = QueryBuilder::new("SELECT * FROM users");
+        query.push_where(&mut builder, None);
+        query.push_order_by(&mut builder);
+        builder.sql().to_string()
+    }
+
+    #[test]
+    fn test_parse_filter_and_sort() {
+        let query = UserQuery::parse(
+            Some("role eq 'moderator' and created_at ge 2026-01-01"),
+            Some("-created_at,name"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            query.filter,
+            Some(Filter::And(
+                Box::new(Filter::Compare {
+                    field: UserField::Role,
+                    op: Operator::Eq,
+                    value: FilterValue::Text("moderator".to_string()),
+                }),
+                Box::new(Filter::Compare {
+                    field: UserField::CreatedAt,
+                    op: Operator::Ge,
+                    value: FilterValue::Timestamp(parse_timestamp("2026-01-01").unwrap()),
+                }),
+            ))
+        );
+        assert_eq!(
+            sql(&query),
+            "SELECT * FROM users WHERE deleted_at IS NULL AND ((role = $1 AND created_at >= $2)) \
+             ORDER BY created_at DESC, name ASC, id ASC"
+        );
+    }
+
+    #[test]
+    fn test_precedence_and_grouping() {
+        let query = UserQuery::parse(
+            Some("not (name co 'a''b' or email sw \"x_\") and role ne admin"),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            sql(&query),
+            "SELECT * FROM users WHERE deleted_at IS NULL \
+             AND ((NOT ((name ILIKE $1 OR email ILIKE $2)) AND role <> $3)) \
+             ORDER BY created_at ASC, id ASC"
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_expressions() {
+        for filter in [
+            "password_hash eq 'x'",
+            "name eq",
+            "name like 'x'",
+            "role gt 'user'",
+            "role eq 'superuser'",
+            "created_at ge yesterday",
+            "id co 'abc'",
+            "(name eq 'a'",
+            "name eq 'a' name eq 'b'",
+            "name eq 'unterminated",
+            "name eq 'a'; DROP TABLE users",
+        ] {
+            assert!(
+                UserQuery::parse(Some(filter), None).is_err(),
+                "{} should be rejected",
+                filter
+            );
+        }
+
+        let too_many = vec!["name eq 'a'"; MAX_FILTER_TERMS + 1].join(" or ");
+        assert!(UserQuery::parse(Some(&too_many), None).is_err());
+
+        assert!(UserQuery::parse(None, Some("password_hash")).is_err());
+        assert!(UserQuery::parse(None, Some("name,-name")).is_err());
+        assert!(UserQuery::parse(None, Some("name,email,role,id")).is_err());
+    }
+
+    #[test]
+    fn test_keyset_predicate() {
+        let query = UserQuery {
+            include_deleted: true,
+            ..UserQuery::parse(None, Some("-name")).unwrap()
+        };
+        let after = query
+            .parse_position(&["Bob".to_string(), Uuid::nil().to_string()])
+            .unwrap();
+
+        let mut builder = QueryBuilder::new("SELECT * FROM users");
+        query.push_where(&mut builder, Some(&after));
+        assert_eq!(
+            builder.sql(),
+            "SELECT * FROM users WHERE ((name < $1) OR (name = $2 AND id > $3))"
+        );
+
+        assert!(query.parse_position(&["Bob".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_select_columns() {
+        let mut query = UserQuery::parse(None, Some("email")).unwrap();
+        let mut builder = QueryBuilder::new("");
+        query.push_select(&mut builder);
+        assert_eq!(
+            builder.sql(),
+            "SELECT id, name, email, password_hash, role, created_at, updated_at, deleted_at, version FROM users"
+        );
+
+        // Sort columns and the ID tie-breaker are kept for cursors
+        query.select = Some(vec!["name".to_string()]);
+        let mut builder = QueryBuilder::new("");
+        query.push_select(&mut builder);
+        assert_eq!(
+            builder.sql(),
+            "SELECT id, name, email, '' AS password_hash, '' AS role, \
+             'epoch'::timestamptz AS created_at, 'epoch'::timestamptz AS updated_at, \
+             NULL::timestamptz AS deleted_at, 0::bigint AS version FROM users"
+        );
+    }
+}
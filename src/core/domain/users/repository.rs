@@ -1,7 +1,33 @@
-use crate::core::domain::users::model::{User, UserRole};
-use sqlx::PgPool;
+use crate::core::domain::{
+    error::AppError,
+    users::{
+        model::{User, UserRole, UserSearchHit, UNUSABLE_PASSWORD},
+        query::{FilterValue, UserQuery},
+    },
+};
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgExecutor, PgPool, Postgres, QueryBuilder};
 use uuid::Uuid;
 
+/// Unique index on the emails of users that are not deleted
+pub const EMAIL_KEY: &str = "users_email_key";
+
+/// The conflict reported when an email belongs to another user
+pub fn email_taken() -> AppError {
+    AppError::Conflict {
+        message: "Email already exists".to_string(),
+    }
+}
+
+/// `e` as an [`AppError`], reporting a violation of [`EMAIL_KEY`] as
+/// [`email_taken`]
+pub fn map_email_conflict(e: sqlx::Error) -> AppError {
+    match e {
+        sqlx::Error::Database(db_err) if db_err.constraint() == Some(EMAIL_KEY) => email_taken(),
+        e => AppError::from(e),
+    }
+}
+
 pub struct UserRepository<'a> {
     pub pool: &'a PgPool,
 }
@@ -10,21 +36,96 @@ impl<'a> UserRepository<'a> {
     /// Find all users
     pub async fn find_all(&self) -> Result<Vec<User>, sqlx::Error> {
         sqlx::query_as::<_, User>(
-            "SELECT id, name, email, password_hash, role, created_at, updated_at FROM users",
+            "SELECT id, name, email, password_hash, role, created_at, updated_at, deleted_at, version FROM users WHERE deleted_at IS NULL",
         )
         .fetch_all(self.pool)
         .await
     }
 
+    /// Find users matching a filter, in the query's sort order.
+    ///
+    /// With `after`, only rows past that keyset position are returned and
+    /// `offset` should be zero.
+    pub async fn find_by_query(
+        &self,
+        query: &UserQuery,
+        after: Option<&[FilterValue]>,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<User>, sqlx::Error> {
+        let mut builder = QueryBuilder::new("");
+        query.push_select(&mut builder);
+        query.push_where(&mut builder, after);
+        query.push_order_by(&mut builder);
+        builder.push(" LIMIT ").push_bind(limit);
+        builder.push(" OFFSET ").push_bind(offset);
+        builder.build_query_as::<User>().fetch_all(self.pool).await
+    }
+
+    /// Select users matching a filter, in the query's sort order, for
+    /// streaming with `fetch` instead of loading the whole result
+    pub fn select_by_query(query: &UserQuery) -> QueryBuilder<'static, Postgres> {
+        let mut builder = QueryBuilder::new("");
+        query.push_select(&mut builder);
+        query.push_where(&mut builder, None);
+        query.push_order_by(&mut builder);
+        builder
+    }
+
+    /// Count users matching a filter
+    pub async fn count_by_query(&self, query: &UserQuery) -> Result<i64, sqlx::Error> {
+        let mut builder = QueryBuilder::new("SELECT COUNT(*) FROM users");
+        query.push_where(&mut builder, None);
+        let count: (i64,) = builder.build_query_as().fetch_one(self.pool).await?;
+        Ok(count.0)
+    }
+
+    /// Search names and emails by word prefixes (`prefix_query`, a `tsquery`)
+    /// or trigram word similarity to the raw `text`, most relevant first
+    pub async fn search(
+        &self,
+        text: &str,
+        prefix_query: &str,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<UserSearchHit>, sqlx::Error> {
+        sqlx::query_as::<_, UserSearchHit>(
+            "SELECT id, name, email, password_hash, role, created_at, updated_at, deleted_at, version, \
+                (ts_rank(search_vector, query) + GREATEST(word_similarity($1, name), word_similarity($1, email)))::real AS rank \
+             FROM users, to_tsquery('simple', $2) AS query \
+             WHERE deleted_at IS NULL AND (search_vector @@ query OR $1 <% name OR $1 <% email) \
+             ORDER BY rank DESC, created_at, id LIMIT $3 OFFSET $4",
+        )
+        .bind(text)
+        .bind(prefix_query)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.pool)
+        .await
+    }
+
+    /// Count users matched by [`Self::search`]
+    pub async fn count_search(&self, text: &str, prefix_query: &str) -> Result<i64, sqlx::Error> {
+        let count: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM users, to_tsquery('simple', $2) AS query \
+             WHERE deleted_at IS NULL AND (search_vector @@ query OR $1 <% name OR $1 <% email)",
+        )
+        .bind(text)
+        .bind(prefix_query)
+        .fetch_one(self.pool)
+        .await?;
+        Ok(count.0)
+    }
+
     /// Create a new user (legacy method for backward compatibility)
     pub async fn create_user(&self, name: &str, email: &str) -> Result<User, sqlx::Error> {
         sqlx::query_as::<_, User>(
-            "INSERT INTO users (id, name, email, password_hash, role, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, NOW(), NOW()) RETURNING id, name, email, password_hash, role, created_at, updated_at",
+            "INSERT INTO users (id, name, email, password_hash, role, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, NOW(), NOW()) RETURNING id, name, email, password_hash, role, created_at, updated_at, deleted_at, version",
         )
         .bind(Uuid::new_v4())
         .bind(name)
         .bind(email)
-        .bind("") // Empty password hash for legacy compatibility
+        .bind(UNUSABLE_PASSWORD) // Legacy users have no password
         .bind("user") // Default role
         .fetch_one(self.pool)
         .await
@@ -38,22 +139,13 @@ impl<'a> UserRepository<'a> {
         password_hash: &str,
         role: &UserRole,
     ) -> Result<User, sqlx::Error> {
-        sqlx::query_as::<_, User>(
-            "INSERT INTO users (id, name, email, password_hash, role, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, NOW(), NOW()) RETURNING id, name, email, password_hash, role, created_at, updated_at",
-        )
-        .bind(Uuid::new_v4())
-        .bind(name)
-        .bind(email)
-        .bind(password_hash)
-        .bind(role.to_string())
-        .fetch_one(self.pool)
-        .await
+        insert_user(self.pool, name, email, password_hash, role).await
     }
 
-    /// Find user by email
+    /// Find user by email, ignoring case
     pub async fn find_by_email(&self, email: &str) -> Result<User, sqlx::Error> {
         sqlx::query_as::<_, User>(
-            "SELECT id, name, email, password_hash, role, created_at, updated_at FROM users WHERE email = $1",
+            "SELECT id, name, email, password_hash, role, created_at, updated_at, deleted_at, version FROM users WHERE lower(email) = lower($1) AND deleted_at IS NULL",
         )
         .bind(email)
         .fetch_one(self.pool)
@@ -62,8 +154,26 @@ impl<'a> UserRepository<'a> {
 
     /// Find user by ID
     pub async fn find_by_id(&self, id: Uuid) -> Result<User, sqlx::Error> {
+        select_by_id(self.pool, id)
+            .await?
+            .ok_or(sqlx::Error::RowNotFound)
+    }
+
+    /// Whether a user exists and is not deleted
+    pub async fn is_active(&self, id: Uuid) -> Result<bool, sqlx::Error> {
+        let active: (bool,) = sqlx::query_as(
+            "SELECT EXISTS (SELECT 1 FROM users WHERE id = $1 AND deleted_at IS NULL)",
+        )
+        .bind(id)
+        .fetch_one(self.pool)
+        .await?;
+        Ok(active.0)
+    }
+
+    /// Find user by ID, including soft deleted users
+    pub async fn find_by_id_including_deleted(&self, id: Uuid) -> Result<User, sqlx::Error> {
         sqlx::query_as::<_, User>(
-            "SELECT id, name, email, password_hash, role, created_at, updated_at FROM users WHERE id = $1",
+            "SELECT id, name, email, password_hash, role, created_at, updated_at, deleted_at, version FROM users WHERE id = $1",
         )
         .bind(id)
         .fetch_one(self.pool)
@@ -76,13 +186,9 @@ impl<'a> UserRepository<'a> {
         id: Uuid,
         password_hash: &str,
     ) -> Result<User, sqlx::Error> {
-        sqlx::query_as::<_, User>(
-            "UPDATE users SET password_hash = $1, updated_at = NOW() WHERE id = $2 RETURNING id, name, email, password_hash, role, created_at, updated_at",
-        )
-        .bind(password_hash)
-        .bind(id)
-        .fetch_one(self.pool)
-        .await
+        update_password_hash(self.pool, id, password_hash)
+            .await?
+            .ok_or(sqlx::Error::RowNotFound)
     }
 
     /// Update user profile
@@ -92,71 +198,300 @@ impl<'a> UserRepository<'a> {
         name: Option<&str>,
         email: Option<&str>,
     ) -> Result<User, sqlx::Error> {
-        let mut query = "UPDATE users SET updated_at = NOW()".to_string();
-        let mut params: Vec<&str> = vec![];
-        let mut param_count = 1;
-
-        if let Some(name) = name {
-            query.push_str(&format!(", name = ${}", param_count));
-            params.push(name);
-            param_count += 1;
-        }
-
-        if let Some(email) = email {
-            query.push_str(&format!(", email = ${}", param_count));
-            params.push(email);
-            param_count += 1;
-        }
+        update_profile(self.pool, id, None, name, email)
+            .await?
+            .ok_or(sqlx::Error::RowNotFound)
+    }
 
-        query.push_str(&format!(" WHERE id = ${} RETURNING id, name, email, password_hash, role, created_at, updated_at", param_count));
+    /// Update user profile if it is still at `version`; `None` when the
+    /// user is missing or was changed in the meantime
+    pub async fn update_user_if_version(
+        &self,
+        id: Uuid,
+        version: i64,
+        name: Option<&str>,
+        email: Option<&str>,
+    ) -> Result<Option<User>, sqlx::Error> {
+        update_profile(self.pool, id, Some(version), name, email).await
+    }
 
-        let mut sql_query = sqlx::query_as::<_, User>(&query);
+    /// Update user role (admin only)
+    pub async fn update_user_role(&self, id: Uuid, role: &UserRole) -> Result<User, sqlx::Error> {
+        update_role(self.pool, id, None, role)
+            .await?
+            .ok_or(sqlx::Error::RowNotFound)
+    }
 
-        for param in params {
-            sql_query = sql_query.bind(param);
-        }
+    /// Update user role if the user is still at `version`; `None` when the
+    /// user is missing or was changed in the meantime
+    pub async fn update_user_role_if_version(
+        &self,
+        id: Uuid,
+        version: i64,
+        role: &UserRole,
+    ) -> Result<Option<User>, sqlx::Error> {
+        update_role(self.pool, id, Some(version), role).await
+    }
 
-        sql_query = sql_query.bind(id);
+    /// Soft delete user; the row is kept until purged. `false` when the
+    /// user is missing or already deleted
+    pub async fn delete_user(&self, id: Uuid) -> Result<bool, sqlx::Error> {
+        soft_delete(self.pool, id, None).await
+    }
 
-        sql_query.fetch_one(self.pool).await
+    /// Soft delete user if it is still at `version`; `false` when the user
+    /// is missing or was changed in the meantime
+    pub async fn delete_user_if_version(
+        &self,
+        id: Uuid,
+        version: i64,
+    ) -> Result<bool, sqlx::Error> {
+        soft_delete(self.pool, id, Some(version)).await
     }
 
-    /// Update user role (admin only)
-    pub async fn update_user_role(&self, id: Uuid, role: &UserRole) -> Result<User, sqlx::Error> {
+    /// Restore a soft deleted user
+    pub async fn restore_user(&self, id: Uuid) -> Result<User, sqlx::Error> {
         sqlx::query_as::<_, User>(
-            "UPDATE users SET role = $1, updated_at = NOW() WHERE id = $2 RETURNING id, name, email, password_hash, role, created_at, updated_at",
+            "UPDATE users SET deleted_at = NULL, updated_at = NOW(), version = version + 1 WHERE id = $1 AND deleted_at IS NOT NULL RETURNING id, name, email, password_hash, role, created_at, updated_at, deleted_at, version",
         )
-        .bind(role.to_string())
         .bind(id)
         .fetch_one(self.pool)
         .await
     }
 
-    /// Delete user
-    pub async fn delete_user(&self, id: Uuid) -> Result<(), sqlx::Error> {
-        sqlx::query("DELETE FROM users WHERE id = $1")
-            .bind(id)
+    /// Permanently delete users soft deleted before `deleted_before`
+    pub async fn purge_deleted(&self, deleted_before: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM users WHERE deleted_at < $1")
+            .bind(deleted_before)
             .execute(self.pool)
             .await?;
-        Ok(())
+        Ok(result.rows_affected())
     }
 
     /// Find users by role
     pub async fn find_by_role(&self, role: &UserRole) -> Result<Vec<User>, sqlx::Error> {
-        sqlx::query_as::<_, User>(
-            "SELECT id, name, email, password_hash, role, created_at, updated_at FROM users WHERE role = $1",
+        select_by_role(self.pool, role).await
+    }
+
+    /// Check if email is used by a user that is not deleted, ignoring case
+    pub async fn email_exists(&self, email: &str) -> Result<bool, sqlx::Error> {
+        email_in_use(self.pool, email).await
+    }
+
+    /// Which of `emails` are used by users that are not deleted, ignoring
+    /// case; returned in lower case
+    pub async fn existing_emails(&self, emails: &[String]) -> Result<Vec<String>, sqlx::Error> {
+        let rows: Vec<(String,)> = sqlx::query_as(
+            "SELECT lower(email) FROM users WHERE lower(email) IN (SELECT lower(e) FROM unnest($1::text[]) AS e) AND deleted_at IS NULL",
         )
-        .bind(role.to_string())
+        .bind(emails)
         .fetch_all(self.pool)
-        .await
+        .await?;
+        Ok(rows.into_iter().map(|(email,)| email).collect())
     }
+}
 
-    /// Check if email exists
-    pub async fn email_exists(&self, email: &str) -> Result<bool, sqlx::Error> {
-        let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users WHERE email = $1")
-            .bind(email)
-            .fetch_one(self.pool)
-            .await?;
-        Ok(count.0 > 0)
+/// User repository on a single connection, usually a transaction, so that
+/// several writes are committed or rolled back together.
+///
+/// Updates and deletes take an optional expected version and return `None`
+/// (or `false`) when the user is missing or was changed in the meantime.
+pub struct UserTxRepository<'c> {
+    pub conn: &'c mut PgConnection,
+}
+
+impl UserTxRepository<'_> {
+    /// Find user by ID
+    pub async fn find_by_id(&mut self, id: Uuid) -> Result<Option<User>, sqlx::Error> {
+        select_by_id(&mut *self.conn, id).await
+    }
+
+    /// Find users by role
+    pub async fn find_by_role(&mut self, role: &UserRole) -> Result<Vec<User>, sqlx::Error> {
+        select_by_role(&mut *self.conn, role).await
+    }
+
+    /// Check if email is used by a user that is not deleted, ignoring case
+    pub async fn email_exists(&mut self, email: &str) -> Result<bool, sqlx::Error> {
+        email_in_use(&mut *self.conn, email).await
+    }
+
+    /// Create a new user with password and role
+    pub async fn create_user_with_password(
+        &mut self,
+        name: &str,
+        email: &str,
+        password_hash: &str,
+        role: &UserRole,
+    ) -> Result<User, sqlx::Error> {
+        insert_user(&mut *self.conn, name, email, password_hash, role).await
+    }
+
+    /// Update user profile
+    pub async fn update_user(
+        &mut self,
+        id: Uuid,
+        version: Option<i64>,
+        name: Option<&str>,
+        email: Option<&str>,
+    ) -> Result<Option<User>, sqlx::Error> {
+        update_profile(&mut *self.conn, id, version, name, email).await
+    }
+
+    /// Update user password
+    pub async fn update_password(
+        &mut self,
+        id: Uuid,
+        password_hash: &str,
+    ) -> Result<Option<User>, sqlx::Error> {
+        update_password_hash(&mut *self.conn, id, password_hash).await
     }
+
+    /// Update user role
+    pub async fn update_user_role(
+        &mut self,
+        id: Uuid,
+        version: Option<i64>,
+        role: &UserRole,
+    ) -> Result<Option<User>, sqlx::Error> {
+        update_role(&mut *self.conn, id, version, role).await
+    }
+
+    /// Soft delete user
+    pub async fn delete_user(
+        &mut self,
+        id: Uuid,
+        version: Option<i64>,
+    ) -> Result<bool, sqlx::Error> {
+        soft_delete(&mut *self.conn, id, version).await
+    }
+}
+
+// Statements shared by both repositories; a `version` of `None` skips the
+// version check
+
+async fn select_by_id<'e>(
+    executor: impl PgExecutor<'e>,
+    id: Uuid,
+) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as::<_, User>(
+        "SELECT id, name, email, password_hash, role, created_at, updated_at, deleted_at, version FROM users WHERE id = $1 AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .fetch_optional(executor)
+    .await
+}
+
+async fn select_by_role<'e>(
+    executor: impl PgExecutor<'e>,
+    role: &UserRole,
+) -> Result<Vec<User>, sqlx::Error> {
+    sqlx::query_as::<_, User>(
+        "SELECT id, name, email, password_hash, role, created_at, updated_at, deleted_at, version FROM users WHERE role = $1 AND deleted_at IS NULL",
+    )
+    .bind(role.to_string())
+    .fetch_all(executor)
+    .await
+}
+
+async fn email_in_use<'e>(executor: impl PgExecutor<'e>, email: &str) -> Result<bool, sqlx::Error> {
+    let count: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM users WHERE lower(email) = lower($1) AND deleted_at IS NULL",
+    )
+    .bind(email)
+    .fetch_one(executor)
+    .await?;
+    Ok(count.0 > 0)
+}
+
+async fn insert_user<'e>(
+    executor: impl PgExecutor<'e>,
+    name: &str,
+    email: &str,
+    password_hash: &str,
+    role: &UserRole,
+) -> Result<User, sqlx::Error> {
+    sqlx::query_as::<_, User>(
+        "INSERT INTO users (id, name, email, password_hash, role, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, NOW(), NOW()) RETURNING id, name, email, password_hash, role, created_at, updated_at, deleted_at, version",
+    )
+    .bind(Uuid::new_v4())
+    .bind(name)
+    .bind(email)
+    .bind(password_hash)
+    .bind(role.to_string())
+    .fetch_one(executor)
+    .await
+}
+
+async fn update_profile<'e>(
+    executor: impl PgExecutor<'e>,
+    id: Uuid,
+    version: Option<i64>,
+    name: Option<&str>,
+    email: Option<&str>,
+) -> Result<Option<User>, sqlx::Error> {
+    let mut builder =
+        QueryBuilder::<Postgres>::new("UPDATE users SET updated_at = NOW(), version = version + 1");
+    if let Some(name) = name {
+        builder.push(", name = ").push_bind(name);
+    }
+    if let Some(email) = email {
+        builder.push(", email = ").push_bind(email);
+    }
+    builder.push(" WHERE id = ").push_bind(id);
+    builder.push(" AND deleted_at IS NULL");
+    if let Some(version) = version {
+        builder.push(" AND version = ").push_bind(version);
+    }
+    builder.push(" RETURNING id, name, email, password_hash, role, created_at, updated_at, deleted_at, version");
+
+    builder
+        .build_query_as::<User>()
+        .fetch_optional(executor)
+        .await
+}
+
+async fn update_password_hash<'e>(
+    executor: impl PgExecutor<'e>,
+    id: Uuid,
+    password_hash: &str,
+) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as::<_, User>(
+        "UPDATE users SET password_hash = $1, updated_at = NOW(), version = version + 1 WHERE id = $2 AND deleted_at IS NULL RETURNING id, name, email, password_hash, role, created_at, updated_at, deleted_at, version",
+    )
+    .bind(password_hash)
+    .bind(id)
+    .fetch_optional(executor)
+    .await
+}
+
+async fn update_role<'e>(
+    executor: impl PgExecutor<'e>,
+    id: Uuid,
+    version: Option<i64>,
+    role: &UserRole,
+) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as::<_, User>(
+        "UPDATE users SET role = $1, updated_at = NOW(), version = version + 1 WHERE id = $2 AND deleted_at IS NULL AND ($3::BIGINT IS NULL OR version = $3) RETURNING id, name, email, password_hash, role, created_at, updated_at, deleted_at, version",
+    )
+    .bind(role.to_string())
+    .bind(id)
+    .bind(version)
+    .fetch_optional(executor)
+    .await
+}
+
+async fn soft_delete<'e>(
+    executor: impl PgExecutor<'e>,
+    id: Uuid,
+    version: Option<i64>,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE users SET deleted_at = NOW(), version = version + 1 WHERE id = $1 AND deleted_at IS NULL AND ($2::BIGINT IS NULL OR version = $2)",
+    )
+    .bind(id)
+    .bind(version)
+    .execute(executor)
+    .await?;
+    Ok(result.rows_affected() > 0)
 }
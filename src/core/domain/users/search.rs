@@ -0,0 +1,136 @@
+use regex::Regex;
+
+use crate::core::domain::{
+    error::AppError,
+    users::model::{SearchHighlights, UserSearchHit, UserSearchResult},
+};
+
+/// Longest search text accepted
+pub const MAX_SEARCH_LENGTH: usize = 100;
+/// Most words of the search text that are matched
+pub const MAX_SEARCH_TERMS: usize = 8;
+
+/// A validated search, split into lowercase words
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserSearch {
+    pub text: String,
+    pub terms: Vec<String>,
+}
+
+impl UserSearch {
+    pub fn parse(q: &str) -> Result<Self, AppError> {
+        let text = q.trim();
+        if text.chars().count() > MAX_SEARCH_LENGTH {
+            return Err(AppError::Validation {
+                message: format!(
+                    "Search text must be at most {} characters",
+                    MAX_SEARCH_LENGTH
+                ),
+                errors: Vec::new(),
+            });
+        }
+
+        let mut terms: Vec<String> = Vec::new();
+        for term in text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|t| !t.is_empty())
+            .map(str::to_lowercase)
+        {
+            if !terms.contains(&term) && terms.len() < MAX_SEARCH_TERMS {
+                terms.push(term);
+            }
+        }
+        if terms.is_empty() {
+            return Err(AppError::Validation {
+                message: "Search text must contain at least one letter or digit".to_string(),
+                errors: Vec::new(),
+            });
+        }
+
+        Ok(Self {
+            text: text.to_string(),
+            terms,
+        })
+    }
+
+    /// `tsquery` matching every term as a word prefix; terms are alphanumeric
+    /// so they cannot inject query operators
+    pub fn prefix_query(&self) -> String {
+        self.terms
+            .iter()
+            .map(|term| format!("{}:*", term))
+            .collect::<Vec<_>>()
+            .join(" & ")
+    }
+
+    /// HTML escape `text` and wrap occurrences of the search terms in `<mark>`
+    pub fn highlight(&self, text: &str) -> String {
+        let pattern = self
+            .terms
+            .iter()
+            .map(|term| regex::escape(term))
+            .collect::<Vec<_>>()
+            .join("|");
+        let Ok(regex) = Regex::new(&format!("(?i){}", pattern)) else {
+            return escape_html(text);
+        };
+
+        let mut highlighted = String::with_capacity(text.len());
+        let mut last = 0;
+        for found in regex.find_iter(text) {
+            highlighted.push_str(&escape_html(&text[last..found.start()]));
+            highlighted.push_str("<mark>");
+            highlighted.push_str(&escape_html(found.as_str()));
+            highlighted.push_str("</mark>");
+            last = found.end();
+        }
+        highlighted.push_str(&escape_html(&text[last..]));
+        highlighted
+    }
+
+    pub fn result(&self, hit: UserSearchHit) -> UserSearchResult {
+        let highlights = SearchHighlights {
+            name: self.highlight(&hit.user.name),
+            email: self.highlight(&hit.user.email),
+        };
+        UserSearchResult {
+            user: hit.user.into(),
+            rank: hit.rank,
+            highlights,
+        }
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_search_terms() {
+        let search = UserSearch::parse("  John  d'Oe john ").unwrap();
+        assert_eq!(search.terms, vec!["john", "d", "oe"]);
+        assert_eq!(search.prefix_query(), "john:* & d:* & oe:*");
+
+        assert!(UserSearch::parse("  ").is_err());
+        assert!(UserSearch::parse("&|!:*").is_err());
+        assert!(UserSearch::parse(&"a".repeat(MAX_SEARCH_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn test_highlight_escapes_html() {
+        let search = UserSearch::parse("jo").unwrap();
+        assert_eq!(
+            search.highlight("<b>Jo</b> & jojo"),
+            "&lt;b&gt;<mark>Jo</mark>&lt;/b&gt; &amp; <mark>jo</mark><mark>jo</mark>"
+        );
+        assert_eq!(search.highlight("Alice"), "Alice");
+    }
+}
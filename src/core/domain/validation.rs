@@ -0,0 +1,31 @@
+use validator::Validate;
+
+use crate::core::domain::error::{AppError, Result};
+
+/// Payloads that are normalized and then validated before they are used,
+/// such as the request bodies read with
+/// [`ValidatedJson`](crate::core::rest::handler::body::ValidatedJson)
+pub trait ValidatedPayload: Validate {
+    /// Clean up user input, such as surrounding whitespace, before validation
+    fn normalize(&mut self) {}
+
+    /// Validate the payload and return an AppResult
+    fn validate_payload(&self) -> Result<()> {
+        self.validate().map_err(AppError::from)
+    }
+}
+
+/// Trim surrounding whitespace
+pub fn normalize_name(name: &mut String) {
+    *name = name.trim().to_string();
+}
+
+/// Trim and lowercase, so addresses compare equal however they were typed
+pub fn normalize_email(email: &mut String) {
+    *email = email.trim().to_lowercase();
+}
+
+/// Trim and lowercase a role name
+pub fn normalize_role(role: &mut String) {
+    *role = role.trim().to_lowercase();
+}
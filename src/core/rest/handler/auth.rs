@@ -1,41 +1,58 @@
-use actix_web::{get, post, web, HttpResponse, Responder};
-use serde::{Deserialize, Serialize};
+use actix_web::{
+    cookie::{time::Duration, Cookie, SameSite},
+    get,
+    http::header,
+    post, web, HttpRequest, HttpResponse, Responder,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use rand::RngCore;
+use serde::Deserialize;
 use sqlx::PgPool;
 use utoipa::ToSchema;
 use validator::Validate;
 
-use crate::core::{
-    domain::{
-        auth::service::AuthService,
-        error::{AppError, Result},
-        users::model::{CreateUserRequest, LoginRequest, UserRole},
+use crate::{
+    config::SessionConfig,
+    core::{
+        domain::{
+            auth::{jwt::JwtService, revocation::TokenRevocationList, service::AuthService},
+            error::{AppError, Result},
+            users::model::{CreateUserRequest, LoginRequest, UserRole},
+        },
+        rest::{
+            handler::{
+                body::ValidatedJson,
+                precondition::{entity_tag, last_modified},
+                response::{build_success_response, Empty},
+            },
+            middleware::{
+                auth::{AuthData, AuthExtractor},
+                conditional_get::{CachePolicy, ConditionalGet},
+            },
+        },
     },
-    rest::handler::response::build_success_response,
 };
 
 /// Register a new user
 #[utoipa::path(
-    post,
-    path = "/auth/register",
     tag = "auth",
     request_body = CreateUserRequest,
     responses(
-        (status = 201, description = "User registered successfully", body = AuthResponse),
-        (status = 400, description = "Invalid request payload"),
-        (status = 409, description = "Email already exists"),
-        (status = 500, description = "Internal server error")
+        (status = 201, description = "User registered successfully", body = AuthTokenResponse),
+        (status = 400, description = "Invalid request payload", body = ErrorResponse),
+        (status = 409, description = "Email already exists", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
     )
 )]
 #[post("/auth/register")]
 pub async fn register(
     pool: web::Data<PgPool>,
-    payload: web::Json<CreateUserRequest>,
+    jwt_service: web::Data<JwtService>,
+    payload: ValidatedJson<CreateUserRequest>,
 ) -> Result<impl Responder> {
     let payload = payload.into_inner();
-
-    // Create auth service with JWT secret from environment
-    let jwt_secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "your-secret-key".to_string()); // TODO: Move to config
-    let auth_service = AuthService::new(&jwt_secret);
+    let auth_service = AuthService::with_jwt_service(jwt_service.get_ref().clone());
 
     // Register user
     let response = auth_service.register_user(&pool, payload).await?;
@@ -47,85 +64,169 @@ pub async fn register(
 }
 
 /// User login
+///
+/// When cookie sessions are enabled the token is also set as an HttpOnly
+/// session cookie, together with a CSRF cookie that must be echoed in the
+/// CSRF header on state-changing requests.
 #[utoipa::path(
-    post,
-    path = "/auth/login",
     tag = "auth",
     request_body = LoginRequest,
     responses(
-        (status = 200, description = "Login successful", body = AuthResponse),
-        (status = 400, description = "Invalid request payload"),
-        (status = 401, description = "Invalid credentials"),
-        (status = 500, description = "Internal server error")
+        (status = 200, description = "Login successful", body = AuthTokenResponse),
+        (status = 400, description = "Invalid request payload", body = ErrorResponse),
+        (status = 401, description = "Invalid credentials", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
     )
 )]
 #[post("/auth/login")]
 pub async fn login(
     pool: web::Data<PgPool>,
-    payload: web::Json<LoginRequest>,
+    jwt_service: web::Data<JwtService>,
+    session: Option<web::Data<SessionConfig>>,
+    payload: ValidatedJson<LoginRequest>,
 ) -> Result<impl Responder> {
     let payload = payload.into_inner();
-
-    // Create auth service with JWT secret from environment
-    let jwt_secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "your-secret-key".to_string()); // TODO: Move to config
-    let auth_service = AuthService::new(&jwt_secret);
+    let auth_service = AuthService::with_jwt_service(jwt_service.get_ref().clone());
 
     // Login user
     let response = auth_service.login_user(&pool, payload).await?;
 
-    Ok(HttpResponse::Ok().json(build_success_response(response, "Login successful")))
+    let mut builder = HttpResponse::Ok();
+    if let Some(session) = session.filter(|session| session.enabled) {
+        let claims = jwt_service.verify_token(&response.token)?;
+        let max_age = Duration::seconds(claims.exp as i64 - Utc::now().timestamp());
+
+        let mut csrf_token = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut csrf_token);
+        let csrf_token = URL_SAFE_NO_PAD.encode(csrf_token);
+
+        builder
+            .cookie(session_cookie(
+                &session,
+                &session.cookie_name,
+                response.token.clone(),
+                true,
+                max_age,
+            ))
+            .cookie(session_cookie(
+                &session,
+                &session.csrf_cookie_name,
+                csrf_token,
+                false,
+                max_age,
+            ));
+    }
+
+    Ok(builder.json(build_success_response(response, "Login successful")))
 }
 
-/// Get current user profile (protected route)
+/// Build a session or CSRF cookie with the configured attributes
+fn session_cookie(
+    session: &SessionConfig,
+    name: &str,
+    value: String,
+    http_only: bool,
+    max_age: Duration,
+) -> Cookie<'static> {
+    let same_site = match session.same_site.to_lowercase().as_str() {
+        "strict" => SameSite::Strict,
+        "none" => SameSite::None,
+        _ => SameSite::Lax,
+    };
+
+    let mut cookie = Cookie::build(name.to_string(), value)
+        .path(session.path.clone())
+        .http_only(http_only)
+        .secure(session.secure)
+        .same_site(same_site)
+        .max_age(max_age)
+        .finish();
+    if let Some(domain) = &session.domain {
+        cookie.set_domain(domain.clone());
+    }
+    cookie
+}
+
+/// Log out: revoke the current token and clear the session cookies
 #[utoipa::path(
-    get,
-    path = "/auth/me",
     tag = "auth",
     responses(
-        (status = 200, description = "User profile retrieved successfully", body = PublicUser),
-        (status = 401, description = "Authentication required"),
-        (status = 500, description = "Internal server error")
+        (status = 200, description = "Logged out successfully", body = EmptyResponse),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Missing or invalid CSRF token", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
     ),
     security(
-        ("bearer_auth" = [])
+        ("bearer_auth" = []),
+        ("session_cookie" = [])
     )
 )]
-#[get("/auth/me")]
-pub async fn me(pool: web::Data<PgPool>, req: actix_web::HttpRequest) -> Result<impl Responder> {
-    // Extract Bearer token from Authorization header
-    let auth_header = req
-        .headers()
-        .get("Authorization")
-        .and_then(|h| h.to_str().ok())
-        .ok_or_else(|| AppError::Authentication {
-            message: "Authorization header required".to_string(),
-        })?;
-
-    if !auth_header.starts_with("Bearer ") {
-        return Err(AppError::Authentication {
-            message: "Invalid authorization format".to_string(),
-        });
+#[post("/auth/logout")]
+pub async fn logout(
+    req: HttpRequest,
+    _auth: AuthData,
+    jwt_service: web::Data<JwtService>,
+    revocations: web::Data<TokenRevocationList>,
+    session: Option<web::Data<SessionConfig>>,
+) -> Result<impl Responder> {
+    let (token, _) = AuthExtractor::extract_token(&req)?;
+    let claims = jwt_service.verify_token(&token)?;
+    revocations.revoke(&claims.jti, claims.exp);
+
+    let mut builder = HttpResponse::Ok();
+    if let Some(session) = session.filter(|session| session.enabled) {
+        for (name, http_only) in [
+            (&session.cookie_name, true),
+            (&session.csrf_cookie_name, false),
+        ] {
+            builder.cookie(session_cookie(
+                &session,
+                name,
+                String::new(),
+                http_only,
+                Duration::ZERO,
+            ));
+        }
     }
 
-    let token = &auth_header[7..]; // Remove "Bearer " prefix
-
-    // Create auth service with JWT secret from environment
-    let jwt_secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "your-secret-key".to_string()); // TODO: Move to config
-    let auth_service = AuthService::new(&jwt_secret);
+    Ok(builder.json(build_success_response(Empty {}, "Logged out successfully")))
+}
 
-    // Verify token and get user ID
-    let claims = auth_service.verify_token(token)?;
-    let user_id = uuid::Uuid::parse_str(&claims.sub).map_err(|_| AppError::Authentication {
-        message: "Invalid token".to_string(),
-    })?;
+/// Get current user profile (protected route)
+#[utoipa::path(
+    tag = "auth",
+    responses(
+        (status = 200, description = "User profile retrieved successfully", body = UserResponse,
+            headers(("ETag" = String, description = "Version of the user"))),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Service callers have no profile", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("session_cookie" = [])
+    )
+)]
+#[get("/auth/me", wrap = "ConditionalGet::new(CachePolicy::private(0))")]
+pub async fn me(
+    pool: web::Data<PgPool>,
+    jwt_service: web::Data<JwtService>,
+    auth: AuthData,
+) -> Result<impl Responder> {
+    let auth_service = AuthService::with_jwt_service(jwt_service.get_ref().clone());
 
     // Get user from database
-    let user = auth_service.get_user_by_id(&pool, user_id).await?;
+    let user = auth_service
+        .get_user_by_id(&pool, auth.require_user()?)
+        .await?;
 
-    Ok(HttpResponse::Ok().json(build_success_response(
-        user,
-        "User profile retrieved successfully",
-    )))
+    Ok(HttpResponse::Ok()
+        .insert_header(header::ETag(entity_tag(user.version)))
+        .insert_header(last_modified(user.updated_at))
+        .json(build_success_response(
+            user,
+            "User profile retrieved successfully",
+        )))
 }
 
 /// Password change request
@@ -145,59 +246,35 @@ pub struct ChangePasswordRequest {
 
 /// Change user password (protected route)
 #[utoipa::path(
-    post,
-    path = "/auth/change-password",
     tag = "auth",
     request_body = ChangePasswordRequest,
     responses(
-        (status = 200, description = "Password changed successfully"),
-        (status = 400, description = "Invalid request payload"),
-        (status = 401, description = "Authentication required or current password incorrect"),
-        (status = 500, description = "Internal server error")
+        (status = 200, description = "Password changed successfully", body = EmptyResponse),
+        (status = 400, description = "Invalid request payload", body = ErrorResponse),
+        (status = 401, description = "Authentication required or current password incorrect", body = ErrorResponse),
+        (status = 403, description = "Missing or invalid CSRF token, or a service caller", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
     ),
     security(
-        ("bearer_auth" = [])
+        ("bearer_auth" = []),
+        ("session_cookie" = [])
     )
 )]
 #[post("/auth/change-password")]
 pub async fn change_password(
     pool: web::Data<PgPool>,
-    req: actix_web::HttpRequest,
-    payload: web::Json<ChangePasswordRequest>,
+    jwt_service: web::Data<JwtService>,
+    auth: AuthData,
+    payload: ValidatedJson<ChangePasswordRequest>,
 ) -> Result<impl Responder> {
     let payload = payload.into_inner();
-    payload.validate()?;
-
-    // Extract Bearer token from Authorization header
-    let auth_header = req
-        .headers()
-        .get("Authorization")
-        .and_then(|h| h.to_str().ok())
-        .ok_or_else(|| AppError::Authentication {
-            message: "Authorization header required".to_string(),
-        })?;
 
-    if !auth_header.starts_with("Bearer ") {
-        return Err(AppError::Authentication {
-            message: "Invalid authorization format".to_string(),
-        });
-    }
-
-    let token = &auth_header[7..]; // Remove "Bearer " prefix
-
-    // Create auth service with JWT secret from environment
-    let jwt_secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "your-secret-key".to_string()); // TODO: Move to config
-    let auth_service = AuthService::new(&jwt_secret);
-
-    // Verify token and get user ID
-    let claims = auth_service.verify_token(token)?;
-    let user_id = uuid::Uuid::parse_str(&claims.sub).map_err(|_| AppError::Authentication {
-        message: "Invalid token".to_string(),
-    })?;
+    let auth_service = AuthService::with_jwt_service(jwt_service.get_ref().clone());
+    let user_id = auth.require_user()?;
 
     // Get current user
     let user = sqlx::query_as::<_, crate::core::domain::users::model::User>(
-        "SELECT * FROM users WHERE id = $1",
+        "SELECT * FROM users WHERE id = $1 AND deleted_at IS NULL",
     )
     .bind(user_id)
     .fetch_optional(&**pool)
@@ -206,6 +283,12 @@ pub async fn change_password(
         resource: "User".to_string(),
     })?;
 
+    if !user.has_password() {
+        return Err(AppError::Authentication {
+            message: "No password is set for this account".to_string(),
+        });
+    }
+
     // Verify current password
     if !auth_service.verify_password(&payload.current_password, &user.password_hash)? {
         return Err(AppError::Authentication {
@@ -217,18 +300,17 @@ pub async fn change_password(
     let new_password_hash = auth_service.hash_password(&payload.new_password)?;
 
     // Update password in database
-    sqlx::query("UPDATE users SET password_hash = $1, updated_at = $2 WHERE id = $3")
-        .bind(&new_password_hash)
-        .bind(chrono::Utc::now())
-        .bind(user_id)
-        .execute(&**pool)
-        .await?;
-
-    #[derive(Serialize)]
-    struct EmptyResponse {}
+    sqlx::query(
+        "UPDATE users SET password_hash = $1, updated_at = $2, version = version + 1 WHERE id = $3 AND deleted_at IS NULL",
+    )
+    .bind(&new_password_hash)
+    .bind(chrono::Utc::now())
+    .bind(user_id)
+    .execute(&**pool)
+    .await?;
 
     Ok(HttpResponse::Ok().json(build_success_response(
-        EmptyResponse {},
+        Empty {},
         "Password changed successfully",
     )))
 }
@@ -261,66 +343,49 @@ pub struct CreateUserWithRoleRequest {
     pub role: String,
 }
 
+/// Create a user with a role (admin only)
 #[utoipa::path(
-    post,
-    path = "/auth/admin/create-user",
     tag = "auth",
     request_body = CreateUserWithRoleRequest,
     responses(
-        (status = 201, description = "User created successfully", body = PublicUser),
-        (status = 400, description = "Invalid request payload"),
-        (status = 401, description = "Authentication required"),
-        (status = 403, description = "Admin access required"),
-        (status = 409, description = "Email already exists"),
-        (status = 500, description = "Internal server error")
+        (status = 201, description = "User created successfully", body = UserResponse),
+        (status = 400, description = "Invalid request payload", body = ErrorResponse),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Admin access required", body = ErrorResponse),
+        (status = 409, description = "Email already exists", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
     ),
     security(
-        ("bearer_auth" = [])
+        ("bearer_auth" = []),
+        ("session_cookie" = [])
     )
 )]
 #[post("/auth/admin/create-user")]
 pub async fn admin_create_user(
     pool: web::Data<PgPool>,
-    req: actix_web::HttpRequest,
-    payload: web::Json<CreateUserWithRoleRequest>,
+    jwt_service: web::Data<JwtService>,
+    auth: AuthData,
+    payload: ValidatedJson<CreateUserWithRoleRequest>,
 ) -> Result<impl Responder> {
     let payload = payload.into_inner();
-    payload.validate()?;
-
-    // Extract Bearer token from Authorization header
-    let auth_header = req
-        .headers()
-        .get("Authorization")
-        .and_then(|h| h.to_str().ok())
-        .ok_or_else(|| AppError::Authentication {
-            message: "Authorization header required".to_string(),
-        })?;
-
-    if !auth_header.starts_with("Bearer ") {
-        return Err(AppError::Authentication {
-            message: "Invalid authorization format".to_string(),
-        });
-    }
-
-    let token = &auth_header[7..]; // Remove "Bearer " prefix
 
-    // Create auth service with JWT secret from environment
-    let jwt_secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "your-secret-key".to_string()); // TODO: Move to config
-    let auth_service = AuthService::new(&jwt_secret);
-
-    // Verify token and check admin role
-    let claims = auth_service.verify_token(token)?;
-    if claims.role != UserRole::Admin.to_string() {
+    // Check admin role
+    if auth.role != UserRole::Admin {
         return Err(AppError::Authorization {
             message: "Admin access required".to_string(),
         });
     }
 
+    let auth_service = AuthService::with_jwt_service(jwt_service.get_ref().clone());
+
     // Parse and validate role
     let role = payload
         .role
         .parse::<UserRole>()
-        .map_err(|e| AppError::Validation { message: e })?;
+        .map_err(|e| AppError::Validation {
+            message: e,
+            errors: Vec::new(),
+        })?;
 
     // Create user request
     let create_request = CreateUserRequest {
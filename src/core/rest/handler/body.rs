@@ -0,0 +1,187 @@
+use actix_web::{
+    dev::Payload,
+    error::PayloadError,
+    http::header,
+    mime::Mime,
+    web::{self, Bytes},
+    FromRequest, HttpRequest,
+};
+use futures::{future::LocalBoxFuture, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+
+use crate::{
+    config::BodyLimitConfig,
+    core::{
+        domain::error::AppError,
+        rest::{
+            handler::extract::too_large, middleware::content_negotiation::MediaType,
+            validation::ValidatedPayload,
+        },
+    },
+};
+
+/// Largest request body accepted without a `BodyLimitConfig`, the same as
+/// `web::Json`'s default
+pub const MAX_BODY_SIZE: usize = 2 * 1024 * 1024;
+
+type ErrorHandler = Arc<dyn Fn(AppError, &HttpRequest) -> actix_web::Error + Send + Sync>;
+
+/// Settings of the [`Body`] extractor, registered with `app_data` like
+/// `web::JsonConfig`
+#[derive(Clone, Default)]
+pub struct BodyConfig {
+    error_handler: Option<ErrorHandler>,
+}
+
+impl BodyConfig {
+    /// Render extraction failures with `handler` instead of the standard
+    /// error envelope
+    pub fn error_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(AppError, &HttpRequest) -> actix_web::Error + Send + Sync + 'static,
+    {
+        self.error_handler = Some(Arc::new(handler));
+        self
+    }
+}
+
+/// Request body extractor used instead of `web::Json`.
+///
+/// Decodes JSON, MessagePack or CBOR according to the `Content-Type` header
+/// and rejects other types with `415 Unsupported Media Type` and bodies
+/// over the configured limit with `413 Payload Too Large`.
+#[derive(Debug)]
+pub struct Body<T>(pub T);
+
+impl<T> Body<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Body<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Body<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: DeserializeOwned + 'static> FromRequest for Body<T> {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let media_type = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<Mime>().ok())
+            .and_then(|mime| MediaType::from_mime(&mime));
+        let limit = req
+            .app_data::<web::Data<BodyLimitConfig>>()
+            .map_or(MAX_BODY_SIZE, |limits| limits.json_bytes);
+        let length = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<usize>().ok());
+        let payload = payload.take();
+        let error_handler = req
+            .app_data::<BodyConfig>()
+            .and_then(|config| config.error_handler.clone());
+        let req = req.clone();
+
+        Box::pin(async move {
+            let body = async {
+                let media_type = media_type.ok_or_else(|| AppError::UnsupportedMediaType {
+                    message: format!(
+                        "Request bodies must be {}, {} or {}",
+                        MediaType::Json.content_type(),
+                        MediaType::MsgPack.content_type(),
+                        MediaType::Cbor.content_type()
+                    ),
+                })?;
+
+                if length.is_some_and(|length| length > limit) {
+                    return Err(too_large(limit));
+                }
+                let body = read_body(payload, limit).await?;
+
+                media_type.decode(&body)
+            };
+
+            match (body.await, error_handler) {
+                (Ok(value), _) => Ok(Body(value)),
+                (Err(e), Some(handler)) => Err(handler(e, &req)),
+                (Err(e), None) => Err(e.into()),
+            }
+        })
+    }
+}
+
+/// [`Body`] that is normalized and validated before the handler runs.
+///
+/// Failed `validator` rules are answered with `400` and a field-level
+/// `errors` list, so handlers only see valid payloads.
+#[derive(Debug)]
+pub struct ValidatedJson<T>(pub T);
+
+impl<T> ValidatedJson<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for ValidatedJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: ValidatedPayload + DeserializeOwned + 'static> FromRequest for ValidatedJson<T> {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let body = Body::<T>::from_request(req, payload);
+
+        Box::pin(async move {
+            let mut value = body.await?.into_inner();
+            value.normalize();
+            value.validate_payload()?;
+            Ok(ValidatedJson(value))
+        })
+    }
+}
+
+/// Read a whole request body, failing with `413` once it exceeds `limit`
+pub async fn read_body<S>(mut payload: S, limit: usize) -> Result<Vec<u8>, AppError>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+{
+    let mut body = Vec::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|e| AppError::Validation {
+            message: format!("Invalid request body: {}", e),
+            errors: Vec::new(),
+        })?;
+        if body.len() + chunk.len() > limit {
+            return Err(too_large(limit));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
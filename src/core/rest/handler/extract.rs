@@ -0,0 +1,240 @@
+//! Extractor failures and unmatched routes as [`AppError`]s.
+//!
+//! actix answers malformed JSON bodies, path and query parameters, oversized
+//! bodies and unknown routes with plain text. [`configure`] installs error
+//! handlers that report them in the standard error envelope instead, with
+//! the offending field in `errors`.
+
+use actix_web::{
+    dev::ResourceDef,
+    error::{JsonPayloadError, PathError, QueryPayloadError, UrlencodedError},
+    http::{header, Method},
+    web, HttpRequest, HttpResponse, ResponseError,
+};
+use serde_json::{Map, Value};
+use std::fmt::Display;
+
+use crate::{
+    config::BodyLimitConfig,
+    core::domain::error::{AppError, FieldError, Result},
+};
+
+/// Install the extractor error handlers, body limits and the JSON 404 and
+/// 405 default service
+pub fn configure(limits: BodyLimitConfig) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        cfg.app_data(
+            web::JsonConfig::default()
+                .limit(limits.json_bytes)
+                .error_handler(|e, _| json_error(e).into()),
+        )
+        .app_data(web::PathConfig::default().error_handler(|e, req| path_error(e, req).into()))
+        .app_data(web::QueryConfig::default().error_handler(|e, _| query_error(e).into()))
+        .app_data(web::FormConfig::default().error_handler(|e, _| form_error(e).into()))
+        .app_data(web::Data::new(limits))
+        .default_service(web::to(unmatched));
+    }
+}
+
+/// Methods routed for each path pattern, registered with `app_data` for
+/// the `Allow` header of `405` responses
+#[derive(Default)]
+pub struct AllowedMethods(Vec<(ResourceDef, Method)>);
+
+impl AllowedMethods {
+    /// Record that `method` is routed for paths matching `pattern`
+    pub fn add(&mut self, pattern: &str, method: Method) {
+        self.0.push((ResourceDef::new(pattern), method));
+    }
+
+    /// Methods routed for `path`, in the order they were added
+    pub fn for_path(&self, path: &str) -> Vec<Method> {
+        let mut methods: Vec<Method> = Vec::new();
+        for (pattern, method) in &self.0 {
+            if pattern.is_match(path) && !methods.contains(method) {
+                methods.push(method.clone());
+            }
+        }
+        methods
+    }
+}
+
+/// A request body over `limit` bytes
+pub fn too_large(limit: usize) -> AppError {
+    AppError::PayloadTooLarge {
+        message: format!("Request body exceeds the limit of {} bytes", limit),
+    }
+}
+
+/// A JSON body that could not be deserialized; `path` locates the value that
+/// failed, such as `user.email`, and is empty for the body itself
+pub fn invalid_json(path: &str, error: &serde_json::Error) -> AppError {
+    let code = match error.classify() {
+        serde_json::error::Category::Syntax | serde_json::error::Category::Eof => "syntax",
+        _ => "invalid",
+    };
+    let mut params = Map::new();
+    let mut message = error.to_string();
+    if error.line() > 0 {
+        let position = format!(" at line {} column {}", error.line(), error.column());
+        message = message.trim_end_matches(&position).to_string();
+        params.insert("line".to_string(), Value::from(error.line()));
+        params.insert("column".to_string(), Value::from(error.column()));
+    }
+    invalid("request body", "body", path, code, &message, params)
+}
+
+/// A MessagePack or CBOR body that could not be deserialized
+pub fn invalid_body(path: &str, error: &impl Display) -> AppError {
+    invalid(
+        "request body",
+        "body",
+        path,
+        "invalid",
+        &error.to_string(),
+        Map::new(),
+    )
+}
+
+/// A validation error for one field. Serde reports missing and unknown
+/// fields against the enclosing value, so their name is appended to `path`.
+fn invalid(
+    what: &str,
+    root: &str,
+    path: &str,
+    code: &str,
+    message: &str,
+    params: Map<String, Value>,
+) -> AppError {
+    let named = |prefix: &str| {
+        message
+            .strip_prefix(prefix)
+            .and_then(|rest| rest.split('`').next())
+            .map(str::to_string)
+    };
+    let (field, code) = match (named("missing field `"), named("unknown field `")) {
+        (Some(name), _) => (name, "required"),
+        (_, Some(name)) => (name, "unknown_field"),
+        _ => (String::new(), code),
+    };
+    let field = match (path, field.as_str()) {
+        ("", "") => root.to_string(),
+        ("", field) => field.to_string(),
+        (path, "") => path.to_string(),
+        (path, field) => format!("{}.{}", path, field),
+    };
+
+    AppError::Validation {
+        message: format!("Invalid {}: {}: {}", what, field, message),
+        errors: vec![FieldError {
+            field,
+            code: code.to_string(),
+            message: message.to_string(),
+            params,
+        }],
+    }
+}
+
+fn json_error(error: JsonPayloadError) -> AppError {
+    match error {
+        JsonPayloadError::OverflowKnownLength { limit, .. }
+        | JsonPayloadError::Overflow { limit } => too_large(limit),
+        JsonPayloadError::ContentType => AppError::UnsupportedMediaType {
+            message: "Request bodies must be application/json".to_string(),
+        },
+        JsonPayloadError::Deserialize(e) => invalid_json("", &e),
+        e => AppError::Validation {
+            message: format!("Invalid request body: {}", e),
+            errors: Vec::new(),
+        },
+    }
+}
+
+/// A path parameter that could not be deserialized
+pub fn path_error(error: PathError, req: &HttpRequest) -> AppError {
+    let PathError::Deserialize(e) = error else {
+        return AppError::Validation {
+            message: format!("Invalid path parameter: {}", error),
+            errors: Vec::new(),
+        };
+    };
+    // A single parameter is named after its segment, as in `/users/{id}`
+    let mut segments = req.match_info().iter();
+    let root = match (segments.next(), segments.next()) {
+        (Some((name, _)), None) => name,
+        _ => "path",
+    };
+    invalid(
+        "path parameter",
+        root,
+        "",
+        "invalid",
+        &e.to_string(),
+        Map::new(),
+    )
+}
+
+/// A query string that could not be deserialized
+pub fn query_error(error: QueryPayloadError) -> AppError {
+    let QueryPayloadError::Deserialize(e) = error else {
+        return AppError::Validation {
+            message: format!("Invalid query string: {}", error),
+            errors: Vec::new(),
+        };
+    };
+    invalid(
+        "query string",
+        "query",
+        "",
+        "invalid",
+        &e.to_string(),
+        Map::new(),
+    )
+}
+
+fn form_error(error: UrlencodedError) -> AppError {
+    match error {
+        UrlencodedError::Overflow { limit, .. } => too_large(limit),
+        UrlencodedError::ContentType => AppError::UnsupportedMediaType {
+            message: "Request bodies must be application/x-www-form-urlencoded".to_string(),
+        },
+        UrlencodedError::Parse(e) => invalid(
+            "form body",
+            "body",
+            "",
+            "invalid",
+            &e.to_string(),
+            Map::new(),
+        ),
+        e => AppError::Validation {
+            message: format!("Invalid form body: {}", e),
+            errors: Vec::new(),
+        },
+    }
+}
+
+/// Default service: 405 with the routed methods in `Allow` when another
+/// method is routed for the path, else 404
+async fn unmatched(req: HttpRequest) -> Result<HttpResponse> {
+    if req.resource_map().has_resource(req.path()) {
+        let allowed = req
+            .app_data::<AllowedMethods>()
+            .map(|allowed| allowed.for_path(req.path()))
+            .unwrap_or_default()
+            .iter()
+            .map(Method::as_str)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut response = AppError::MethodNotAllowed {
+            message: format!("{} is not supported for {}", req.method(), req.path()),
+        }
+        .error_response();
+        if let Ok(allowed) = header::HeaderValue::from_str(&allowed) {
+            response.headers_mut().insert(header::ALLOW, allowed);
+        }
+        return Ok(response);
+    }
+    Err(AppError::NotFound {
+        resource: req.path().to_string(),
+    })
+}
@@ -0,0 +1,267 @@
+//! Sparse fieldsets and related-resource expansion.
+//!
+//! `fields=id,name` limits each returned resource to the listed fields and
+//! `expand=role` replaces a reference with the embedded resource. Both are
+//! checked against the resource's [`ResourceFields`], and responses without
+//! either parameter are serialized unchanged.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::IntoParams;
+
+use crate::{
+    core::{
+        domain::{
+            error::{AppError, Result},
+            users::model::{RoleDetails, UserRole},
+        },
+        rest::middleware::request_id,
+    },
+    pkg::i18n,
+};
+
+/// Fields and expansions a resource supports
+#[derive(Debug)]
+pub struct ResourceFields {
+    /// Resource name used in error messages
+    pub resource: &'static str,
+    pub fields: &'static [&'static str],
+    /// Fields that can be replaced by the resource they refer to
+    pub expand: &'static [&'static str],
+}
+
+/// Users as returned by `GET /users` and `GET /users/{id}`
+pub const USER_FIELDS: ResourceFields = ResourceFields {
+    resource: "users",
+    fields: &[
+        "id",
+        "name",
+        "email",
+        "role",
+        "created_at",
+        "updated_at",
+        "deleted_at",
+        "version",
+    ],
+    expand: &["role"],
+};
+
+/// Results of `GET /users/search`
+pub const USER_SEARCH_FIELDS: ResourceFields = ResourceFields {
+    resource: "search results",
+    fields: &[
+        "id",
+        "name",
+        "email",
+        "role",
+        "created_at",
+        "updated_at",
+        "version",
+        "rank",
+        "highlights",
+    ],
+    expand: &["role"],
+};
+
+/// `fields` and `expand` query parameters
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FieldsQuery {
+    /// Comma separated fields to return; all fields when absent
+    #[param(example = "id,name")]
+    pub fields: Option<String>,
+    /// Comma separated fields to replace by the resource they refer to
+    #[param(example = "role")]
+    pub expand: Option<String>,
+}
+
+/// Validated `fields` and `expand` of a request
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selection {
+    /// Fields to return; `None` returns all of them
+    pub fields: Option<Vec<&'static str>>,
+    pub expand: Vec<&'static str>,
+}
+
+/// A resource as requested: unchanged, or projected and expanded
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Selected<T> {
+    Full(T),
+    Sparse(Value),
+}
+
+impl Selection {
+    /// Check the requested fields and expansions against `resource`.
+    ///
+    /// Expanded fields are returned even when `fields` does not list them.
+    pub fn parse(query: &FieldsQuery, resource: &ResourceFields) -> Result<Self> {
+        let fields = query
+            .fields
+            .as_deref()
+            .map(|fields| parse_list(fields, resource.fields, "field", resource))
+            .transpose()?;
+        let expand = query
+            .expand
+            .as_deref()
+            .map(|expand| parse_list(expand, resource.expand, "expansion", resource))
+            .transpose()?
+            .unwrap_or_default();
+
+        let fields = fields.map(|mut fields| {
+            for name in &expand {
+                if !fields.contains(name) {
+                    fields.push(name);
+                }
+            }
+            fields
+        });
+        Ok(Self { fields, expand })
+    }
+
+    /// Whether the full resource was requested
+    pub fn is_full(&self) -> bool {
+        self.fields.is_none() && self.expand.is_empty()
+    }
+
+    /// Column names to load from the database, if not all of them
+    pub fn columns(&self) -> Option<Vec<String>> {
+        self.fields
+            .as_ref()
+            .map(|fields| fields.iter().map(|f| f.to_string()).collect())
+    }
+
+    /// Apply the selection to a resource
+    pub fn select<T: Serialize>(&self, item: T) -> Result<Selected<T>> {
+        if self.is_full() {
+            return Ok(Selected::Full(item));
+        }
+        let mut value = serde_json::to_value(&item).map_err(|_| AppError::Internal)?;
+        if let Value::Object(object) = &mut value {
+            if let Some(fields) = &self.fields {
+                object.retain(|key, _| fields.contains(&key.as_str()));
+            }
+            for name in &self.expand {
+                if let Some(field) = object.get_mut(*name) {
+                    *field = expand(name, field)?;
+                }
+            }
+        }
+        Ok(Selected::Sparse(value))
+    }
+}
+
+fn parse_list(
+    list: &str,
+    allowed: &'static [&'static str],
+    kind: &str,
+    resource: &ResourceFields,
+) -> Result<Vec<&'static str>> {
+    let mut names = Vec::new();
+    for name in list.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        let name = allowed
+            .iter()
+            .copied()
+            .find(|allowed| *allowed == name)
+            .ok_or_else(|| AppError::Validation {
+                message: match allowed.is_empty() {
+                    true => format!("Unknown {} '{}' for {}", kind, name, resource.resource),
+                    false => format!(
+                        "Unknown {} '{}' for {}; expected one of {}",
+                        kind,
+                        name,
+                        resource.resource,
+                        allowed.join(", ")
+                    ),
+                },
+                errors: Vec::new(),
+            })?;
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    Ok(names)
+}
+
+/// The embedded resource for a reference
+fn expand(name: &str, reference: &Value) -> Result<Value> {
+    match name {
+        "role" => {
+            let role = reference
+                .as_str()
+                .and_then(|role| role.parse::<UserRole>().ok())
+                .unwrap_or_default();
+            serde_json::to_value(role_details(role)).map_err(|_| AppError::Internal)
+        }
+        _ => Ok(reference.clone()),
+    }
+}
+
+/// Details of `role` with the description in the locale of the current
+/// request
+fn role_details(role: UserRole) -> RoleDetails {
+    request_id::mark_localized();
+    let description = request_id::current()
+        .filter(|request| request.locale != i18n::SOURCE_LOCALE)
+        .and_then(|request| {
+            i18n::lookup(
+                &request.locale,
+                &format!("role-{}", role),
+                &Default::default(),
+            )
+        });
+    let mut details = RoleDetails::from(role);
+    if let Some(description) = description {
+        details.description = description;
+    }
+    details
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn query(fields: Option<&str>, expand: Option<&str>) -> FieldsQuery {
+        FieldsQuery {
+            fields: fields.map(str::to_string),
+            expand: expand.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_parse_selection() {
+        let selection = Selection::parse(&query(None, None), &USER_FIELDS).unwrap();
+        assert!(selection.is_full());
+
+        let selection =
+            Selection::parse(&query(Some("name, id,name"), Some("role")), &USER_FIELDS).unwrap();
+        assert_eq!(selection.fields, Some(vec!["name", "id", "role"]));
+        assert_eq!(selection.expand, vec!["role"]);
+
+        let error = Selection::parse(&query(Some("id,password_hash"), None), &USER_FIELDS)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Unknown field 'password_hash' for users"));
+        assert!(Selection::parse(&query(None, Some("email")), &USER_FIELDS).is_err());
+    }
+
+    #[test]
+    fn test_select() {
+        let user = json!({ "id": 1, "name": "Ada", "email": "ada@example.com", "role": "admin" });
+
+        let selection = Selection::parse(&query(Some("name,id"), None), &USER_FIELDS).unwrap();
+        let selected = serde_json::to_value(selection.select(&user).unwrap()).unwrap();
+        assert_eq!(selected, json!({ "id": 1, "name": "Ada" }));
+
+        let selection = Selection::parse(&query(Some("id"), Some("role")), &USER_FIELDS).unwrap();
+        let selected = serde_json::to_value(selection.select(&user).unwrap()).unwrap();
+        assert_eq!(
+            selected,
+            json!({
+                "id": 1,
+                "role": { "name": "admin", "description": "Administrator with full permissions" }
+            })
+        );
+    }
+}
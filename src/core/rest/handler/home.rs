@@ -1,6 +1,14 @@
 use actix_web::{get, HttpResponse, Responder};
 
+/// Check that the service is up
+#[utoipa::path(
+    tag = "home",
+    responses(
+        (status = 200, description = "Service is up", body = String,
+            content_type = "application/json", example = json!("{}"))
+    )
+)]
 #[get("/")]
-async fn home() -> impl Responder {
+pub async fn home() -> impl Responder {
     HttpResponse::Ok().json(String::from(r#"{}"#))
 }
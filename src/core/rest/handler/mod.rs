@@ -1,6 +1,13 @@
+pub mod body;
+pub mod extract;
+pub mod fields;
+pub mod precondition;
 pub mod response;
 pub mod validator;
 
 pub mod auth;
 pub mod home;
+pub mod oauth;
+pub mod scim;
 pub mod users;
+pub mod v2;
@@ -0,0 +1,187 @@
+use actix_web::{http::header, post, web, HttpRequest, HttpResponse, Responder};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    config::IntrospectionConfig,
+    core::domain::{
+        auth::{jwt::JwtService, revocation::TokenRevocationList},
+        error::{AppError, Result},
+        users::{model::UserRole, repository::UserRepository},
+    },
+    pkg::crypto::constant_time_eq,
+};
+
+/// Token introspection request (RFC 7662, section 2.1)
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct IntrospectionRequest {
+    /// The token to introspect
+    pub token: String,
+    /// Optional hint about the type of the token
+    #[schema(example = "access_token")]
+    pub token_type_hint: Option<String>,
+}
+
+/// Token introspection response (RFC 7662, section 2.2)
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct IntrospectionResponse {
+    /// Whether the token is currently active
+    pub active: bool,
+    /// Space separated list of scopes granted to the token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "user moderator")]
+    pub scope: Option<String>,
+    /// Client that requested the introspection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    /// Email address of the token owner
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// Type of the token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Bearer")]
+    pub token_type: Option<String>,
+    /// Expiration time (seconds since the epoch)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<usize>,
+    /// Issue time (seconds since the epoch)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<usize>,
+    /// Subject of the token (user ID)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    /// Token identifier
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    /// Role of the token owner
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "user")]
+    pub role: Option<String>,
+}
+
+/// Introspect an access token
+#[utoipa::path(
+    tag = "auth",
+    request_body(content = IntrospectionRequest, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Token introspected", body = IntrospectionResponse),
+        (status = 401, description = "Client authentication failed"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("basic_auth" = []),
+        ("api_key" = [])
+    )
+)]
+#[post("/oauth/introspect")]
+pub async fn introspect(
+    req: HttpRequest,
+    config: web::Data<IntrospectionConfig>,
+    jwt_service: web::Data<JwtService>,
+    revocations: web::Data<TokenRevocationList>,
+    pool: Option<web::Data<PgPool>>,
+    form: web::Form<IntrospectionRequest>,
+) -> Result<impl Responder> {
+    let client_id = authenticate_client(&req, &config)?;
+
+    let claims = match jwt_service.verify_token(&form.token) {
+        Ok(claims) if !revocations.is_revoked(&claims.jti) => Some(claims),
+        _ => None,
+    };
+    // Tokens whose subject is not a user id, or of deleted users, are no
+    // longer active
+    let claims = match claims.map(|claims| (Uuid::parse_str(&claims.sub), claims)) {
+        Some((Ok(user_id), claims)) => match pool {
+            Some(pool) => UserRepository { pool: &pool }
+                .is_active(user_id)
+                .await?
+                .then_some(claims),
+            None => Some(claims),
+        },
+        Some((Err(_), _)) | None => None,
+    };
+
+    // RFC 7662 section 4: the answer depends on the token in the form body,
+    // which shared caches do not key on, so it must not be stored at all
+    let mut response = HttpResponse::Ok();
+    response
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .insert_header((header::PRAGMA, "no-cache"));
+
+    let claims = match claims {
+        Some(claims) => claims,
+        // Unknown, expired, revoked and orphaned tokens are all reported the
+        // same way
+        None => return Ok(response.json(IntrospectionResponse::default())),
+    };
+
+    let role = claims.role.parse::<UserRole>().ok();
+    Ok(response.json(IntrospectionResponse {
+        active: true,
+        scope: role.as_ref().map(scope_for_role),
+        client_id: Some(client_id),
+        username: Some(claims.email),
+        token_type: Some("Bearer".to_string()),
+        exp: Some(claims.exp),
+        iat: Some(claims.iat),
+        sub: Some(claims.sub),
+        jti: Some(claims.jti).filter(|jti| !jti.is_empty()),
+        role: role.map(|role| role.to_string()),
+    }))
+}
+
+/// Authenticate the calling client with an API key or HTTP Basic client credentials
+fn authenticate_client(req: &HttpRequest, config: &IntrospectionConfig) -> Result<String> {
+    if let Some(api_key) = req.headers().get("X-API-Key").and_then(|h| h.to_str().ok()) {
+        if config
+            .api_keys
+            .iter()
+            .any(|key| constant_time_eq(key.as_bytes(), api_key.as_bytes()))
+        {
+            return Ok("api_key".to_string());
+        }
+        return Err(AppError::Authentication {
+            message: "Invalid API key".to_string(),
+        });
+    }
+
+    let credentials = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Basic "))
+        .and_then(|encoded| STANDARD.decode(encoded).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .ok_or_else(|| AppError::Authentication {
+            message: "Client authentication required".to_string(),
+        })?;
+
+    let (client_id, client_secret) =
+        credentials
+            .split_once(':')
+            .ok_or_else(|| AppError::Authentication {
+                message: "Invalid client credentials".to_string(),
+            })?;
+
+    match config.clients.get(client_id) {
+        Some(secret) if constant_time_eq(secret.as_bytes(), client_secret.as_bytes()) => {
+            Ok(client_id.to_string())
+        }
+        _ => Err(AppError::Authentication {
+            message: "Invalid client credentials".to_string(),
+        }),
+    }
+}
+
+/// Scopes implied by a role, following the hierarchy used by `AuthExtractor::check_role`
+fn scope_for_role(role: &UserRole) -> String {
+    match role {
+        UserRole::User => "user",
+        UserRole::Moderator => "user moderator",
+        UserRole::Admin => "user moderator admin",
+    }
+    .to_string()
+}
@@ -0,0 +1,119 @@
+use actix_web::{
+    http::header::{self, EntityTag, Header, IfMatch, LastModified},
+    HttpRequest,
+};
+use chrono::{DateTime, Utc};
+use std::time::SystemTime;
+
+use crate::{
+    config::ConcurrencyConfig,
+    core::domain::error::{AppError, Result},
+};
+
+/// Entity tag of a resource at `version`. It is weak because the bytes
+/// sent for one version still vary with the media type, language and
+/// fields of the request.
+pub fn entity_tag(version: i64) -> EntityTag {
+    EntityTag::new_weak(version.to_string())
+}
+
+/// `Last-Modified` header for a resource updated at `updated_at`
+pub fn last_modified(updated_at: DateTime<Utc>) -> LastModified {
+    LastModified(SystemTime::from(updated_at).into())
+}
+
+/// Check the request's `If-Match` header against the current `version` of
+/// a resource. Tags are compared by the version they carry, weak or not,
+/// since updates depend on the version rather than on a representation.
+///
+/// Returns the version a compare-and-swap update must expect, or `None`
+/// when no precondition was sent and `If-Match` is not required.
+pub fn expected_version(
+    req: &HttpRequest,
+    version: i64,
+    config: Option<&ConcurrencyConfig>,
+) -> Result<Option<i64>> {
+    if !req.headers().contains_key(header::IF_MATCH) {
+        if config.is_some_and(|config| config.if_match_required) {
+            return Err(AppError::PreconditionRequired {
+                message: "If-Match header is required".to_string(),
+            });
+        }
+        return Ok(None);
+    }
+
+    let matches = match IfMatch::parse(req) {
+        Ok(IfMatch::Any) => true,
+        Ok(IfMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&entity_tag(version))),
+        Err(_) => {
+            return Err(AppError::Validation {
+                message: "Invalid If-Match header".to_string(),
+                errors: Vec::new(),
+            })
+        }
+    };
+    if !matches {
+        return Err(precondition_failed());
+    }
+    Ok(Some(version))
+}
+
+/// Error for a resource that no longer matches the client's `If-Match`
+pub fn precondition_failed() -> AppError {
+    AppError::PreconditionFailed {
+        message: "Resource has been modified; fetch it again and retry".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn required() -> ConcurrencyConfig {
+        ConcurrencyConfig {
+            if_match_required: true,
+        }
+    }
+
+    #[test]
+    fn test_if_match() {
+        let req = TestRequest::default()
+            .insert_header((header::IF_MATCH, "\"1\", \"3\""))
+            .to_http_request();
+        assert_eq!(expected_version(&req, 3, None).unwrap(), Some(3));
+        assert!(matches!(
+            expected_version(&req, 2, None),
+            Err(AppError::PreconditionFailed { .. })
+        ));
+
+        let req = TestRequest::default()
+            .insert_header((header::IF_MATCH, "*"))
+            .to_http_request();
+        assert_eq!(
+            expected_version(&req, 7, Some(&required())).unwrap(),
+            Some(7)
+        );
+
+        // The version is compared whether the tag is weak or strong
+        let req = TestRequest::default()
+            .insert_header((header::IF_MATCH, "W/\"3\""))
+            .to_http_request();
+        assert_eq!(expected_version(&req, 3, None).unwrap(), Some(3));
+        assert!(expected_version(&req, 4, None).is_err());
+    }
+
+    #[test]
+    fn test_if_match_required() {
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(expected_version(&req, 1, None).unwrap(), None);
+        assert_eq!(
+            expected_version(&req, 1, Some(&ConcurrencyConfig::default())).unwrap(),
+            None
+        );
+        assert!(matches!(
+            expected_version(&req, 1, Some(&required())),
+            Err(AppError::PreconditionRequired { .. })
+        ));
+    }
+}
@@ -1,25 +1,36 @@
-use serde::Serialize;
-use utoipa::ToSchema;
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{
+    core::{
+        domain::{
+            error::{AppError, ErrorCode, FieldError},
+            users::{
+                bulk::{BulkMode, BulkOutcome},
+                import::ImportReport,
+                model::{AuthResponse, PublicUser, User, UserSearchResult},
+            },
+        },
+        rest::middleware::request_id,
+    },
+    pkg::i18n::{self, LanguageIdentifier},
+};
 
 // Constants for meta information
 const APP_NAME: &str = "afaf-rest-rust";
 const VERSION: &str = "1.0.0";
 
-/// Error response structure
-#[derive(Debug, Serialize, ToSchema)]
-pub struct ErrorResponse {
-    /// Metadata about the API
-    pub meta: Meta,
-    /// Error code identifier
-    #[schema(example = "not_found")]
-    pub error: String,
-    /// Detailed error message
-    #[schema(example = "The requested resource was not found")]
-    pub message: String,
-}
+/// Page size used when the client does not ask for one
+pub const DEFAULT_PAGE_SIZE: i64 = 20;
+/// Largest page size the server will return
+pub const MAX_PAGE_SIZE: i64 = 100;
 
 /// API metadata structure
-#[derive(Debug, Serialize, ToSchema)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct Meta {
     /// API version
     #[schema(example = "1.0.0")]
@@ -29,20 +40,192 @@ pub struct Meta {
     pub app: String,
 }
 
-// Helper function to build the error response
-pub fn build_error_response(error_code: &str, message: &str) -> ErrorResponse {
-    ErrorResponse {
-        meta: Meta {
+impl Default for Meta {
+    fn default() -> Self {
+        Meta {
             version: VERSION.to_string(),
             app: APP_NAME.to_string(),
-        },
-        error: error_code.to_string(),
-        message: message.to_string(),
+        }
+    }
+}
+
+/// Error response body shared by every endpoint
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ErrorResponse {
+    /// Metadata about the API
+    pub meta: Meta,
+    /// Error code identifier
+    #[schema(example = "not_found")]
+    pub error: ErrorCode,
+    /// Detailed error message
+    #[schema(example = "Resource not found")]
+    pub message: String,
+    /// The English message, when `message` is the generic message for the
+    /// error code because the requested locale has no translation of it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Cannot sort by nickname")]
+    pub detail: Option<String>,
+    /// Id of the request, also sent in the `X-Request-Id` header
+    #[schema(example = "8d6f0b2e-3c1a-4f7e-9b9a-2f1c6d4e5a7b")]
+    pub request_id: String,
+    /// Field-level failures of a `validation_error`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+impl ErrorResponse {
+    /// Status code and body of the response for `error`, with the id and
+    /// locale of the current request
+    pub fn from_error(error: &AppError) -> (StatusCode, Self) {
+        let (code, message, status) = error.parts();
+        let request = request_id::current();
+
+        let mut body = ErrorResponse {
+            meta: Meta::default(),
+            error: code,
+            message: message.to_string(),
+            detail: None,
+            request_id: request
+                .as_ref()
+                .map(|request| request.id.clone())
+                .unwrap_or_else(|| Uuid::new_v4().to_string()),
+            errors: match error {
+                AppError::Validation { errors, .. } => errors.clone(),
+                _ => Vec::new(),
+            },
+        };
+        if let Some(request) = request.filter(|request| request.locale != i18n::SOURCE_LOCALE) {
+            body.localize(&request.locale);
+        }
+        (status, body)
+    }
+
+    /// Replace the English messages by catalog messages in `locale`. A
+    /// message without a translation of its own becomes the message for the
+    /// error code, and the English message moves to `detail`; without either
+    /// the English message stays.
+    pub fn localize(&mut self, locale: &LanguageIdentifier) {
+        let key = i18n::message_key(&self.message);
+        if let Some(message) = i18n::lookup(locale, &key, &Map::new()) {
+            self.message = message;
+        } else if let Some(message) = i18n::lookup(locale, self.error.as_str(), &Map::new()) {
+            self.detail = Some(std::mem::replace(&mut self.message, message));
+        }
+        for error in &mut self.errors {
+            localize_field_error(error, locale);
+        }
+    }
+}
+
+/// Replace the message of `error` by the catalog message for its rule in
+/// `locale`, with the field and the rule's parameters interpolated
+fn localize_field_error(error: &mut FieldError, locale: &LanguageIdentifier) {
+    let has = |param: &str| error.params.contains_key(param);
+    let variant = match (has("min"), has("max"), has("equal")) {
+        (_, _, true) => "-equal",
+        (true, true, _) => "-min-max",
+        (true, false, _) => "-min",
+        (false, true, _) => "-max",
+        _ => "",
+    };
+    let key = format!("validation-{}{}", error.code, variant);
+    let mut args = error.params.clone();
+    args.insert("field".to_string(), Value::String(error.field.clone()));
+    if let Some(message) = i18n::lookup(locale, &key, &args) {
+        error.message = message;
+    }
+}
+
+/// RFC 7807 problem details, sent instead of [`ErrorResponse`] to clients
+/// that accept `application/problem+json`
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProblemDetails {
+    /// URI identifying the kind of problem
+    #[serde(rename = "type")]
+    #[schema(example = "urn:afaf-rest-rust:error:not_found")]
+    pub problem_type: String,
+    /// Summary of the kind of problem
+    #[schema(example = "Not Found")]
+    pub title: String,
+    #[schema(example = 404)]
+    pub status: u16,
+    /// Explanation of this occurrence of the problem
+    #[schema(example = "Resource not found")]
+    pub detail: String,
+    /// Path of the request that failed
+    #[schema(example = "/users/123e4567-e89b-12d3-a456-426614174000")]
+    pub instance: String,
+    /// Same as `error` in [`ErrorResponse`]
+    pub code: ErrorCode,
+    pub request_id: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+impl ProblemDetails {
+    /// `error` as problem details for the current request
+    pub fn from_error(error: &AppError) -> Self {
+        let (status, body) = ErrorResponse::from_error(error);
+        // A localized generic message summarizes the problem better than the
+        // English reason phrase, leaving the English message as the detail
+        let (title, detail) = match body.detail {
+            Some(detail) => (body.message, detail),
+            None => (
+                status.canonical_reason().unwrap_or_default().to_string(),
+                body.message,
+            ),
+        };
+        ProblemDetails {
+            problem_type: body.error.problem_type(),
+            title,
+            status: status.as_u16(),
+            detail,
+            instance: request_id::current()
+                .map(|request| request.path)
+                .unwrap_or_default(),
+            code: body.error,
+            request_id: body.request_id,
+            errors: body.errors,
+        }
+    }
+}
+
+impl ResponseError for AppError {
+    fn error_response(&self) -> HttpResponse {
+        let (_, _, status) = self.parts();
+        if status.is_server_error() {
+            log::error!("{}: {:?}", status, self);
+        }
+
+        let Some(request) = request_id::current() else {
+            let (status, body) = ErrorResponse::from_error(self);
+            return HttpResponse::build(status).json(body);
+        };
+        request_id::mark_localized();
+
+        if request.problem_json {
+            let problem = ProblemDetails::from_error(self);
+            return HttpResponse::build(StatusCode::from_u16(problem.status).unwrap_or_default())
+                .content_type("application/problem+json")
+                .json(problem);
+        }
+        let (status, body) = ErrorResponse::from_error(self);
+        HttpResponse::build(status).json(body)
     }
 }
 
 /// Success response structure
 #[derive(Debug, Serialize, ToSchema)]
+#[aliases(
+    UserResponse = Response<PublicUser>,
+    StoredUserResponse = Response<User>,
+    UserPageResponse = Response<UserPage>,
+    UserSearchPageResponse = Response<UserSearchPage>,
+    AuthTokenResponse = Response<AuthResponse>,
+    BulkResultResponse = Response<BulkResponse>,
+    ImportReportResponse = Response<ImportReport>,
+    EmptyResponse = Response<Empty>,
+)]
 pub struct Response<T> {
     /// Metadata about the API
     pub meta: Meta,
@@ -56,11 +239,192 @@ pub struct Response<T> {
 // Helper function to build the success response
 pub fn build_success_response<T: Serialize>(data: T, message: &str) -> Response<T> {
     Response {
-        meta: Meta {
-            version: VERSION.to_string(),
-            app: APP_NAME.to_string(),
-        },
+        meta: Meta::default(),
         data,
         message: message.to_string(),
     }
 }
+
+/// Data of responses that only carry a message
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Empty {}
+
+/// Outcome of one bulk operation, in request order
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BulkItemResult {
+    /// Position of the operation in the request
+    pub index: usize,
+    /// HTTP status the operation would have had as a single request
+    #[schema(example = 201)]
+    pub status: u16,
+    /// The created or updated user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<PublicUser>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorResponse>,
+}
+
+/// Results of a bulk request
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BulkResponse {
+    pub mode: BulkMode,
+    /// Whether any changes were saved
+    pub committed: bool,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<BulkItemResult>,
+}
+
+impl From<BulkOutcome> for BulkResponse {
+    fn from(outcome: BulkOutcome) -> Self {
+        let results: Vec<BulkItemResult> = outcome
+            .results
+            .into_iter()
+            .enumerate()
+            .map(|(index, result)| match result {
+                Ok((status, data)) => BulkItemResult {
+                    index,
+                    status: status.as_u16(),
+                    data,
+                    error: None,
+                },
+                Err(e) => {
+                    let (status, body) = ErrorResponse::from_error(&e);
+                    if status.is_server_error() {
+                        log::error!("Bulk operation {}: {}: {:?}", index, status, e);
+                    }
+                    BulkItemResult {
+                        index,
+                        status: status.as_u16(),
+                        data: None,
+                        error: Some(body),
+                    }
+                }
+            })
+            .collect();
+        let succeeded = results.iter().filter(|r| r.error.is_none()).count();
+        BulkResponse {
+            mode: outcome.mode,
+            committed: outcome.committed,
+            succeeded,
+            failed: results.len() - succeeded,
+            results,
+        }
+    }
+}
+
+/// Pagination query parameters shared by list endpoints
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageQuery {
+    /// Maximum number of items to return (capped by the server)
+    #[param(example = 20)]
+    pub limit: Option<i64>,
+    /// Number of items to skip; ignored when `cursor` is set
+    #[param(example = 0)]
+    pub offset: Option<i64>,
+    /// Opaque cursor from a previous page's `next_cursor`
+    pub cursor: Option<String>,
+}
+
+impl PageQuery {
+    /// Requested page size, clamped to `1..=MAX_PAGE_SIZE`
+    pub fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE)
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset.unwrap_or(0).max(0)
+    }
+}
+
+/// One page of a list, with the metadata needed to fetch the next one
+#[derive(Debug, Serialize, ToSchema)]
+#[aliases(UserPage = Page<PublicUser>, UserSearchPage = Page<UserSearchResult>)]
+pub struct Page<T> {
+    /// Items on this page
+    pub items: Vec<T>,
+    /// Total number of items across all pages
+    #[schema(example = 42)]
+    pub total: i64,
+    /// Page size that was applied
+    #[schema(example = 20)]
+    pub limit: i64,
+    /// Offset of the first item; absent for cursor pages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 0)]
+    pub offset: Option<i64>,
+    /// Cursor for the next page; absent on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// RFC 8288 `Link` header value for the `first`, `prev` and `next` pages.
+    ///
+    /// `path` and `query` are those of the current request; pagination
+    /// parameters are replaced and everything else is kept.
+    pub fn link_header(&self, path: &str, query: &str) -> String {
+        let base: Vec<&str> = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .filter(|pair| {
+                let key = pair.split('=').next().unwrap_or_default();
+                !matches!(key, "limit" | "offset" | "cursor")
+            })
+            .collect();
+        let link = |params: String, rel: &str| {
+            let mut query = base.clone();
+            query.push(&params);
+            format!("<{}?{}>; rel=\"{}\"", path, query.join("&"), rel)
+        };
+
+        let mut links = vec![link(format!("limit={}", self.limit), "first")];
+        match self.offset {
+            Some(offset) => {
+                if offset > 0 {
+                    let prev = (offset - self.limit).max(0);
+                    links.push(link(
+                        format!("limit={}&offset={}", self.limit, prev),
+                        "prev",
+                    ));
+                }
+                if offset + self.limit < self.total {
+                    let next = offset + self.limit;
+                    links.push(link(
+                        format!("limit={}&offset={}", self.limit, next),
+                        "next",
+                    ));
+                }
+            }
+            None => {
+                if let Some(cursor) = &self.next_cursor {
+                    links.push(link(
+                        format!("limit={}&cursor={}", self.limit, cursor),
+                        "next",
+                    ));
+                }
+            }
+        }
+        links.join(", ")
+    }
+}
+
+/// Encode a sort key as an opaque, URL safe cursor
+pub fn encode_cursor<K: Serialize>(key: &K) -> String {
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(key).unwrap_or_default())
+}
+
+/// Decode a cursor produced by [`encode_cursor`]
+pub fn decode_cursor<K: DeserializeOwned>(cursor: &str) -> Result<K, AppError> {
+    URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .ok_or_else(|| AppError::Validation {
+            message: "Invalid cursor".to_string(),
+            errors: Vec::new(),
+        })
+}
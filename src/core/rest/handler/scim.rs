@@ -0,0 +1,1057 @@
+use actix_web::{
+    delete, get,
+    http::{header, StatusCode},
+    patch, post, put, web, HttpRequest, HttpResponse,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+use validator::ValidateEmail;
+
+use crate::{
+    config::ScimConfig,
+    core::{
+        domain::{
+            auth::service::hash_passwords,
+            error::AppError,
+            users::{
+                model::{User, UserRole, UNUSABLE_PASSWORD},
+                query::UserQuery,
+                repository::{UserRepository, UserTxRepository},
+            },
+            validation::normalize_email,
+        },
+        rest::{
+            handler::{
+                body::{Body, BodyConfig},
+                extract::{path_error, query_error},
+            },
+            scim::{
+                filter::{parse_member_path, ScimFilter, ScimFilterAttribute},
+                model::{
+                    ScimError, ScimGroup, ScimListQuery, ScimListResponse, ScimPatchOperation,
+                    ScimPatchRequest, ScimUser, GROUP_SCHEMA, SCIM_CONTENT_TYPE, SCIM_PREFIX,
+                    USER_SCHEMA,
+                },
+            },
+        },
+    },
+    pkg::crypto::constant_time_eq,
+};
+
+type ScimResult<T> = std::result::Result<T, ScimError>;
+
+/// Maximum number of resources returned by a single list request
+const MAX_RESULTS: i64 = 200;
+
+/// Roles exposed as SCIM groups, in listing order
+const GROUP_ROLES: [UserRole; 3] = [UserRole::User, UserRole::Moderator, UserRole::Admin];
+
+/// Render extractor failures in the SCIM scope as SCIM errors (RFC 7644,
+/// section 3.12) instead of the standard error envelope
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.app_data(BodyConfig::default().error_handler(|e, _| invalid_request(e, "invalidSyntax")))
+        .app_data(
+            web::PathConfig::default()
+                .error_handler(|e, req| invalid_request(path_error(e, req), "invalidValue")),
+        )
+        .app_data(
+            web::QueryConfig::default()
+                .error_handler(|e, _| invalid_request(query_error(e), "invalidValue")),
+        );
+}
+
+/// A request that could not be extracted; validation failures become
+/// `scim_type` errors
+fn invalid_request(error: AppError, scim_type: &str) -> actix_web::Error {
+    match error {
+        AppError::Validation { message, .. } => ScimError::bad_request(scim_type, message),
+        error => error.into(),
+    }
+    .into()
+}
+
+/// Check the dedicated SCIM bearer token
+fn authorize(req: &HttpRequest, config: &ScimConfig) -> ScimResult<()> {
+    let expected = config
+        .bearer_token
+        .as_deref()
+        .ok_or_else(ScimError::unauthorized)?;
+
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .ok_or_else(ScimError::unauthorized)?;
+
+    if !constant_time_eq(expected.as_bytes(), token.as_bytes()) {
+        return Err(ScimError::unauthorized());
+    }
+    Ok(())
+}
+
+/// Absolute base URL of the SCIM API, used for `meta.location` and `$ref`
+fn base_url(req: &HttpRequest) -> String {
+    let info = req.connection_info();
+    format!("{}://{}{}", info.scheme(), info.host(), SCIM_PREFIX)
+}
+
+fn scim_response<T: Serialize>(status: StatusCode, body: &T) -> HttpResponse {
+    HttpResponse::build(status)
+        .content_type(SCIM_CONTENT_TYPE)
+        .json(body)
+}
+
+fn parse_user_id(id: &str) -> ScimResult<Uuid> {
+    Uuid::parse_str(id).map_err(|_| ScimError::not_found(format!("User {} not found", id)))
+}
+
+fn parse_group_id(id: &str) -> ScimResult<UserRole> {
+    id.parse::<UserRole>()
+        .map_err(|_| ScimError::not_found(format!("Group {} not found", id)))
+}
+
+/// Hash `password` off the worker thread
+async fn hash_password(password: &str) -> ScimResult<String> {
+    let mut hashes = hash_passwords(vec![password.to_string()]).await?;
+    hashes.pop().ok_or_else(|| AppError::Internal.into())
+}
+
+/// Check a password supplied by the provisioning client, with the rule
+/// that applies to passwords set through the user API
+fn validate_password(password: &str) -> ScimResult<()> {
+    if !(8..=128).contains(&password.chars().count()) {
+        return Err(ScimError::bad_request(
+            "invalidValue",
+            "Password must be between 8 and 128 characters",
+        ));
+    }
+    Ok(())
+}
+
+/// Save new attributes of user `id` in one transaction; the password is
+/// hashed before the transaction starts
+async fn save_user(
+    pool: &PgPool,
+    id: Uuid,
+    name: Option<&str>,
+    email: Option<&str>,
+    password: Option<&str>,
+) -> ScimResult<User> {
+    let password_hash = match password {
+        Some(password) => Some(hash_password(password).await?),
+        None => None,
+    };
+
+    let mut tx = pool.begin().await?;
+    let mut repo = UserTxRepository { conn: &mut tx };
+    let mut user = match (name, email) {
+        (None, None) => repo.find_by_id(id).await?,
+        _ => repo.update_user(id, None, name, email).await?,
+    };
+    if let (Some(password_hash), Some(_)) = (&password_hash, &user) {
+        user = repo.update_password(id, password_hash).await?;
+    }
+    let user = user.ok_or_else(|| ScimError::not_found(format!("User {} not found", id)))?;
+    tx.commit().await?;
+    Ok(user)
+}
+
+/// Check the attributes of a user supplied by the provisioning client
+fn validate_user(user: &ScimUser) -> ScimResult<()> {
+    if !user.user_name.validate_email() {
+        return Err(ScimError::bad_request(
+            "invalidValue",
+            "userName must be a valid email address",
+        ));
+    }
+    if user.resolved_name().chars().count() > 255 {
+        return Err(ScimError::bad_request(
+            "invalidValue",
+            "Name must be at most 255 characters",
+        ));
+    }
+    if let Some(password) = &user.password {
+        validate_password(password)?;
+    }
+    if user.active == Some(false) {
+        return Err(deactivation_unsupported());
+    }
+    Ok(())
+}
+
+fn deactivation_unsupported() -> ScimError {
+    ScimError::bad_request(
+        "invalidValue",
+        "Deactivating users is not supported; delete the user instead",
+    )
+}
+
+/// Pagination parameters as a zero-based offset and a bounded count
+fn page_bounds(query: &ScimListQuery) -> (i64, i64) {
+    let start_index = query.start_index.unwrap_or(1).max(1);
+    let count = query.count.unwrap_or(MAX_RESULTS).clamp(0, MAX_RESULTS);
+    (start_index, count)
+}
+
+/// SCIM service provider configuration
+#[utoipa::path(
+    tag = "scim",
+    responses(
+        (status = 200, description = "Service provider configuration"),
+        (status = 401, description = "Authorization failure", body = ScimError)
+    ),
+    security(("scim_bearer" = []))
+)]
+#[get("/ServiceProviderConfig")]
+pub async fn scim_service_provider_config(
+    req: HttpRequest,
+    config: web::Data<ScimConfig>,
+) -> ScimResult<HttpResponse> {
+    authorize(&req, &config)?;
+    Ok(scim_response(
+        StatusCode::OK,
+        &json!({
+            "schemas": ["urn:ietf:params:scim:schemas:core:2.0:ServiceProviderConfig"],
+            "patch": { "supported": true },
+            "bulk": { "supported": false, "maxOperations": 0, "maxPayloadSize": 0 },
+            "filter": { "supported": true, "maxResults": MAX_RESULTS },
+            "changePassword": { "supported": true },
+            "sort": { "supported": false },
+            "etag": { "supported": false },
+            "authenticationSchemes": [{
+                "type": "oauthbearertoken",
+                "name": "OAuth Bearer Token",
+                "description": "Authentication with the dedicated SCIM bearer token",
+                "primary": true
+            }],
+            "meta": {
+                "resourceType": "ServiceProviderConfig",
+                "location": format!("{}/ServiceProviderConfig", base_url(&req))
+            }
+        }),
+    ))
+}
+
+/// SCIM resource types
+#[utoipa::path(
+    tag = "scim",
+    responses(
+        (status = 200, description = "Supported resource types"),
+        (status = 401, description = "Authorization failure", body = ScimError)
+    ),
+    security(("scim_bearer" = []))
+)]
+#[get("/ResourceTypes")]
+pub async fn scim_resource_types(
+    req: HttpRequest,
+    config: web::Data<ScimConfig>,
+) -> ScimResult<HttpResponse> {
+    authorize(&req, &config)?;
+    let base = base_url(&req);
+    let resources = vec![
+        json!({
+            "schemas": ["urn:ietf:params:scim:schemas:core:2.0:ResourceType"],
+            "id": "User",
+            "name": "User",
+            "endpoint": "/Users",
+            "schema": USER_SCHEMA,
+            "meta": { "resourceType": "ResourceType", "location": format!("{}/ResourceTypes/User", base) }
+        }),
+        json!({
+            "schemas": ["urn:ietf:params:scim:schemas:core:2.0:ResourceType"],
+            "id": "Group",
+            "name": "Group",
+            "endpoint": "/Groups",
+            "schema": GROUP_SCHEMA,
+            "meta": { "resourceType": "ResourceType", "location": format!("{}/ResourceTypes/Group", base) }
+        }),
+    ];
+    Ok(scim_response(
+        StatusCode::OK,
+        &ScimListResponse::new(resources, 2, 1),
+    ))
+}
+
+/// SCIM schema definitions for the supported resources
+#[utoipa::path(
+    tag = "scim",
+    responses(
+        (status = 200, description = "Supported schemas"),
+        (status = 401, description = "Authorization failure", body = ScimError)
+    ),
+    security(("scim_bearer" = []))
+)]
+#[get("/Schemas")]
+pub async fn scim_schemas(
+    req: HttpRequest,
+    config: web::Data<ScimConfig>,
+) -> ScimResult<HttpResponse> {
+    authorize(&req, &config)?;
+    let base = base_url(&req);
+    let attribute = |name: &str, kind: &str, required: bool, mutability: &str, uniqueness: &str| {
+        json!({
+            "name": name,
+            "type": kind,
+            "multiValued": false,
+            "required": required,
+            "caseExact": false,
+            "mutability": mutability,
+            "returned": if name == "password" { "never" } else { "default" },
+            "uniqueness": uniqueness
+        })
+    };
+    let resources = vec![
+        json!({
+            "id": USER_SCHEMA,
+            "name": "User",
+            "description": "User Account",
+            "attributes": [
+                attribute("userName", "string", true, "readWrite", "server"),
+                attribute("displayName", "string", false, "readWrite", "none"),
+                {
+                    "name": "name",
+                    "type": "complex",
+                    "multiValued": false,
+                    "required": false,
+                    "mutability": "readWrite",
+                    "returned": "default",
+                    "subAttributes": [
+                        attribute("formatted", "string", false, "readWrite", "none"),
+                        attribute("givenName", "string", false, "writeOnly", "none"),
+                        attribute("familyName", "string", false, "writeOnly", "none")
+                    ]
+                },
+                attribute("password", "string", false, "writeOnly", "none"),
+                attribute("active", "boolean", false, "readWrite", "none"),
+                {
+                    "name": "groups",
+                    "type": "complex",
+                    "multiValued": true,
+                    "required": false,
+                    "mutability": "readOnly",
+                    "returned": "default"
+                }
+            ],
+            "meta": { "resourceType": "Schema", "location": format!("{}/Schemas/{}", base, USER_SCHEMA) }
+        }),
+        json!({
+            "id": GROUP_SCHEMA,
+            "name": "Group",
+            "description": "Group backed by a user role",
+            "attributes": [
+                attribute("displayName", "string", true, "readOnly", "server"),
+                {
+                    "name": "members",
+                    "type": "complex",
+                    "multiValued": true,
+                    "required": false,
+                    "mutability": "readWrite",
+                    "returned": "default"
+                }
+            ],
+            "meta": { "resourceType": "Schema", "location": format!("{}/Schemas/{}", base, GROUP_SCHEMA) }
+        }),
+    ];
+    Ok(scim_response(
+        StatusCode::OK,
+        &ScimListResponse::new(resources, 2, 1),
+    ))
+}
+
+/// List or filter users
+#[utoipa::path(
+    tag = "scim",
+    params(
+        ("filter" = Option<String>, Query, description = "Equality filter, e.g. userName eq \"john@example.com\""),
+        ("startIndex" = Option<i64>, Query, description = "1-based index of the first result"),
+        ("count" = Option<i64>, Query, description = "Maximum number of results")
+    ),
+    responses(
+        (status = 200, description = "Users retrieved", body = ScimUserList),
+        (status = 400, description = "Invalid filter", body = ScimError),
+        (status = 401, description = "Authorization failure", body = ScimError)
+    ),
+    security(("scim_bearer" = []))
+)]
+#[get("/Users")]
+pub async fn scim_list_users(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    config: web::Data<ScimConfig>,
+    query: web::Query<ScimListQuery>,
+) -> ScimResult<HttpResponse> {
+    authorize(&req, &config)?;
+    let repo = UserRepository { pool: &pool };
+    let (start_index, count) = page_bounds(&query);
+
+    let filter = query.filter.as_deref().map(ScimFilter::parse).transpose()?;
+    let (users, total) = match filter.as_ref().map(ScimFilter::to_user_filter) {
+        // An id that is not a UUID matches no user
+        Some(None) => (Vec::new(), 0),
+        filter => {
+            let users_query = UserQuery {
+                filter: filter.flatten(),
+                ..UserQuery::default()
+            };
+            (
+                repo.find_by_query(&users_query, None, start_index - 1, count)
+                    .await?,
+                repo.count_by_query(&users_query).await?,
+            )
+        }
+    };
+
+    let base = base_url(&req);
+    let resources = users
+        .iter()
+        .map(|user| ScimUser::from_user(user, &base))
+        .collect();
+    Ok(scim_response(
+        StatusCode::OK,
+        &ScimListResponse::new(resources, total, start_index),
+    ))
+}
+
+/// Provision a new user
+#[utoipa::path(
+    tag = "scim",
+    request_body(content = ScimUser, content_type = "application/scim+json"),
+    responses(
+        (status = 201, description = "User created", body = ScimUser),
+        (status = 400, description = "Invalid user", body = ScimError),
+        (status = 401, description = "Authorization failure", body = ScimError),
+        (status = 409, description = "userName already in use", body = ScimError)
+    ),
+    security(("scim_bearer" = []))
+)]
+#[post("/Users")]
+pub async fn scim_create_user(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    config: web::Data<ScimConfig>,
+    payload: Body<ScimUser>,
+) -> ScimResult<HttpResponse> {
+    authorize(&req, &config)?;
+    let mut payload = payload.into_inner();
+    normalize_email(&mut payload.user_name);
+    validate_user(&payload)?;
+
+    // Users provisioned without a password can only sign in through the identity provider
+    let password_hash = match &payload.password {
+        Some(password) => hash_password(password).await?,
+        None => UNUSABLE_PASSWORD.to_string(),
+    };
+
+    let repo = UserRepository { pool: &pool };
+    let user = repo
+        .create_user_with_password(
+            &payload.resolved_name(),
+            &payload.user_name,
+            &password_hash,
+            &UserRole::User,
+        )
+        .await?;
+
+    let resource = ScimUser::from_user(&user, &base_url(&req));
+    let location = resource.meta.as_ref().map(|m| m.location.clone());
+    let mut response = scim_response(StatusCode::CREATED, &resource);
+    if let Some(location) = location.and_then(|l| header::HeaderValue::from_str(&l).ok()) {
+        response.headers_mut().insert(header::LOCATION, location);
+    }
+    Ok(response)
+}
+
+/// Retrieve a user
+#[utoipa::path(
+    tag = "scim",
+    params(("id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "User retrieved", body = ScimUser),
+        (status = 401, description = "Authorization failure", body = ScimError),
+        (status = 404, description = "User not found", body = ScimError)
+    ),
+    security(("scim_bearer" = []))
+)]
+#[get("/Users/{id}")]
+pub async fn scim_get_user(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    config: web::Data<ScimConfig>,
+    path: web::Path<String>,
+) -> ScimResult<HttpResponse> {
+    authorize(&req, &config)?;
+    let id = parse_user_id(&path)?;
+    let user = UserRepository { pool: &pool }.find_by_id(id).await?;
+    Ok(scim_response(
+        StatusCode::OK,
+        &ScimUser::from_user(&user, &base_url(&req)),
+    ))
+}
+
+/// Replace a user
+#[utoipa::path(
+    tag = "scim",
+    params(("id" = String, Path, description = "User ID")),
+    request_body(content = ScimUser, content_type = "application/scim+json"),
+    responses(
+        (status = 200, description = "User replaced", body = ScimUser),
+        (status = 400, description = "Invalid user", body = ScimError),
+        (status = 401, description = "Authorization failure", body = ScimError),
+        (status = 404, description = "User not found", body = ScimError),
+        (status = 409, description = "userName already in use", body = ScimError)
+    ),
+    security(("scim_bearer" = []))
+)]
+#[put("/Users/{id}")]
+pub async fn scim_replace_user(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    config: web::Data<ScimConfig>,
+    path: web::Path<String>,
+    payload: Body<ScimUser>,
+) -> ScimResult<HttpResponse> {
+    authorize(&req, &config)?;
+    let id = parse_user_id(&path)?;
+    let mut payload = payload.into_inner();
+    normalize_email(&mut payload.user_name);
+    validate_user(&payload)?;
+
+    let user = save_user(
+        &pool,
+        id,
+        Some(&payload.resolved_name()),
+        Some(&payload.user_name),
+        payload.password.as_deref(),
+    )
+    .await?;
+
+    Ok(scim_response(
+        StatusCode::OK,
+        &ScimUser::from_user(&user, &base_url(&req)),
+    ))
+}
+
+/// Attribute changes collected from the operations of a PATCH request
+#[derive(Default)]
+struct UserChanges {
+    name: Option<String>,
+    email: Option<String>,
+    password: Option<String>,
+}
+
+impl UserChanges {
+    fn apply(&mut self, path: &str, value: &Value) -> ScimResult<()> {
+        let invalid_value =
+            || ScimError::bad_request("invalidValue", format!("Invalid value for {}", path));
+        let as_string =
+            |value: &Value| value.as_str().map(str::to_string).ok_or_else(invalid_value);
+
+        match path.to_ascii_lowercase().as_str() {
+            "username" => self.email = Some(as_string(value)?),
+            "displayname" | "name.formatted" => self.name = Some(as_string(value)?),
+            "name" => {
+                let formatted = value.get("formatted").ok_or_else(invalid_value)?;
+                self.name = Some(as_string(formatted)?);
+            }
+            "emails" => {
+                let emails = value.as_array().ok_or_else(invalid_value)?;
+                let email = emails
+                    .iter()
+                    .find(|e| e.get("primary").and_then(Value::as_bool) == Some(true))
+                    .or_else(|| emails.first())
+                    .and_then(|e| e.get("value"))
+                    .ok_or_else(invalid_value)?;
+                self.email = Some(as_string(email)?);
+            }
+            "password" => self.password = Some(as_string(value)?),
+            "active" => {
+                if !value.as_bool().ok_or_else(invalid_value)? {
+                    return Err(deactivation_unsupported());
+                }
+            }
+            _ => {
+                return Err(ScimError::bad_request(
+                    "invalidPath",
+                    format!("Unsupported attribute path: {}", path),
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+fn apply_user_operation(
+    changes: &mut UserChanges,
+    operation: &ScimPatchOperation,
+) -> ScimResult<()> {
+    match operation.op.to_ascii_lowercase().as_str() {
+        "add" | "replace" => {}
+        "remove" => {
+            return Err(ScimError::bad_request(
+                "mutability",
+                "User attributes cannot be removed",
+            ))
+        }
+        _ => {
+            return Err(ScimError::bad_request(
+                "invalidSyntax",
+                format!("Unsupported operation: {}", operation.op),
+            ))
+        }
+    }
+
+    let value = operation
+        .value
+        .as_ref()
+        .ok_or_else(|| ScimError::bad_request("noTarget", "Operation value is required"))?;
+
+    match &operation.path {
+        Some(path) => changes.apply(path, value),
+        None => {
+            let attributes = value.as_object().ok_or_else(|| {
+                ScimError::bad_request("invalidValue", "Operation value must be an object")
+            })?;
+            attributes
+                .iter()
+                .try_for_each(|(path, value)| changes.apply(path, value))
+        }
+    }
+}
+
+/// Partially update a user
+#[utoipa::path(
+    tag = "scim",
+    params(("id" = String, Path, description = "User ID")),
+    request_body(content = ScimPatchRequest, content_type = "application/scim+json"),
+    responses(
+        (status = 200, description = "User updated", body = ScimUser),
+        (status = 400, description = "Invalid operation", body = ScimError),
+        (status = 401, description = "Authorization failure", body = ScimError),
+        (status = 404, description = "User not found", body = ScimError),
+        (status = 409, description = "userName already in use", body = ScimError)
+    ),
+    security(("scim_bearer" = []))
+)]
+#[patch("/Users/{id}")]
+pub async fn scim_patch_user(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    config: web::Data<ScimConfig>,
+    path: web::Path<String>,
+    payload: Body<ScimPatchRequest>,
+) -> ScimResult<HttpResponse> {
+    authorize(&req, &config)?;
+    let id = parse_user_id(&path)?;
+
+    let mut changes = UserChanges::default();
+    for operation in &payload.operations {
+        apply_user_operation(&mut changes, operation)?;
+    }
+    if let Some(email) = &mut changes.email {
+        normalize_email(email);
+        if !email.validate_email() {
+            return Err(ScimError::bad_request(
+                "invalidValue",
+                "userName must be a valid email address",
+            ));
+        }
+    }
+
+    if let Some(password) = &changes.password {
+        validate_password(password)?;
+    }
+
+    let user = save_user(
+        &pool,
+        id,
+        changes.name.as_deref(),
+        changes.email.as_deref(),
+        changes.password.as_deref(),
+    )
+    .await?;
+
+    Ok(scim_response(
+        StatusCode::OK,
+        &ScimUser::from_user(&user, &base_url(&req)),
+    ))
+}
+
+/// Deprovision a user
+#[utoipa::path(
+    tag = "scim",
+    params(("id" = String, Path, description = "User ID")),
+    responses(
+        (status = 204, description = "User deleted"),
+        (status = 401, description = "Authorization failure", body = ScimError),
+        (status = 404, description = "User not found", body = ScimError)
+    ),
+    security(("scim_bearer" = []))
+)]
+#[delete("/Users/{id}")]
+pub async fn scim_delete_user(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    config: web::Data<ScimConfig>,
+    path: web::Path<String>,
+) -> ScimResult<HttpResponse> {
+    authorize(&req, &config)?;
+    let id = parse_user_id(&path)?;
+    let repo = UserRepository { pool: &pool };
+    if !repo.delete_user(id).await? {
+        return Err(ScimError::not_found(format!("User {} not found", id)));
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// List the groups backing each user role
+#[utoipa::path(
+    tag = "scim",
+    params(
+        ("filter" = Option<String>, Query, description = "Equality filter on id or displayName"),
+        ("startIndex" = Option<i64>, Query, description = "1-based index of the first result"),
+        ("count" = Option<i64>, Query, description = "Maximum number of results")
+    ),
+    responses(
+        (status = 200, description = "Groups retrieved", body = ScimGroupList),
+        (status = 400, description = "Invalid filter", body = ScimError),
+        (status = 401, description = "Authorization failure", body = ScimError)
+    ),
+    security(("scim_bearer" = []))
+)]
+#[get("/Groups")]
+pub async fn scim_list_groups(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    config: web::Data<ScimConfig>,
+    query: web::Query<ScimListQuery>,
+) -> ScimResult<HttpResponse> {
+    authorize(&req, &config)?;
+    let (start_index, count) = page_bounds(&query);
+
+    let roles: Vec<UserRole> = match &query.filter {
+        Some(filter) => {
+            let filter = ScimFilter::parse(filter)?;
+            if !matches!(
+                filter.attribute,
+                ScimFilterAttribute::Id | ScimFilterAttribute::DisplayName
+            ) {
+                return Err(ScimError::bad_request(
+                    "invalidFilter",
+                    "Groups can only be filtered by id or displayName",
+                ));
+            }
+            filter.value.parse::<UserRole>().into_iter().collect()
+        }
+        None => GROUP_ROLES.to_vec(),
+    };
+
+    let repo = UserRepository { pool: &pool };
+    let base = base_url(&req);
+    let total = roles.len() as i64;
+    let mut groups = Vec::new();
+    for role in roles
+        .iter()
+        .skip((start_index - 1) as usize)
+        .take(count as usize)
+    {
+        let members = repo.find_by_role(role).await?;
+        groups.push(ScimGroup::from_role(role, &members, &base));
+    }
+
+    Ok(scim_response(
+        StatusCode::OK,
+        &ScimListResponse::new(groups, total, start_index),
+    ))
+}
+
+/// Retrieve a group
+#[utoipa::path(
+    tag = "scim",
+    params(("id" = String, Path, description = "Group ID (role name)")),
+    responses(
+        (status = 200, description = "Group retrieved", body = ScimGroup),
+        (status = 401, description = "Authorization failure", body = ScimError),
+        (status = 404, description = "Group not found", body = ScimError)
+    ),
+    security(("scim_bearer" = []))
+)]
+#[get("/Groups/{id}")]
+pub async fn scim_get_group(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    config: web::Data<ScimConfig>,
+    path: web::Path<String>,
+) -> ScimResult<HttpResponse> {
+    authorize(&req, &config)?;
+    let role = parse_group_id(&path)?;
+    let members = UserRepository { pool: &pool }.find_by_role(&role).await?;
+    Ok(scim_response(
+        StatusCode::OK,
+        &ScimGroup::from_role(&role, &members, &base_url(&req)),
+    ))
+}
+
+/// Parse the member IDs of a `members` value
+fn member_ids(value: &Value) -> ScimResult<Vec<Uuid>> {
+    let members = match value {
+        Value::Array(members) => members.as_slice(),
+        Value::Object(_) => std::slice::from_ref(value),
+        _ => {
+            return Err(ScimError::bad_request(
+                "invalidValue",
+                "members must be an array",
+            ))
+        }
+    };
+    members
+        .iter()
+        .map(|member| {
+            member
+                .get("value")
+                .and_then(Value::as_str)
+                .and_then(|id| Uuid::parse_str(id).ok())
+                .ok_or_else(|| ScimError::bad_request("invalidValue", "Invalid member value"))
+        })
+        .collect()
+}
+
+/// A requested change to the members of a group
+enum MemberChange {
+    Add(Vec<Uuid>),
+    Remove(Vec<Uuid>),
+    /// Make the given users the exact membership
+    Replace(Vec<Uuid>),
+}
+
+/// Apply the membership changes of a request in one transaction. Every user
+/// to be added must exist, which is checked before anything is written, so a
+/// request with an unknown member changes nothing.
+async fn apply_member_changes(
+    pool: &PgPool,
+    role: &UserRole,
+    changes: &[MemberChange],
+) -> ScimResult<()> {
+    let mut tx = pool.begin().await?;
+    let mut repo = UserTxRepository { conn: &mut tx };
+
+    for change in changes {
+        let (MemberChange::Add(ids) | MemberChange::Replace(ids)) = change else {
+            continue;
+        };
+        for id in ids {
+            if repo.find_by_id(*id).await?.is_none() {
+                return Err(unknown_member(*id));
+            }
+        }
+    }
+
+    for change in changes {
+        match change {
+            MemberChange::Add(ids) => add_members(&mut repo, role, ids).await?,
+            MemberChange::Remove(ids) => remove_members(&mut repo, role, ids).await?,
+            MemberChange::Replace(ids) => {
+                let removed: Vec<Uuid> = repo
+                    .find_by_role(role)
+                    .await?
+                    .into_iter()
+                    .map(|user| user.id)
+                    .filter(|id| !ids.contains(id))
+                    .collect();
+                remove_members(&mut repo, role, &removed).await?;
+                add_members(&mut repo, role, ids).await?;
+            }
+        }
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+fn unknown_member(id: Uuid) -> ScimError {
+    ScimError::bad_request("invalidValue", format!("Unknown member: {}", id))
+}
+
+/// Grant a role to the given users
+async fn add_members(
+    repo: &mut UserTxRepository<'_>,
+    role: &UserRole,
+    ids: &[Uuid],
+) -> ScimResult<()> {
+    for id in ids {
+        repo.update_user_role(*id, None, role)
+            .await?
+            .ok_or_else(|| unknown_member(*id))?;
+    }
+    Ok(())
+}
+
+/// Remove users from a group by falling back to the default role
+async fn remove_members(
+    repo: &mut UserTxRepository<'_>,
+    role: &UserRole,
+    ids: &[Uuid],
+) -> ScimResult<()> {
+    for id in ids {
+        if let Some(user) = repo.find_by_id(*id).await? {
+            if user.has_role(role) {
+                repo.update_user_role(*id, None, &UserRole::default())
+                    .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Replace the members of a group
+#[utoipa::path(
+    tag = "scim",
+    params(("id" = String, Path, description = "Group ID (role name)")),
+    request_body(content = ScimGroup, content_type = "application/scim+json"),
+    responses(
+        (status = 200, description = "Group replaced", body = ScimGroup),
+        (status = 400, description = "Invalid group", body = ScimError),
+        (status = 401, description = "Authorization failure", body = ScimError),
+        (status = 404, description = "Group not found", body = ScimError)
+    ),
+    security(("scim_bearer" = []))
+)]
+#[put("/Groups/{id}")]
+pub async fn scim_replace_group(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    config: web::Data<ScimConfig>,
+    path: web::Path<String>,
+    payload: Body<ScimGroup>,
+) -> ScimResult<HttpResponse> {
+    authorize(&req, &config)?;
+    let role = parse_group_id(&path)?;
+    if !payload.display_name.eq_ignore_ascii_case(&role.to_string()) {
+        return Err(ScimError::bad_request(
+            "mutability",
+            "Group displayName cannot be changed",
+        ));
+    }
+
+    let ids = payload
+        .members
+        .iter()
+        .map(|member| {
+            Uuid::parse_str(&member.value)
+                .map_err(|_| ScimError::bad_request("invalidValue", "Invalid member value"))
+        })
+        .collect::<ScimResult<Vec<Uuid>>>()?;
+
+    apply_member_changes(&pool, &role, &[MemberChange::Replace(ids)]).await?;
+
+    let members = UserRepository { pool: &pool }.find_by_role(&role).await?;
+    Ok(scim_response(
+        StatusCode::OK,
+        &ScimGroup::from_role(&role, &members, &base_url(&req)),
+    ))
+}
+
+/// Add or remove group members
+#[utoipa::path(
+    tag = "scim",
+    params(("id" = String, Path, description = "Group ID (role name)")),
+    request_body(content = ScimPatchRequest, content_type = "application/scim+json"),
+    responses(
+        (status = 200, description = "Group updated", body = ScimGroup),
+        (status = 400, description = "Invalid operation", body = ScimError),
+        (status = 401, description = "Authorization failure", body = ScimError),
+        (status = 404, description = "Group not found", body = ScimError)
+    ),
+    security(("scim_bearer" = []))
+)]
+#[patch("/Groups/{id}")]
+pub async fn scim_patch_group(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    config: web::Data<ScimConfig>,
+    path: web::Path<String>,
+    payload: Body<ScimPatchRequest>,
+) -> ScimResult<HttpResponse> {
+    authorize(&req, &config)?;
+    let role = parse_group_id(&path)?;
+
+    // Nothing is changed until every operation has been understood
+    let mut changes = Vec::with_capacity(payload.operations.len());
+    for operation in &payload.operations {
+        let op = operation.op.to_ascii_lowercase();
+        let path = operation.path.as_deref().map(str::trim);
+
+        // Without a path the value is an object of attributes; only members can change
+        let value = match (path, &operation.value) {
+            (None, Some(Value::Object(attributes))) => {
+                if attributes
+                    .keys()
+                    .any(|key| !key.eq_ignore_ascii_case("members"))
+                {
+                    return Err(ScimError::bad_request(
+                        "mutability",
+                        "Only group members can be modified",
+                    ));
+                }
+                attributes.values().next().cloned()
+            }
+            (Some(path), value) if path.eq_ignore_ascii_case("members") => value.clone(),
+            (Some(path), _) if op == "remove" => match parse_member_path(path) {
+                Some(id) => {
+                    let id = Uuid::parse_str(&id).map_err(|_| {
+                        ScimError::bad_request("invalidValue", "Invalid member value")
+                    })?;
+                    changes.push(MemberChange::Remove(vec![id]));
+                    continue;
+                }
+                None => {
+                    return Err(ScimError::bad_request(
+                        "invalidPath",
+                        format!("Unsupported attribute path: {}", path),
+                    ))
+                }
+            },
+            (Some(path), _) => {
+                return Err(ScimError::bad_request(
+                    "mutability",
+                    format!("Attribute {} cannot be modified", path),
+                ))
+            }
+            (None, _) => {
+                return Err(ScimError::bad_request(
+                    "noTarget",
+                    "Operation value is required",
+                ))
+            }
+        };
+
+        let change = match (op.as_str(), value) {
+            ("add", Some(value)) => MemberChange::Add(member_ids(&value)?),
+            ("replace", Some(value)) => MemberChange::Replace(member_ids(&value)?),
+            ("remove", Some(value)) => MemberChange::Remove(member_ids(&value)?),
+            ("remove", None) => MemberChange::Replace(Vec::new()),
+            ("add" | "replace", None) => {
+                return Err(ScimError::bad_request(
+                    "noTarget",
+                    "Operation value is required",
+                ))
+            }
+            _ => {
+                return Err(ScimError::bad_request(
+                    "invalidSyntax",
+                    format!("Unsupported operation: {}", operation.op),
+                ))
+            }
+        };
+        changes.push(change);
+    }
+    apply_member_changes(&pool, &role, &changes).await?;
+
+    let members = UserRepository { pool: &pool }.find_by_role(&role).await?;
+    Ok(scim_response(
+        StatusCode::OK,
+        &ScimGroup::from_role(&role, &members, &base_url(&req)),
+    ))
+}
@@ -1,129 +1,826 @@
-use crate::core::{
-    domain::users::repository::UserRepository,
-    rest::handler::{
-        response::{build_error_response, build_success_response},
-        validator::is_valid_email,
+use crate::{
+    config::{BodyLimitConfig, ConcurrencyConfig},
+    core::{
+        domain::{
+            error::{AppError, Result},
+            users::{
+                bulk::{self, BulkMode, BulkRequest},
+                export::{export_users, ExportColumn, ExportFormat},
+                import::{import_users, ColumnMap, ImportFormat, ImportOptions},
+                model::{PublicUser, UpdateRoleRequest, UpdateUserRequest, User, UserRole},
+                query::UserQuery,
+                repository::{email_taken, map_email_conflict, UserRepository},
+                search::UserSearch,
+            },
+        },
+        rest::{
+            handler::{
+                body::{read_body, ValidatedJson},
+                fields::{FieldsQuery, Selection, USER_FIELDS, USER_SEARCH_FIELDS},
+                precondition::{entity_tag, expected_version, last_modified, precondition_failed},
+                response::{
+                    build_success_response, decode_cursor, encode_cursor, BulkResponse, Page,
+                    PageQuery,
+                },
+            },
+            middleware::{
+                api_version::Deprecated,
+                auth::{AuthData, AuthExtractor, Principal},
+                conditional_get::{CachePolicy, ConditionalGet},
+            },
+            validation::email_format,
+            version::ApiVersion,
+        },
     },
 };
-use actix_web::{get, post, web, HttpResponse, Responder};
+use actix_web::{
+    delete, get,
+    http::{header, StatusCode},
+    patch, post, put, web, HttpRequest, HttpResponse, Responder,
+};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use validator::Validate;
+
+/// Filter and sort parameters for listing users
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UserListQuery {
+    /// Filter expression over `id`, `name`, `email`, `role`, `created_at` and
+    /// `updated_at` with `eq`, `ne`, `gt`, `ge`, `lt`, `le`, `co`, `sw`,
+    /// `and`, `or`, `not` and parentheses
+    #[param(example = "role eq 'moderator' and created_at ge 2026-01-01")]
+    pub filter: Option<String>,
+    /// Comma separated sort fields, descending when prefixed with `-`
+    #[param(example = "-created_at,name")]
+    pub sort: Option<String>,
+    /// Also list soft deleted users (admin only)
+    pub include_deleted: Option<bool>,
+}
 
-/// Get all users in the system
+/// List users, one page at a time
+///
+/// Pages are ordered by creation time unless `sort` is given. Use
+/// `limit`/`offset` for numbered pages or follow `next_cursor` for stable
+/// iteration while users are being added. Navigation links are also
+/// returned in the `Link` header. Soft deleted users are only listed for
+/// admins passing `include_deleted=true`. `fields` and `expand` shape each
+/// listed user.
 #[utoipa::path(
-    get,
-    path = "/users",
     tag = "users",
+    params(UserListQuery, PageQuery, FieldsQuery),
     responses(
-        (status = 200, description = "List of all users retrieved successfully", body = Vec<User>),
-        (status = 404, description = "No users found", body = ErrorResponse),
+        (status = 200, description = "Page of users retrieved successfully", body = UserPageResponse),
+        (status = 400, description = "Invalid filter, sort, cursor, fields or expand", body = ErrorResponse),
+        (status = 401, description = "Authentication required for include_deleted", body = ErrorResponse),
+        (status = 403, description = "Admin access required for include_deleted", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse)
+    ),
+    security(
+        (),
+        ("bearer_auth" = []),
+        ("session_cookie" = [])
     )
 )]
-#[get("/users")]
-pub async fn get_users(pool: web::Data<PgPool>) -> impl Responder {
+#[get("/users", wrap = "ConditionalGet::new(CachePolicy::private(0))")]
+pub async fn get_users(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    auth: Option<AuthData>,
+    list: web::Query<UserListQuery>,
+    page: web::Query<PageQuery>,
+    fields: web::Query<FieldsQuery>,
+) -> Result<impl Responder> {
+    let selection = Selection::parse(&fields, &USER_FIELDS)?;
+    let mut query = UserQuery::parse(list.filter.as_deref(), list.sort.as_deref())?;
+    query.select = selection.columns();
+    if list.include_deleted == Some(true) {
+        let auth = auth.ok_or_else(|| AppError::Authentication {
+            message: "Authentication required to include deleted users".to_string(),
+        })?;
+        AuthExtractor::check_role(&auth, &UserRole::Admin)?;
+        query.include_deleted = true;
+    }
     let repo = UserRepository { pool: &pool };
-    match repo.find_all().await {
-        Ok(users) => {
-            // Return a structured success response
-            HttpResponse::Ok().json(build_success_response(
-                users,
-                "Users retrieved successfully.",
-            ))
-        }
-        Err(sqlx::Error::RowNotFound) => {
-            // Handle case where no users are found
-            HttpResponse::NotFound().json(build_error_response("not_found", "No users found."))
+    let limit = page.limit();
+
+    // Fetch one extra row to know whether there is a next page
+    let (mut users, offset) = match &page.cursor {
+        Some(cursor) => {
+            let after = query.parse_position(&decode_cursor::<Vec<String>>(cursor)?)?;
+            let users = repo
+                .find_by_query(&query, Some(&after), 0, limit + 1)
+                .await?;
+            (users, None)
         }
-        Err(e) => {
-            // Log the error for debugging
-            log::error!("Failed to fetch users: {:?}", e);
-
-            // Return a structured error response
-            HttpResponse::InternalServerError().json(build_error_response(
-                "internal_server_error",
-                "Failed to retrieve users.",
-            ))
+        None => {
+            let offset = page.offset();
+            let users = repo.find_by_query(&query, None, offset, limit + 1).await?;
+            (users, Some(offset))
         }
-    }
+    };
+    let has_more = users.len() as i64 > limit;
+    users.truncate(limit as usize);
+
+    let page = Page {
+        next_cursor: users
+            .last()
+            .filter(|_| has_more)
+            .map(|user| encode_cursor(&query.position_of(user))),
+        items: users
+            .into_iter()
+            .map(|user| selection.select(PublicUser::from(user)))
+            .collect::<Result<Vec<_>>>()?,
+        total: repo.count_by_query(&query).await?,
+        limit,
+        offset,
+    };
+
+    Ok(HttpResponse::Ok()
+        .insert_header((
+            header::LINK,
+            page.link_header(req.path(), req.query_string()),
+        ))
+        .json(build_success_response(
+            page,
+            "Users retrieved successfully.",
+        )))
+}
+
+/// Search parameters
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UserSearchQuery {
+    /// Words to look for in names and emails; partial words and small typos match
+    #[param(example = "jon doe")]
+    pub q: String,
+}
+
+/// Format and columns for exporting users
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    /// `csv` (default), `jsonl` or `ndjson`
+    #[param(example = "csv")]
+    pub format: Option<String>,
+    /// Comma separated columns out of `id`, `name`, `email`, `role`,
+    /// `created_at`, `updated_at`, `version` and `deleted_at`
+    #[param(example = "id,name,email")]
+    pub columns: Option<String>,
+}
+
+/// Export all users matching a filter (admin only)
+///
+/// Takes the same `filter`, `sort` and `include_deleted` parameters as
+/// `GET /users` but streams every matching user instead of one page, as CSV
+/// with a header row or as one JSON object per line. Rows are streamed from
+/// the database as they are read, so exports of any size use constant memory.
+#[utoipa::path(
+    tag = "users",
+    params(UserListQuery, ExportQuery),
+    responses(
+        (status = 200, description = "Matching users", content(
+            ("text/csv" = String),
+            ("application/jsonl" = String),
+            ("application/x-ndjson" = String)
+        )),
+        (status = 400, description = "Invalid filter, sort, format or columns", body = ErrorResponse),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Admin access required", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("session_cookie" = [])
+    )
+)]
+#[get("/users/export")]
+pub async fn export_users_file(
+    pool: web::Data<PgPool>,
+    auth: AuthData,
+    list: web::Query<UserListQuery>,
+    export: web::Query<ExportQuery>,
+) -> Result<impl Responder> {
+    AuthExtractor::check_role(&auth, &UserRole::Admin)?;
+
+    let mut query = UserQuery::parse(list.filter.as_deref(), list.sort.as_deref())?;
+    query.include_deleted = list.include_deleted == Some(true);
+    let format = export
+        .format
+        .as_deref()
+        .unwrap_or("csv")
+        .parse::<ExportFormat>()?;
+    let columns = ExportColumn::parse_list(export.columns.as_deref().unwrap_or_default())?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"users.{}\"", format.extension()),
+        ))
+        .streaming(export_users(pool.get_ref().clone(), query, format, columns)))
+}
+
+/// Search users by name and email
+///
+/// Words match as prefixes of name and email words; trigram similarity
+/// tolerates typos. Results are ranked by relevance and paginated like
+/// `GET /users`, with cursors that continue the ranked list.
+#[utoipa::path(
+    tag = "users",
+    params(UserSearchQuery, PageQuery),
+    responses(
+        (status = 200, description = "Matching users retrieved successfully", body = UserSearchPageResponse),
+        (status = 400, description = "Invalid search text or cursor", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+#[get("/users/search", wrap = "ConditionalGet::new(CachePolicy::private(0))")]
+pub async fn search_users(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    search: web::Query<UserSearchQuery>,
+    page: web::Query<PageQuery>,
+    fields: web::Query<FieldsQuery>,
+) -> Result<impl Responder> {
+    let selection = Selection::parse(&fields, &USER_SEARCH_FIELDS)?;
+    let search = UserSearch::parse(&search.q)?;
+    let repo = UserRepository { pool: &pool };
+    let limit = page.limit();
+
+    // Ranked results have no stable sort key, so cursors carry the offset
+    let offset = match &page.cursor {
+        Some(cursor) => decode_cursor::<i64>(cursor)?.max(0),
+        None => page.offset(),
+    };
+
+    let prefix_query = search.prefix_query();
+    let mut hits = repo
+        .search(&search.text, &prefix_query, offset, limit + 1)
+        .await?;
+    let has_more = hits.len() as i64 > limit;
+    hits.truncate(limit as usize);
+
+    let page = Page {
+        items: hits
+            .into_iter()
+            .map(|hit| selection.select(search.result(hit)))
+            .collect::<Result<Vec<_>>>()?,
+        total: repo.count_search(&search.text, &prefix_query).await?,
+        limit,
+        offset: Some(offset),
+        next_cursor: has_more.then(|| encode_cursor(&(offset + limit))),
+    };
+
+    Ok(HttpResponse::Ok()
+        .insert_header((
+            header::LINK,
+            page.link_header(req.path(), req.query_string()),
+        ))
+        .json(build_success_response(
+            page,
+            "Users retrieved successfully.",
+        )))
 }
 
 /// Request payload for creating a new user
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct CreateUserPayload {
     /// Full name of the user
+    #[validate(
+        required(message = "Name is required."),
+        length(min = 1, message = "Name cannot be empty.")
+    )]
     #[schema(example = "John Doe")]
     pub name: Option<String>,
     /// Email address of the user
+    #[validate(
+        required(message = "Email is required."),
+        custom(function = "email_format", message = "Invalid email format.")
+    )]
     #[schema(example = "john.doe@example.com")]
     pub email: Option<String>,
 }
 
 /// Create a new user in the system
+///
+/// Deprecated by v2, which answers with the public view of the user.
 #[utoipa::path(
-    post,
-    path = "/users",
     tag = "users",
     request_body = CreateUserPayload,
     responses(
-        (status = 201, description = "User created successfully", body = User),
+        (status = 201, description = "User created successfully", body = StoredUserResponse),
         (status = 400, description = "Invalid request payload", body = ErrorResponse),
         (status = 409, description = "Email already exists", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse)
     )
 )]
-#[post("/users")]
+#[post("/users", wrap = "Deprecated::by(ApiVersion::V2)")]
 pub async fn create_user(
     pool: web::Data<PgPool>,
-    payload: web::Json<CreateUserPayload>,
-) -> impl Responder {
+    payload: ValidatedJson<CreateUserPayload>,
+) -> Result<impl Responder> {
+    let user = insert_user(&pool, payload.into_inner()).await?;
+    Ok(HttpResponse::Created().json(build_success_response(user, "User created successfully.")))
+}
+
+/// Create a user from a validated payload, mapping a taken email to
+/// `AppError::Conflict`
+pub(crate) async fn insert_user(pool: &PgPool, payload: CreateUserPayload) -> Result<User> {
+    // Both are required by validation
+    let name = payload.name.unwrap_or_default();
+    let email = payload.email.unwrap_or_default();
+
+    let repo = UserRepository { pool };
+    repo.create_user(&name, &email)
+        .await
+        .map_err(map_email_conflict)
+}
+
+/// Allow users to act on their own account, otherwise require `role`
+fn check_self_or_role(auth: &AuthData, user_id: Uuid, role: &UserRole) -> Result<()> {
+    if auth.principal == Principal::User && auth.user_id == user_id {
+        return Ok(());
+    }
+    AuthExtractor::check_role(auth, role)
+}
+
+/// Fetch a user, mapping a missing row to `AppError::NotFound`
+async fn find_user(repo: &UserRepository<'_>, id: Uuid) -> Result<PublicUser> {
+    match repo.find_by_id(id).await {
+        Ok(user) => Ok(user.into()),
+        Err(sqlx::Error::RowNotFound) => Err(AppError::NotFound {
+            resource: "User".to_string(),
+        }),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Get a user by ID (self, moderator or admin)
+///
+/// The `ETag` header carries the user's version for `If-Match` on updates.
+/// Responses shaped by `fields` or `expand` carry a weak `ETag` of their
+/// content instead.
+#[utoipa::path(
+    tag = "users",
+    params(("id" = Uuid, Path, description = "User ID"), FieldsQuery),
+    responses(
+        (status = 200, description = "User retrieved successfully", body = UserResponse,
+            headers(("ETag" = String, description = "Version of the user"))),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Not allowed to view this user", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("session_cookie" = [])
+    )
+)]
+#[get("/users/{id}", wrap = "ConditionalGet::new(CachePolicy::private(0))")]
+pub async fn get_user(
+    pool: web::Data<PgPool>,
+    auth: AuthData,
+    path: web::Path<Uuid>,
+    fields: web::Query<FieldsQuery>,
+) -> Result<impl Responder> {
+    let selection = Selection::parse(&fields, &USER_FIELDS)?;
+    let id = path.into_inner();
+    check_self_or_role(&auth, id, &UserRole::Moderator)?;
+
+    let repo = UserRepository { pool: &pool };
+    let user = find_user(&repo, id).await?;
+
+    let mut response = HttpResponse::Ok();
+    if selection.is_full() {
+        response.insert_header(header::ETag(entity_tag(user.version)));
+    }
+    Ok(response
+        .insert_header(last_modified(user.updated_at))
+        .json(build_success_response(
+            selection.select(user)?,
+            "User retrieved successfully",
+        )))
+}
+
+/// Update a user's name or email (self or admin)
+#[utoipa::path(
+    tag = "users",
+    params(
+        ("id" = Uuid, Path, description = "User ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag of the user being changed")
+    ),
+    request_body = UpdateUserRequest,
+    responses(
+        (status = 200, description = "User updated successfully", body = UserResponse,
+            headers(("ETag" = String, description = "New version of the user"))),
+        (status = 400, description = "Invalid request payload", body = ErrorResponse),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Not allowed to update this user", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 409, description = "Email already exists", body = ErrorResponse),
+        (status = 412, description = "User was modified since it was fetched", body = ErrorResponse),
+        (status = 428, description = "If-Match header required", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("session_cookie" = [])
+    )
+)]
+#[patch("/users/{id}")]
+pub async fn update_user(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    concurrency: Option<web::Data<ConcurrencyConfig>>,
+    auth: AuthData,
+    path: web::Path<Uuid>,
+    payload: ValidatedJson<UpdateUserRequest>,
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    check_self_or_role(&auth, id, &UserRole::Admin)?;
+
     let payload = payload.into_inner();
+    if payload.name.is_none() && payload.email.is_none() {
+        return Err(AppError::Validation {
+            message: "At least one of name or email is required".to_string(),
+            errors: Vec::new(),
+        });
+    }
 
-    // Deserialization errors
-    let name = match payload.name {
-        Some(name) => name,
-        None => {
-            return HttpResponse::BadRequest()
-                .json(build_error_response("bad_request", "Name is required."))
-        }
-    };
-    let email = match payload.email {
-        Some(email) => email,
-        None => {
-            return HttpResponse::BadRequest()
-                .json(build_error_response("bad_request", "Email is required."))
+    let repo = UserRepository { pool: &pool };
+    let current = find_user(&repo, id).await?;
+    let expected = expected_version(
+        &req,
+        current.version,
+        concurrency.as_ref().map(|c| c.get_ref()),
+    )?;
+
+    if let Some(email) = &payload.email {
+        if repo.find_by_email(email).await.is_ok_and(|u| u.id != id) {
+            return Err(email_taken());
         }
+    }
+
+    // The check above can race with another write of the same email
+    let (name, email) = (payload.name.as_deref(), payload.email.as_deref());
+    let user = match expected {
+        Some(version) => repo
+            .update_user_if_version(id, version, name, email)
+            .await
+            .map_err(map_email_conflict)?
+            .ok_or_else(precondition_failed)?,
+        None => repo
+            .update_user(id, name, email)
+            .await
+            .map_err(map_email_conflict)?,
     };
 
-    // Validate input
-    if name.trim().is_empty() {
-        return HttpResponse::BadRequest()
-            .json(build_error_response("bad_request", "Name cannot be empty."));
-    }
-    if !is_valid_email(&email) {
-        return HttpResponse::BadRequest()
-            .json(build_error_response("bad_request", "Invalid email format."));
+    Ok(HttpResponse::Ok()
+        .insert_header(header::ETag(entity_tag(user.version)))
+        .json(build_success_response(
+            PublicUser::from(user),
+            "User updated successfully",
+        )))
+}
+
+/// Change a user's role (admin only)
+#[utoipa::path(
+    tag = "users",
+    params(
+        ("id" = Uuid, Path, description = "User ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag of the user being changed")
+    ),
+    request_body = UpdateRoleRequest,
+    responses(
+        (status = 200, description = "Role updated successfully", body = UserResponse,
+            headers(("ETag" = String, description = "New version of the user"))),
+        (status = 400, description = "Invalid role", body = ErrorResponse),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Admin access required", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 412, description = "User was modified since it was fetched", body = ErrorResponse),
+        (status = 428, description = "If-Match header required", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("session_cookie" = [])
+    )
+)]
+#[put("/users/{id}/role")]
+pub async fn update_user_role(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    concurrency: Option<web::Data<ConcurrencyConfig>>,
+    auth: AuthData,
+    path: web::Path<Uuid>,
+    payload: ValidatedJson<UpdateRoleRequest>,
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    AuthExtractor::check_role(&auth, &UserRole::Admin)?;
+
+    let role = payload
+        .role
+        .parse::<UserRole>()
+        .map_err(|e| AppError::Validation {
+            message: e,
+            errors: Vec::new(),
+        })?;
+
+    // Keep admins from locking themselves out
+    if auth.principal == Principal::User && auth.user_id == id && role != UserRole::Admin {
+        return Err(AppError::Validation {
+            message: "Admins cannot remove their own admin role".to_string(),
+            errors: Vec::new(),
+        });
     }
 
     let repo = UserRepository { pool: &pool };
-    match repo.create_user(&name, &email).await {
-        Ok(user) => {
-            HttpResponse::Created().json(build_success_response(user, "User created successfully."))
+    let current = find_user(&repo, id).await?;
+    let user = match expected_version(
+        &req,
+        current.version,
+        concurrency.as_ref().map(|c| c.get_ref()),
+    )? {
+        Some(version) => repo
+            .update_user_role_if_version(id, version, &role)
+            .await?
+            .ok_or_else(precondition_failed)?,
+        None => repo.update_user_role(id, &role).await?,
+    };
+
+    Ok(HttpResponse::Ok()
+        .insert_header(header::ETag(entity_tag(user.version)))
+        .json(build_success_response(
+            PublicUser::from(user),
+            "Role updated successfully",
+        )))
+}
+
+/// Delete a user (self or admin)
+///
+/// Users are soft deleted: they disappear from all reads but can be
+/// restored by an admin until the purge retention period has passed.
+#[utoipa::path(
+    tag = "users",
+    params(
+        ("id" = Uuid, Path, description = "User ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag of the user being changed")
+    ),
+    responses(
+        (status = 204, description = "User deleted successfully"),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Not allowed to delete this user", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 412, description = "User was modified since it was fetched", body = ErrorResponse),
+        (status = 428, description = "If-Match header required", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("session_cookie" = [])
+    )
+)]
+#[delete("/users/{id}")]
+pub async fn delete_user(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    concurrency: Option<web::Data<ConcurrencyConfig>>,
+    auth: AuthData,
+    path: web::Path<Uuid>,
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    check_self_or_role(&auth, id, &UserRole::Admin)?;
+
+    let repo = UserRepository { pool: &pool };
+    let current = find_user(&repo, id).await?;
+    match expected_version(
+        &req,
+        current.version,
+        concurrency.as_ref().map(|c| c.get_ref()),
+    )? {
+        Some(version) => {
+            if !repo.delete_user_if_version(id, version).await? {
+                return Err(precondition_failed());
+            }
         }
-        Err(sqlx::Error::Database(db_err)) => {
-            if db_err.constraint() == Some("users_email_key") {
-                HttpResponse::Conflict().json(build_error_response("conflict", "Email is in use."))
-            } else {
-                HttpResponse::InternalServerError().json(build_error_response(
-                    "internal_server_error",
-                    "Database error.",
-                ))
+        None => {
+            if !repo.delete_user(id).await? {
+                return Err(AppError::NotFound {
+                    resource: "User".to_string(),
+                });
             }
         }
-        Err(_) => HttpResponse::InternalServerError().json(build_error_response(
-            "internal_server_error",
-            "Unexpected error.",
-        )),
     }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Restore a soft deleted user (admin only)
+#[utoipa::path(
+    tag = "users",
+    params(("id" = Uuid, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "User restored successfully", body = UserResponse),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Admin access required", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 409, description = "User is not deleted or the email is in use", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("session_cookie" = [])
+    )
+)]
+#[post("/users/{id}/restore")]
+pub async fn restore_user(
+    pool: web::Data<PgPool>,
+    auth: AuthData,
+    path: web::Path<Uuid>,
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    AuthExtractor::check_role(&auth, &UserRole::Admin)?;
+
+    let repo = UserRepository { pool: &pool };
+    let user = match repo.find_by_id_including_deleted(id).await {
+        Ok(user) => user,
+        Err(sqlx::Error::RowNotFound) => {
+            return Err(AppError::NotFound {
+                resource: "User".to_string(),
+            })
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if user.deleted_at.is_none() {
+        return Err(AppError::Conflict {
+            message: "User is not deleted".to_string(),
+        });
+    }
+    if repo.email_exists(&user.email).await? {
+        return Err(email_taken());
+    }
+
+    let user = repo.restore_user(id).await.map_err(map_email_conflict)?;
+
+    Ok(HttpResponse::Ok().json(build_success_response(
+        PublicUser::from(user),
+        "User restored successfully",
+    )))
+}
+
+/// Create, update, re-role or delete many users in one request (admin only)
+///
+/// In `atomic` mode (the default) either every operation is applied or, on
+/// the first failure, none are; the response is then 422. In `best_effort`
+/// mode successful operations are kept and a partial failure answers 207.
+/// Each result carries the status and error body the operation would have
+/// had on its own endpoint. Updates and deletes may carry the user's
+/// `version`, which is required when `If-Match` is.
+#[utoipa::path(
+    tag = "users",
+    request_body = BulkRequest,
+    responses(
+        (status = 200, description = "All operations succeeded", body = BulkResultResponse),
+        (status = 207, description = "Some operations failed in best-effort mode", body = BulkResultResponse),
+        (status = 400, description = "No operations or too many", body = ErrorResponse),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Admin access required", body = ErrorResponse),
+        (status = 422, description = "An operation failed and all were rolled back", body = BulkResultResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("session_cookie" = [])
+    )
+)]
+#[post("/users/bulk")]
+pub async fn bulk_users(
+    pool: web::Data<PgPool>,
+    concurrency: Option<web::Data<ConcurrencyConfig>>,
+    auth: AuthData,
+    payload: ValidatedJson<BulkRequest>,
+) -> Result<impl Responder> {
+    AuthExtractor::check_role(&auth, &UserRole::Admin)?;
+
+    let actor_id = (auth.principal == Principal::User).then_some(auth.user_id);
+    let version_required = concurrency.is_some_and(|c| c.if_match_required);
+    let response: BulkResponse = bulk::run(&pool, actor_id, payload.into_inner(), version_required)
+        .await?
+        .into();
+
+    let (status, message) = match (response.failed, response.mode) {
+        (0, _) => (StatusCode::OK, "Bulk operations completed"),
+        (_, BulkMode::BestEffort) => (
+            StatusCode::MULTI_STATUS,
+            "Bulk operations completed with failures",
+        ),
+        (_, BulkMode::Atomic) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Bulk operations rolled back",
+        ),
+    };
+    Ok(HttpResponse::build(status).json(build_success_response(response, message)))
+}
+
+/// Options for importing users
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportQuery {
+    /// `csv` or `jsonl`; defaults to the `Content-Type` of the body
+    #[param(example = "csv")]
+    pub format: Option<String>,
+    /// Validate and report without inserting
+    pub dry_run: Option<bool>,
+    /// Insert the valid rows even if other rows are invalid
+    pub skip_invalid: Option<bool>,
+    /// Column mapping as `source:field` pairs
+    #[param(example = "mail:email,full_name:name")]
+    pub columns: Option<String>,
+}
+
+/// Import users from a CSV or JSONL body (admin only)
+///
+/// Columns (or JSON keys) named `name`, `email`, `password` and `role` are
+/// used as is; others can be mapped with `columns`. Every row is validated
+/// like `POST /users` and checked for duplicate emails, and the report lists
+/// the problems by line. If any row is invalid nothing is imported and the
+/// response is 422, unless `skip_invalid` is set. Rows are inserted in
+/// batches; if a batch fails its rows are listed in the report and the
+/// response is 207.
+#[utoipa::path(
+    tag = "users",
+    params(ImportQuery),
+    request_body(content = String, content_type = "text/csv", description = "CSV with a header row, or one JSON object per line"),
+    responses(
+        (status = 200, description = "Import report", body = ImportReportResponse),
+        (status = 207, description = "Some batches failed; their rows were not imported", body = ImportReportResponse),
+        (status = 400, description = "Unknown format or column mapping", body = ErrorResponse),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Admin access required", body = ErrorResponse),
+        (status = 422, description = "Invalid rows; nothing was imported", body = ImportReportResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("session_cookie" = [])
+    )
+)]
+#[post("/users/import")]
+pub async fn import_users_file(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    auth: AuthData,
+    query: web::Query<ImportQuery>,
+    limits: Option<web::Data<BodyLimitConfig>>,
+    payload: web::Payload,
+) -> Result<impl Responder> {
+    AuthExtractor::check_role(&auth, &UserRole::Admin)?;
+    let limit = limits.map_or(BodyLimitConfig::default().upload_bytes, |limits| {
+        limits.upload_bytes
+    });
+    let body = read_body(payload, limit).await?;
+
+    let format = match query.format.as_deref() {
+        Some(format) => format,
+        None => req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(';').next())
+            .unwrap_or_default(),
+    }
+    .trim()
+    .parse::<ImportFormat>()?;
+    let options = ImportOptions {
+        columns: ColumnMap::parse(query.columns.as_deref().unwrap_or_default())?,
+        dry_run: query.dry_run.unwrap_or(false),
+        skip_invalid: query.skip_invalid.unwrap_or(false),
+        ..Default::default()
+    };
+
+    let report = import_users(&pool, &body, format, &options, |inserted, total| {
+        log::info!("Imported {}/{} users", inserted, total);
+    })
+    .await?;
+
+    let (status, message) = if options.dry_run {
+        (StatusCode::OK, "Import validated")
+    } else if report.invalid > 0 && !options.skip_invalid {
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Import rejected because of invalid rows",
+        )
+    } else if report.failed > 0 {
+        (
+            StatusCode::MULTI_STATUS,
+            "Import completed with failed batches",
+        )
+    } else {
+        (StatusCode::OK, "Users imported")
+    };
+    Ok(HttpResponse::build(status).json(build_success_response(report, message)))
 }
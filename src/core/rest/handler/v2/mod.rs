@@ -0,0 +1,4 @@
+//! Handlers that differ in v2. Every other v2 route is served by the v1
+//! handler.
+
+pub mod users;
@@ -0,0 +1,46 @@
+use crate::core::{
+    domain::{error::Result, users::model::PublicUser},
+    rest::handler::{
+        body::ValidatedJson,
+        precondition::entity_tag,
+        response::build_success_response,
+        users::{insert_user, CreateUserPayload},
+    },
+};
+use actix_web::{http::header, post, web, HttpRequest, HttpResponse, Responder};
+use sqlx::PgPool;
+
+/// Create a new user in the system
+///
+/// Answers with the public view of the user, its `Location` and an `ETag`
+/// for `If-Match` on updates.
+#[utoipa::path(
+    tag = "users",
+    request_body = CreateUserPayload,
+    responses(
+        (status = 201, description = "User created successfully", body = UserResponse,
+            headers(
+                ("Location" = String, description = "URL of the user"),
+                ("ETag" = String, description = "Version of the user")
+            )),
+        (status = 400, description = "Invalid request payload", body = ErrorResponse),
+        (status = 409, description = "Email already exists", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+#[post("/users")]
+pub async fn create_user(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    payload: ValidatedJson<CreateUserPayload>,
+) -> Result<impl Responder> {
+    let user: PublicUser = insert_user(&pool, payload.into_inner()).await?.into();
+
+    Ok(HttpResponse::Created()
+        .insert_header((
+            header::LOCATION,
+            format!("{}/{}", req.path().trim_end_matches('/'), user.id),
+        ))
+        .insert_header(header::ETag(entity_tag(user.version)))
+        .json(build_success_response(user, "User created successfully.")))
+}
@@ -0,0 +1,219 @@
+//! Version scopes and deprecated routes.
+//!
+//! [`ApiVersioning`] wraps the scopes of an [`ApiVersion`]: it rejects
+//! unknown `Api-Version` values, makes the version being served available
+//! to handlers as a request extension and names it in the `Api-Version`
+//! response header.
+//!
+//! Routes replaced in a later version are marked with [`Deprecated`]:
+//!
+//! ```text
+//! #[post("/users", wrap = "Deprecated::by(ApiVersion::V2)")]
+//! ```
+//!
+//! Their responses carry `Deprecation` (RFC 9745) from the successor's
+//! release, `Sunset` (RFC 8594) when one is configured for the version and a
+//! `successor-version` `Link` to the same route in the successor.
+
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{self, HeaderValue, HttpDate},
+    web, Error, HttpMessage,
+};
+use chrono::NaiveTime;
+use futures::future::{ok, Ready};
+use std::{future::Future, pin::Pin, rc::Rc, time::SystemTime};
+
+use crate::{
+    config::ApiVersionConfig,
+    core::{
+        domain::error::AppError,
+        rest::version::{ApiVersion, API_VERSION_HEADER},
+    },
+};
+
+/// Version scope middleware, see the module documentation
+pub struct ApiVersioning(pub ApiVersion);
+
+impl<S, B> Transform<S, ServiceRequest> for ApiVersioning
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = ApiVersioningMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ApiVersioningMiddleware {
+            service: Rc::new(service),
+            version: self.0,
+        })
+    }
+}
+
+pub struct ApiVersioningMiddleware<S> {
+    service: Rc<S>,
+    version: ApiVersion,
+}
+
+impl<S, B> Service<ServiceRequest> for ApiVersioningMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let version = self.version;
+
+        Box::pin(async move {
+            if let Some(Err(value)) = ApiVersion::requested(req.headers()) {
+                return Err(unsupported_version(&value).into());
+            }
+            req.extensions_mut().insert(version);
+
+            let mut res = service.call(req).await?;
+            res.headers_mut().insert(
+                API_VERSION_HEADER,
+                HeaderValue::from(u16::from(version.number())),
+            );
+            Ok(res)
+        })
+    }
+}
+
+fn unsupported_version(value: &str) -> AppError {
+    let supported: Vec<String> = ApiVersion::ALL
+        .iter()
+        .map(|version| version.number().to_string())
+        .collect();
+    AppError::Validation {
+        message: format!(
+            "Unsupported API version {:?}; supported versions are {}",
+            value,
+            supported.join(", ")
+        ),
+        errors: Vec::new(),
+    }
+}
+
+/// Deprecated route middleware, see the module documentation
+pub struct Deprecated {
+    successor: ApiVersion,
+}
+
+impl Deprecated {
+    /// A route replaced by `successor`
+    pub fn by(successor: ApiVersion) -> Self {
+        Self { successor }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Deprecated
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = DeprecatedMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(DeprecatedMiddleware {
+            service: Rc::new(service),
+            successor: self.successor,
+        })
+    }
+}
+
+pub struct DeprecatedMiddleware<S> {
+    service: Rc<S>,
+    successor: ApiVersion,
+}
+
+impl<S, B> Service<ServiceRequest> for DeprecatedMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let successor = self.successor;
+        let version = req.extensions().get::<ApiVersion>().copied();
+        let sunset = version
+            .zip(req.app_data::<web::Data<ApiVersionConfig>>())
+            .and_then(|(version, config)| config.sunset(version));
+        let link = format!(
+            "<{}>; rel=\"successor-version\"",
+            successor_path(req.path(), version, successor)
+        );
+
+        Box::pin(async move {
+            let mut res = service.call(req).await?;
+            let headers = res.headers_mut();
+
+            if let Some(released) = successor.released() {
+                let timestamp = released.and_time(NaiveTime::MIN).and_utc().timestamp();
+                if let Ok(value) = HeaderValue::from_str(&format!("@{}", timestamp)) {
+                    headers.insert(header::HeaderName::from_static("deprecation"), value);
+                }
+            }
+            if let Some(sunset) = sunset {
+                let sunset = SystemTime::from(sunset.and_time(NaiveTime::MIN).and_utc());
+                if let Ok(value) = HeaderValue::from_str(&HttpDate::from(sunset).to_string()) {
+                    headers.insert(header::HeaderName::from_static("sunset"), value);
+                }
+            }
+            // Appended, as handlers may set pagination links
+            if let Ok(value) = HeaderValue::from_str(&link) {
+                headers.append(header::LINK, value);
+            }
+            Ok(res)
+        })
+    }
+}
+
+/// The path of the same route in `successor`: `/api/v1/users` becomes
+/// `/api/v2/users`, and so does `/users`
+fn successor_path(path: &str, version: Option<ApiVersion>, successor: ApiVersion) -> String {
+    let unversioned = version
+        .and_then(|version| path.strip_prefix(&version.prefix()))
+        .unwrap_or(path);
+    format!("{}{}", successor.prefix(), unversioned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_successor_path() {
+        let (v1, v2) = (ApiVersion::V1, ApiVersion::V2);
+        assert_eq!(
+            successor_path("/api/v1/users", Some(v1), v2),
+            "/api/v2/users"
+        );
+        assert_eq!(successor_path("/users", Some(v1), v2), "/api/v2/users");
+        assert_eq!(successor_path("/users", None, v2), "/api/v2/users");
+    }
+}
@@ -1,63 +1,180 @@
-use actix_web::{dev::Payload, Error, FromRequest, HttpMessage, HttpRequest};
-use futures::future::{ready, Ready};
+use actix_web::{dev::Payload, http::Method, web, Error, FromRequest, HttpMessage, HttpRequest};
+use futures::future::LocalBoxFuture;
+use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::core::domain::{auth::jwt::JwtService, error::AppError, users::model::UserRole};
+use crate::{
+    config::SessionConfig,
+    core::domain::{
+        auth::{jwt::JwtService, revocation::TokenRevocationList},
+        error::AppError,
+        users::{model::UserRole, repository::UserRepository},
+    },
+    pkg::crypto::constant_time_eq,
+};
 
-/// Authentication data extracted from JWT token
+/// Authentication data extracted from JWT token or set by an authentication middleware
 #[derive(Debug, Clone)]
 pub struct AuthData {
     pub user_id: Uuid,
     pub email: String,
     pub role: UserRole,
+    pub principal: Principal,
+}
+
+/// The kind of caller a request was authenticated as
+#[derive(Debug, Clone, PartialEq)]
+pub enum Principal {
+    /// A user authenticated with a token, session or client certificate
+    User,
+    /// An internal service authenticated by a signing key or client
+    /// certificate; `key_id` identifies the credential
+    Service { name: String, key_id: String },
+}
+
+impl AuthData {
+    /// Authentication data for an internal service; services have no user ID
+    pub fn service(name: &str, key_id: &str, role: UserRole) -> Self {
+        Self {
+            user_id: Uuid::nil(),
+            email: format!("{}@service.internal", name),
+            role,
+            principal: Principal::Service {
+                name: name.to_string(),
+                key_id: key_id.to_string(),
+            },
+        }
+    }
+
+    /// The ID of the authenticated user, for endpoints that act on the
+    /// caller's own account; services are refused with 403
+    pub fn require_user(&self) -> Result<Uuid, AppError> {
+        match self.principal {
+            Principal::User => Ok(self.user_id),
+            Principal::Service { .. } => Err(AppError::Authorization {
+                message: "This endpoint is only available to users".to_string(),
+            }),
+        }
+    }
 }
 
 /// Extractor for authentication data from request
 impl FromRequest for AuthData {
     type Error = Error;
-    type Future = Ready<Result<Self, Self::Error>>;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
 
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        match req.extensions().get::<AuthData>() {
-            Some(auth_data) => ready(Ok(auth_data.clone())),
-            None => {
-                let error = AppError::Authentication {
-                    message: "Authentication data not found in request".to_string(),
-                };
-                ready(Err(error.into()))
-            }
+        if let Some(auth_data) = req.extensions().get::<AuthData>() {
+            let auth_data = auth_data.clone();
+            return Box::pin(async move { Ok(auth_data) });
         }
+
+        // Fall back to validating the request's own credentials
+        let result = match req.app_data::<web::Data<JwtService>>() {
+            Some(jwt_service) => AuthExtractor::extract_auth_data(req, jwt_service),
+            None => Err(AppError::Authentication {
+                message: "Authentication data not found in request".to_string(),
+            }),
+        };
+        let pool = req.app_data::<web::Data<PgPool>>().cloned();
+        Box::pin(async move {
+            let auth_data = result?;
+            if let Some(pool) = pool {
+                AuthExtractor::check_user_active(&pool, auth_data.user_id).await?;
+            }
+            Ok(auth_data)
+        })
     }
 }
 
+/// Where the credentials of a request came from
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenSource {
+    /// `Authorization: Bearer` header
+    Bearer,
+    /// Session cookie set by `/auth/login`
+    Cookie,
+}
+
 /// Authentication functions for extracting and validating JWT tokens
 pub struct AuthExtractor;
 
 impl AuthExtractor {
+    /// Extract the raw token from the Authorization header or, when cookie
+    /// sessions are enabled, from the session cookie
+    pub fn extract_token(req: &HttpRequest) -> Result<(String, TokenSource), AppError> {
+        if let Some(auth_header) = req.headers().get("Authorization") {
+            let auth_header = auth_header.to_str().unwrap_or_default();
+
+            // Extract token from header (remove "Bearer " prefix)
+            if !auth_header.starts_with("Bearer ") {
+                return Err(AppError::Authentication {
+                    message: "Invalid authorization format".to_string(),
+                });
+            }
+            return Ok((auth_header[7..].to_string(), TokenSource::Bearer));
+        }
+
+        req.app_data::<web::Data<SessionConfig>>()
+            .filter(|session| session.enabled)
+            .and_then(|session| req.cookie(&session.cookie_name))
+            .map(|cookie| (cookie.value().to_string(), TokenSource::Cookie))
+            .ok_or_else(|| AppError::Authentication {
+                message: "Missing Authorization header".to_string(),
+            })
+    }
+
+    /// Require the CSRF header to echo the CSRF cookie on state-changing requests
+    pub fn check_csrf(req: &HttpRequest) -> Result<(), AppError> {
+        if matches!(
+            *req.method(),
+            Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+        ) {
+            return Ok(());
+        }
+
+        let session = req
+            .app_data::<web::Data<SessionConfig>>()
+            .ok_or(AppError::Internal)?;
+        let cookie = req.cookie(&session.csrf_cookie_name);
+        let header = req
+            .headers()
+            .get(session.csrf_header_name.as_str())
+            .and_then(|h| h.to_str().ok());
+
+        match (cookie, header) {
+            (Some(cookie), Some(header))
+                if !header.is_empty()
+                    && constant_time_eq(cookie.value().as_bytes(), header.as_bytes()) =>
+            {
+                Ok(())
+            }
+            _ => Err(AppError::Authorization {
+                message: "Missing or invalid CSRF token".to_string(),
+            }),
+        }
+    }
+
     /// Extract and validate JWT token from request, return AuthData
     pub fn extract_auth_data(
         req: &HttpRequest,
         jwt_service: &JwtService,
     ) -> Result<AuthData, AppError> {
-        // Extract Authorization header
-        let auth_header = req
-            .headers()
-            .get("Authorization")
-            .and_then(|h| h.to_str().ok())
-            .ok_or_else(|| AppError::Authentication {
-                message: "Missing Authorization header".to_string(),
-            })?;
-
-        // Extract token from header (remove "Bearer " prefix)
-        if !auth_header.starts_with("Bearer ") {
-            return Err(AppError::Authentication {
-                message: "Invalid authorization format".to_string(),
-            });
+        let (token, source) = Self::extract_token(req)?;
+        if source == TokenSource::Cookie {
+            Self::check_csrf(req)?;
         }
-        let token = &auth_header[7..];
 
         // Validate token
-        let claims = jwt_service.verify_token(token)?;
+        let claims = jwt_service.verify_token(&token)?;
+
+        if let Some(revocations) = req.app_data::<web::Data<TokenRevocationList>>() {
+            if revocations.is_revoked(&claims.jti) {
+                return Err(AppError::Authentication {
+                    message: "Token has been revoked".to_string(),
+                });
+            }
+        }
 
         // Parse user role from claims
         let user_role = claims
@@ -76,9 +193,21 @@ impl AuthExtractor {
             user_id,
             email: claims.email,
             role: user_role,
+            principal: Principal::User,
         })
     }
 
+    /// Reject the token of a user that has been deleted since it was issued
+    pub async fn check_user_active(pool: &PgPool, user_id: Uuid) -> Result<(), AppError> {
+        let repo = UserRepository { pool };
+        if !repo.is_active(user_id).await? {
+            return Err(AppError::Authentication {
+                message: "User no longer exists".to_string(),
+            });
+        }
+        Ok(())
+    }
+
     /// Check if user has required role
     pub fn check_role(auth_data: &AuthData, required_role: &UserRole) -> Result<(), AppError> {
         let has_permission = match required_role {
@@ -107,6 +236,7 @@ mod tests {
             user_id: uuid::Uuid::new_v4(),
             email: "test@example.com".to_string(),
             role: UserRole::Admin,
+            principal: Principal::User,
         };
 
         // Admin should have access to all roles
@@ -118,6 +248,7 @@ mod tests {
             user_id: uuid::Uuid::new_v4(),
             email: "user@example.com".to_string(),
             role: UserRole::User,
+            principal: Principal::User,
         };
 
         // Regular user should only have user-level access
@@ -2,11 +2,16 @@ use crate::core::{
     domain::{auth::jwt::JwtService, error::AppError},
     rest::middleware::auth::{AuthData, AuthExtractor},
 };
-use actix_web::{HttpMessage, HttpRequest};
+use actix_web::{web, HttpMessage, HttpRequest};
+use sqlx::PgPool;
 
 /// Guard function that validates JWT token and extracts auth data
 pub async fn auth_guard(req: &HttpRequest, jwt_service: &JwtService) -> Result<AuthData, AppError> {
-    AuthExtractor::extract_auth_data(req, jwt_service)
+    let auth_data = AuthExtractor::extract_auth_data(req, jwt_service)?;
+    if let Some(pool) = req.app_data::<web::Data<PgPool>>() {
+        AuthExtractor::check_user_active(pool, auth_data.user_id).await?;
+    }
+    Ok(auth_data)
 }
 
 /// Macro to create protected route handlers
@@ -20,7 +25,7 @@ macro_rules! protected_route {
                     req.extensions_mut().insert(auth_data);
                     $handler(req, payload).await
                 }
-                Err(error) => Ok(actix_web::HttpResponse::Unauthorized().json(error.to_string())),
+                Err(error) => Ok(actix_web::ResponseError::error_response(&error)),
             }
         }
     };
@@ -0,0 +1,199 @@
+//! Client certificate authentication for mutual TLS connections.
+//!
+//! The certificate verified during the handshake is mapped to a principal:
+//!
+//! - a DNS name or the common name listed in `TLS_CLIENT_SERVICES` becomes a
+//!   service principal with the configured role
+//! - otherwise an email address in the subject alternative names is looked
+//!   up as a user
+//!
+//! Browsers present a certificate on their own, as they do cookies, so
+//! state-changing requests of users authenticated this way must pass the
+//! same double-submit CSRF check as cookie sessions.
+//!
+//! Requests with an `Authorization` header keep using that header, so a
+//! gateway holding a client certificate can still forward user tokens.
+
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    web, Error, HttpMessage,
+};
+use futures::future::{ok, Ready};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::{collections::HashMap, future::Future, pin::Pin, rc::Rc};
+use x509_parser::{extensions::GeneralName, prelude::*};
+
+use crate::{
+    core::{
+        domain::users::{model::UserRole, repository::UserRepository},
+        rest::middleware::auth::{AuthData, AuthExtractor, Principal},
+    },
+    pkg::tls::PeerCertificate,
+};
+
+/// Names a client certificate was issued to
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientIdentity {
+    pub common_name: Option<String>,
+    pub dns_names: Vec<String>,
+    pub emails: Vec<String>,
+    /// Hex encoded SHA-256 digest of the certificate
+    pub fingerprint: String,
+}
+
+impl ClientIdentity {
+    pub fn from_der(der: &[u8]) -> Option<Self> {
+        let (_, cert) = X509Certificate::from_der(der).ok()?;
+
+        let common_name = cert
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|cn| cn.as_str().ok())
+            .map(str::to_string);
+
+        let mut identity = Self {
+            common_name,
+            fingerprint: Sha256::digest(der)
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect(),
+            ..Self::default()
+        };
+
+        if let Ok(Some(san)) = cert.subject_alternative_name() {
+            for name in &san.value.general_names {
+                match name {
+                    GeneralName::DNSName(dns) => identity.dns_names.push(dns.to_string()),
+                    GeneralName::RFC822Name(email) => identity.emails.push(email.to_string()),
+                    _ => {}
+                }
+            }
+        }
+
+        Some(identity)
+    }
+
+    /// The configured service this certificate identifies, if any
+    fn service<'a>(
+        &'a self,
+        services: &'a HashMap<String, UserRole>,
+    ) -> Option<(&'a str, &'a UserRole)> {
+        self.dns_names
+            .iter()
+            .chain(self.common_name.iter())
+            .find_map(|name| services.get_key_value(name))
+            .map(|(name, role)| (name.as_str(), role))
+    }
+}
+
+/// Middleware that authenticates requests by the client certificate of
+/// their TLS connection
+pub struct ClientCertAuth {
+    services: Rc<HashMap<String, UserRole>>,
+}
+
+impl ClientCertAuth {
+    pub fn new(services: HashMap<String, UserRole>) -> Self {
+        Self {
+            services: Rc::new(services),
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ClientCertAuth
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = ClientCertAuthMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ClientCertAuthMiddleware {
+            service: Rc::new(service),
+            services: self.services.clone(),
+        })
+    }
+}
+
+pub struct ClientCertAuthMiddleware<S> {
+    service: Rc<S>,
+    services: Rc<HashMap<String, UserRole>>,
+}
+
+impl<S, B> Service<ServiceRequest> for ClientCertAuthMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let identity = req
+            .conn_data::<PeerCertificate>()
+            .filter(|_| !req.headers().contains_key("Authorization"))
+            .filter(|_| req.extensions().get::<AuthData>().is_none())
+            .and_then(|cert| ClientIdentity::from_der(&cert.0));
+
+        let Some(identity) = identity else {
+            return Box::pin(self.service.call(req));
+        };
+
+        let service = self.service.clone();
+        let services = self.services.clone();
+
+        Box::pin(async move {
+            let pool = req.app_data::<web::Data<PgPool>>().cloned();
+            match resolve(&identity, &services, pool.as_ref().map(|p| p.get_ref())).await {
+                Some(auth_data) => {
+                    if auth_data.principal == Principal::User {
+                        AuthExtractor::check_csrf(req.request())?;
+                    }
+                    req.extensions_mut().insert(auth_data);
+                }
+                None => log::debug!(
+                    "Client certificate {} is not mapped to a principal",
+                    identity.fingerprint
+                ),
+            }
+            service.call(req).await
+        })
+    }
+}
+
+/// Map a client certificate to a service principal or an existing user
+async fn resolve(
+    identity: &ClientIdentity,
+    services: &HashMap<String, UserRole>,
+    pool: Option<&PgPool>,
+) -> Option<AuthData> {
+    if let Some((name, role)) = identity.service(services) {
+        return Some(AuthData::service(
+            name,
+            &format!("cert:{}", identity.fingerprint),
+            role.clone(),
+        ));
+    }
+
+    let repository = UserRepository { pool: pool? };
+    for email in &identity.emails {
+        if let Ok(user) = repository.find_by_email(email).await {
+            return Some(AuthData {
+                user_id: user.id,
+                role: user.get_role().ok()?,
+                email: user.email,
+                principal: Principal::User,
+            });
+        }
+    }
+    None
+}
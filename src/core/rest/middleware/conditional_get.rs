@@ -0,0 +1,225 @@
+//! Conditional GET and HTTP caching headers for read endpoints.
+//!
+//! Wrap a route with [`ConditionalGet`] and a [`CachePolicy`]:
+//!
+//! ```text
+//! #[get("/users", wrap = "ConditionalGet::new(CachePolicy::private(0))")]
+//! ```
+//!
+//! Successful `GET` responses get the policy's `Cache-Control` and `Vary`
+//! headers and, unless the handler set one, a weak `ETag` hashed from the
+//! body. Handlers may also set `Last-Modified`. Requests whose
+//! `If-None-Match` or `If-Modified-Since` still match are answered with
+//! `304 Not Modified` and no body.
+
+use actix_web::{
+    body::{self, BoxBody, MessageBody},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{
+        header::{
+            self, EntityTag, Header, HeaderMap, HeaderValue, HttpDate, IfModifiedSince, IfNoneMatch,
+        },
+        Method, StatusCode,
+    },
+    Error, HttpResponse,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use futures::future::{ok, Ready};
+use sha2::{Digest, Sha256};
+use std::{future::Future, pin::Pin, rc::Rc};
+
+use crate::core::domain::error::AppError;
+
+/// Caching headers for a route
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachePolicy {
+    pub cache_control: String,
+    pub vary: Vec<String>,
+}
+
+impl CachePolicy {
+    /// Per-user responses, cacheable by the client only and revalidated after
+    /// `max_age` seconds; varies on the credentials
+    pub fn private(max_age: u32) -> Self {
+        Self {
+            cache_control: format!("private, max-age={}", max_age),
+            vary: vec!["Authorization".to_string(), "Cookie".to_string()],
+        }
+    }
+
+    /// Responses that shared caches may store for `max_age` seconds
+    pub fn public(max_age: u32) -> Self {
+        Self {
+            cache_control: format!("public, max-age={}", max_age),
+            vary: Vec::new(),
+        }
+    }
+
+    /// Also vary the cached response on `header`
+    pub fn vary(mut self, header: &str) -> Self {
+        self.vary.push(header.to_string());
+        self
+    }
+}
+
+/// Conditional GET middleware, see the module documentation
+pub struct ConditionalGet {
+    policy: Rc<CachePolicy>,
+}
+
+impl ConditionalGet {
+    pub fn new(policy: CachePolicy) -> Self {
+        Self {
+            policy: Rc::new(policy),
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ConditionalGet
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = ConditionalGetMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ConditionalGetMiddleware {
+            service: Rc::new(service),
+            policy: self.policy.clone(),
+        })
+    }
+}
+
+pub struct ConditionalGetMiddleware<S> {
+    service: Rc<S>,
+    policy: Rc<CachePolicy>,
+}
+
+impl<S, B> Service<ServiceRequest> for ConditionalGetMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let policy = self.policy.clone();
+
+        Box::pin(async move {
+            if !matches!(*req.method(), Method::GET | Method::HEAD) {
+                return Ok(service.call(req).await?.map_into_boxed_body());
+            }
+            let if_none_match = req
+                .headers()
+                .contains_key(header::IF_NONE_MATCH)
+                .then(|| IfNoneMatch::parse(req.request()).ok())
+                .flatten();
+            let if_modified_since = IfModifiedSince::parse(req.request()).ok();
+
+            let res = service.call(req).await?;
+            if res.status() != StatusCode::OK {
+                return Ok(res.map_into_boxed_body());
+            }
+
+            let (req, res) = res.into_parts();
+            let (mut res, body) = res.into_parts();
+            let body = body::to_bytes(body).await.map_err(|_| AppError::Internal)?;
+
+            let headers = res.headers_mut();
+            apply_policy(headers, &policy);
+            let etag = match response_etag(headers) {
+                Some(etag) => etag,
+                None => {
+                    let etag = weak_etag(&body);
+                    headers.insert(
+                        header::ETAG,
+                        HeaderValue::from_str(&etag.to_string()).map_err(|_| AppError::Internal)?,
+                    );
+                    etag
+                }
+            };
+
+            let not_modified = match if_none_match {
+                // If-Modified-Since is ignored when If-None-Match is sent
+                Some(IfNoneMatch::Any) => true,
+                Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&etag)),
+                None => match (if_modified_since, last_modified(headers)) {
+                    (Some(IfModifiedSince(since)), Some(modified)) => modified <= since,
+                    _ => false,
+                },
+            };
+
+            let res = if not_modified {
+                let mut not_modified = HttpResponse::NotModified().finish();
+                for name in [
+                    header::ETAG,
+                    header::LAST_MODIFIED,
+                    header::CACHE_CONTROL,
+                    header::VARY,
+                ] {
+                    if let Some(value) = res.headers().get(&name) {
+                        not_modified.headers_mut().insert(name, value.clone());
+                    }
+                }
+                not_modified
+            } else {
+                res.set_body(BoxBody::new(body))
+            };
+
+            Ok(ServiceResponse::new(req, res))
+        })
+    }
+}
+
+/// Weak entity tag hashed from a response body
+pub fn weak_etag(body: &[u8]) -> EntityTag {
+    let digest = Sha256::digest(body);
+    EntityTag::new_weak(URL_SAFE_NO_PAD.encode(&digest[..16]))
+}
+
+fn apply_policy(headers: &mut HeaderMap, policy: &CachePolicy) {
+    if let Ok(value) = HeaderValue::from_str(&policy.cache_control) {
+        headers.insert(header::CACHE_CONTROL, value);
+    }
+    if !policy.vary.is_empty() {
+        if let Ok(value) = HeaderValue::from_str(&policy.vary.join(", ")) {
+            headers.insert(header::VARY, value);
+        }
+    }
+}
+
+/// Add `name` to the `Vary` header, keeping what is already there
+pub fn add_vary(headers: &mut HeaderMap, name: &str) {
+    let vary = match headers.get(header::VARY).and_then(|v| v.to_str().ok()) {
+        Some(vary) if vary.split(',').any(|h| h.trim().eq_ignore_ascii_case(name)) => return,
+        Some(vary) => format!("{}, {}", vary, name),
+        None => name.to_string(),
+    };
+    if let Ok(value) = HeaderValue::from_str(&vary) {
+        headers.insert(header::VARY, value);
+    }
+}
+
+fn response_etag(headers: &HeaderMap) -> Option<EntityTag> {
+    headers.get(header::ETAG)?.to_str().ok()?.parse().ok()
+}
+
+fn last_modified(headers: &HeaderMap) -> Option<HttpDate> {
+    headers
+        .get(header::LAST_MODIFIED)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
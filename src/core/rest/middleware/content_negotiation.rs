@@ -0,0 +1,239 @@
+//! Content negotiation between JSON, MessagePack and CBOR.
+//!
+//! Handlers always produce JSON. [`ContentNegotiation`] re-encodes
+//! `application/json` responses, handler errors included, into the
+//! representation preferred by the `Accept` header and answers
+//! `406 Not Acceptable` when none of the supported types is acceptable.
+//! Errors raised by middleware wrapped outside of it stay JSON. Request
+//! bodies in the same formats are decoded by the
+//! [`Body`](crate::core::rest::handler::body::Body) extractor.
+
+use actix_web::{
+    body::{self, BoxBody, MessageBody},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{
+        header::{self, Accept, Header, HeaderValue},
+        Method, StatusCode,
+    },
+    mime::Mime,
+    Error, ResponseError,
+};
+use futures::future::{ok, Ready};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{future::Future, pin::Pin, rc::Rc};
+
+use super::conditional_get::add_vary;
+use crate::core::{
+    domain::error::AppError,
+    rest::handler::extract::{invalid_body, invalid_json},
+};
+
+/// Encodings the API can read and write
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MediaType {
+    Json,
+    MsgPack,
+    Cbor,
+}
+
+impl MediaType {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            MediaType::Json => "application/json",
+            MediaType::MsgPack => "application/msgpack",
+            MediaType::Cbor => "application/cbor",
+        }
+    }
+
+    /// The encoding of a concrete media type such as a `Content-Type`.
+    ///
+    /// Structured syntax suffixes count, so `application/scim+json` is JSON.
+    pub fn from_mime(mime: &Mime) -> Option<Self> {
+        if mime.type_() != "application" {
+            return None;
+        }
+        match (mime.subtype().as_str(), mime.suffix().map(|s| s.as_str())) {
+            ("json", _) | (_, Some("json")) => Some(MediaType::Json),
+            ("msgpack" | "x-msgpack" | "vnd.msgpack", _) | (_, Some("msgpack")) => {
+                Some(MediaType::MsgPack)
+            }
+            ("cbor", _) | (_, Some("cbor")) => Some(MediaType::Cbor),
+            _ => None,
+        }
+    }
+
+    /// The encoding a media range from `Accept` selects; wildcards mean JSON
+    fn from_range(mime: &Mime) -> Option<Self> {
+        match (mime.type_().as_str(), mime.subtype().as_str()) {
+            ("*", "*") | ("application", "*") => Some(MediaType::Json),
+            _ => Self::from_mime(mime),
+        }
+    }
+
+    /// The preferred encoding for a request, or `None` if the `Accept`
+    /// header rules out every supported one. Without a (valid) `Accept`
+    /// header the response is JSON.
+    pub fn negotiate(req: &ServiceRequest) -> Option<Self> {
+        let Ok(Accept(mut ranges)) = Accept::parse(req.request()) else {
+            return Some(MediaType::Json);
+        };
+        if ranges.is_empty() {
+            return Some(MediaType::Json);
+        }
+        // Stable, so equally preferred ranges keep the client's order
+        ranges.sort_by_key(|range| std::cmp::Reverse(range.quality));
+        ranges
+            .iter()
+            .filter(|range| range.quality > header::Quality::ZERO)
+            .find_map(|range| Self::from_range(&range.item))
+    }
+
+    /// Encode a value in this representation
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>, AppError> {
+        match self {
+            MediaType::Json => serde_json::to_vec(value).map_err(|_| AppError::Internal),
+            MediaType::MsgPack => rmp_serde::to_vec_named(value).map_err(|_| AppError::Internal),
+            MediaType::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(value, &mut bytes).map_err(|_| AppError::Internal)?;
+                Ok(bytes)
+            }
+        }
+    }
+
+    /// Decode a request body in this representation. JSON and MessagePack
+    /// errors name the field that failed.
+    pub fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, AppError> {
+        match self {
+            MediaType::Json => {
+                let mut deserializer = serde_json::Deserializer::from_slice(bytes);
+                serde_path_to_error::deserialize(&mut deserializer)
+                    .map_err(|e| invalid_json(&field_path(e.path()), e.inner()))
+            }
+            MediaType::MsgPack => {
+                let mut deserializer = rmp_serde::Deserializer::new(bytes);
+                serde_path_to_error::deserialize(&mut deserializer)
+                    .map_err(|e| invalid_body(&field_path(e.path()), e.inner()))
+            }
+            MediaType::Cbor => ciborium::from_reader(bytes).map_err(|e| invalid_body("", &e)),
+        }
+    }
+}
+
+/// `user.email` or `operations[2].email`; empty for the root value
+fn field_path(path: &serde_path_to_error::Path) -> String {
+    match path.to_string().as_str() {
+        "." => String::new(),
+        path => path.to_string(),
+    }
+}
+
+/// Content negotiation middleware, see the module documentation
+pub struct ContentNegotiation;
+
+impl<S, B> Transform<S, ServiceRequest> for ContentNegotiation
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = ContentNegotiationMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ContentNegotiationMiddleware {
+            service: Rc::new(service),
+        })
+    }
+}
+
+pub struct ContentNegotiationMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for ContentNegotiationMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            let media_type = MediaType::negotiate(&req);
+
+            // Every endpoint that changes state answers JSON, so refuse
+            // before the change is made rather than after
+            if media_type.is_none() && !matches!(*req.method(), Method::GET | Method::HEAD) {
+                return Ok(req.into_response(not_acceptable().error_response()));
+            }
+
+            let res = service.call(req).await?.map_into_boxed_body();
+            negotiate_response(media_type, res).await
+        })
+    }
+}
+
+async fn negotiate_response(
+    media_type: Option<MediaType>,
+    res: ServiceResponse<BoxBody>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let is_json = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<Mime>().ok())
+        .is_some_and(|mime| mime.essence_str() == "application/json");
+    if !is_json && res.status() != StatusCode::NOT_MODIFIED {
+        return Ok(res);
+    }
+
+    let (req, mut res) = res.into_parts();
+    add_vary(res.headers_mut(), "Accept");
+    let media_type = match media_type {
+        _ if !is_json => return Ok(ServiceResponse::new(req, res)),
+        Some(MediaType::Json) => return Ok(ServiceResponse::new(req, res)),
+        Some(media_type) => media_type,
+        None => {
+            let mut res = not_acceptable().error_response();
+            add_vary(res.headers_mut(), "Accept");
+            return Ok(ServiceResponse::new(req, res));
+        }
+    };
+
+    let (mut res, body) = res.into_parts();
+    let body = body::to_bytes(body).await.map_err(|_| AppError::Internal)?;
+    let body = if body.is_empty() {
+        Vec::new()
+    } else {
+        let value: Value = serde_json::from_slice(&body).map_err(|_| AppError::Internal)?;
+        media_type.encode(&value)?
+    };
+    res.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(media_type.content_type()),
+    );
+    Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(body))))
+}
+
+fn not_acceptable() -> AppError {
+    AppError::NotAcceptable {
+        message: format!(
+            "Supported response types are {}, {} and {}",
+            MediaType::Json.content_type(),
+            MediaType::MsgPack.content_type(),
+            MediaType::Cbor.content_type()
+        ),
+    }
+}
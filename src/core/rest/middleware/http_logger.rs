@@ -1,6 +1,7 @@
+use super::request_id::RequestIdValue;
 use actix_web::{
     dev::{Service, ServiceRequest, ServiceResponse, Transform},
-    Error,
+    Error, HttpMessage,
 };
 use futures::future::{ok, Ready};
 use log::info;
@@ -58,6 +59,10 @@ where
             .and_then(|value| value.to_str().ok())
             .unwrap_or("-")
             .to_string(); // Clone to make it owned
+        let request_id = req
+            .extensions()
+            .get::<RequestIdValue>()
+            .map(|id| id.0.clone());
 
         let fut = self.service.call(req);
 
@@ -76,6 +81,7 @@ where
                     "status": status,
                     "latency": latency,
                     "user_agent": user_agent,  // Use the owned User-Agent here
+                    "request_id": request_id,
                 })
             );
 
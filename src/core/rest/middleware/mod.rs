@@ -1,4 +1,10 @@
+pub mod api_version;
 pub mod auth;
 pub mod auth_guard;
+pub mod client_cert;
+pub mod conditional_get;
+pub mod content_negotiation;
 pub mod error_handler;
 pub mod http_logger;
+pub mod request_id;
+pub mod request_signing;
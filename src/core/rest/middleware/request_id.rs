@@ -0,0 +1,201 @@
+//! Request ids for correlating responses, errors and logs.
+//!
+//! [`RequestId`] takes the id from the `X-Request-Id` header when it is a
+//! reasonable token, or generates one, and echoes it in the response. While
+//! the request is handled the id is available from [`current`], which is how
+//! error responses include it without access to the request. The context
+//! also carries the locale negotiated from `Accept-Language`, in which error
+//! messages are written. Code that writes localized text calls
+//! [`mark_localized`], and the response then gets `Content-Language` and
+//! varies on `Accept-Language`.
+
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    error::InternalError,
+    http::header::{
+        self, Accept, AcceptLanguage, Header, HeaderMap, HeaderName, HeaderValue, Quality,
+    },
+    web, Error, HttpMessage,
+};
+use futures::future::{ok, Ready};
+use std::{cell::Cell, future::Future, pin::Pin, rc::Rc};
+use uuid::Uuid;
+
+use super::conditional_get::add_vary;
+use crate::{
+    config::LocaleConfig,
+    pkg::i18n::{self, LanguageIdentifier},
+};
+
+/// Header carrying the request id in both directions
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest client supplied request id that is kept
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+/// The request being handled, as seen by code without access to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestContext {
+    pub id: String,
+    pub path: String,
+    /// The client accepts `application/problem+json` error responses
+    pub problem_json: bool,
+    /// Locale of error messages
+    pub locale: LanguageIdentifier,
+}
+
+tokio::task_local! {
+    static REQUEST: RequestContext;
+    static LOCALIZED: Cell<bool>;
+}
+
+/// Context of the request being handled by the current task, if any
+pub fn current() -> Option<RequestContext> {
+    REQUEST.try_with(Clone::clone).ok()
+}
+
+/// Record that the response of the current request holds text in its
+/// locale
+pub fn mark_localized() {
+    let _ = LOCALIZED.try_with(|localized| localized.set(true));
+}
+
+/// The id of a request, also stored in the request extensions
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestIdValue(pub String);
+
+/// Request id middleware, see the module documentation
+pub struct RequestId;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestId
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequestIdMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequestIdMiddleware {
+            service: Rc::new(service),
+        })
+    }
+}
+
+pub struct RequestIdMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let context = RequestContext {
+            id: req
+                .headers()
+                .get(&REQUEST_ID_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .filter(|id| is_valid_id(id))
+                .map(str::to_string)
+                .unwrap_or_else(|| Uuid::new_v4().to_string()),
+            path: req.path().to_string(),
+            problem_json: accepts_problem_json(&req),
+            locale: negotiate_locale(&req),
+        };
+        req.extensions_mut()
+            .insert(RequestIdValue(context.id.clone()));
+        let header_value = HeaderValue::from_str(&context.id).ok();
+        let locale = HeaderValue::from_str(&context.locale.to_string()).ok();
+
+        let response = async move {
+            match service.call(req).await {
+                Ok(mut res) => {
+                    add_headers(res.headers_mut(), header_value, locale);
+                    Ok(res)
+                }
+                // Render errors from other middleware while the request id
+                // is still known
+                Err(e) => {
+                    let mut res = e.error_response();
+                    add_headers(res.headers_mut(), header_value, locale);
+                    Err(InternalError::from_response(e, res).into())
+                }
+            }
+        };
+        Box::pin(REQUEST.scope(context, LOCALIZED.scope(Cell::new(false), response)))
+    }
+}
+
+/// The request id, and the language of localized responses
+fn add_headers(headers: &mut HeaderMap, id: Option<HeaderValue>, locale: Option<HeaderValue>) {
+    if let Some(value) = id {
+        headers.insert(REQUEST_ID_HEADER, value);
+    }
+    if let (true, Some(value)) = (LOCALIZED.with(Cell::get), locale) {
+        headers.insert(header::CONTENT_LANGUAGE, value);
+        add_vary(headers, "Accept-Language");
+    }
+}
+
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LENGTH
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+}
+
+fn accepts_problem_json(req: &ServiceRequest) -> bool {
+    Accept::parse(req.request()).is_ok_and(|Accept(ranges)| {
+        ranges.iter().any(|range| {
+            range.quality > Quality::ZERO && range.item.essence_str() == "application/problem+json"
+        })
+    })
+}
+
+/// The catalog locale preferred by `Accept-Language`, or the configured
+/// default
+fn negotiate_locale(req: &ServiceRequest) -> LanguageIdentifier {
+    let default = req
+        .app_data::<web::Data<LocaleConfig>>()
+        .and_then(|config| i18n::parse(&config.default_locale))
+        .unwrap_or(i18n::SOURCE_LOCALE);
+    let Ok(AcceptLanguage(mut ranges)) = AcceptLanguage::parse(req.request()) else {
+        return default;
+    };
+    // Stable, so equally preferred languages keep the client's order
+    ranges.sort_by_key(|range| std::cmp::Reverse(range.quality));
+    let tags: Vec<String> = ranges
+        .iter()
+        .filter(|range| range.quality > Quality::ZERO)
+        .map(|range| range.item.to_string())
+        .collect();
+    i18n::negotiate(tags.iter().map(String::as_str), &default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_ids() {
+        assert!(is_valid_id("3f2a-b1_c.9"));
+        assert!(!is_valid_id(""));
+        assert!(!is_valid_id("id with spaces"));
+        assert!(!is_valid_id(&"a".repeat(MAX_REQUEST_ID_LENGTH + 1)));
+    }
+}
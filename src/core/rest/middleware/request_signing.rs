@@ -0,0 +1,352 @@
+//! HMAC request signing for machine-to-machine calls.
+//!
+//! A signed request carries:
+//!
+//! ```text
+//! Authorization: HMAC-SHA256 keyId="billing-1",headers="host content-type",signature="<base64>"
+//! X-Signature-Timestamp: 1767225600
+//! X-Signature-Nonce: 5f0c2a4e-0b6a-4a8e-9f3e-1c2d3e4f5a6b
+//! ```
+//!
+//! The signature is the base64 encoded HMAC-SHA256 over [`string_to_sign`]:
+//! the method, path and query, timestamp, nonce, each listed header as
+//! `name:value`, and the base64 SHA-256 digest of the body, joined by `\n`.
+
+use actix_web::{
+    dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    web::{self, Bytes},
+    Error, HttpMessage,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use futures::future::{ok, Ready};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    config::{BodyLimitConfig, SigningConfig, SigningKey},
+    core::{
+        domain::error::AppError,
+        rest::{handler::body::read_body, middleware::auth::AuthData},
+    },
+    pkg::crypto::constant_time_eq,
+};
+
+pub const SIGNATURE_SCHEME: &str = "HMAC-SHA256";
+pub const TIMESTAMP_HEADER: &str = "X-Signature-Timestamp";
+pub const NONCE_HEADER: &str = "X-Signature-Nonce";
+
+/// Nonces seen within the accepted clock skew, shared by all workers
+#[derive(Clone, Default)]
+pub struct NonceCache {
+    seen: Arc<Mutex<HashMap<String, i64>>>,
+}
+
+impl NonceCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a nonce, returning `false` if it was already used
+    pub fn insert(&self, nonce: String, now: i64, ttl: i64) -> bool {
+        let mut seen = self.seen.lock().unwrap();
+        seen.retain(|_, expires_at| *expires_at > now);
+        if seen.contains_key(&nonce) {
+            return false;
+        }
+        seen.insert(nonce, now + ttl);
+        true
+    }
+}
+
+/// Build the canonical string covered by a request signature
+pub fn string_to_sign(
+    method: &str,
+    path_and_query: &str,
+    timestamp: &str,
+    nonce: &str,
+    headers: &[(String, String)],
+    body: &[u8],
+) -> String {
+    let mut lines = vec![
+        method.to_uppercase(),
+        path_and_query.to_string(),
+        timestamp.to_string(),
+        nonce.to_string(),
+    ];
+    lines.extend(
+        headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}", name.to_lowercase(), value.trim())),
+    );
+    lines.push(STANDARD.encode(Sha256::digest(body)));
+    lines.join("\n")
+}
+
+/// Sign a canonical string with a shared secret
+pub fn sign(secret: &str, string_to_sign: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(string_to_sign.as_bytes());
+    STANDARD.encode(mac.finalize().into_bytes())
+}
+
+/// Parameters of an `Authorization: HMAC-SHA256 ...` header
+struct SignatureParams {
+    key_id: String,
+    headers: Vec<String>,
+    signature: String,
+}
+
+impl SignatureParams {
+    fn parse(value: &str) -> Option<Self> {
+        let params = value.strip_prefix(SIGNATURE_SCHEME)?.trim();
+        let mut key_id = None;
+        let mut headers = Vec::new();
+        let mut signature = None;
+
+        for param in params.split(',') {
+            let (name, value) = param.trim().split_once('=')?;
+            let value = value.trim().trim_matches('"');
+            match name.trim() {
+                "keyId" => key_id = Some(value.to_string()),
+                "headers" => headers = value.split_whitespace().map(|h| h.to_lowercase()).collect(),
+                "signature" => signature = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            key_id: key_id?,
+            headers,
+            signature: signature?,
+        })
+    }
+}
+
+/// Middleware that authenticates requests carrying an HMAC signature and
+/// exposes the signing service as the request's `AuthData`
+pub struct RequestSigning {
+    config: Rc<SigningConfig>,
+    nonces: NonceCache,
+}
+
+impl RequestSigning {
+    pub fn new(config: SigningConfig, nonces: NonceCache) -> Self {
+        Self {
+            config: Rc::new(config),
+            nonces,
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequestSigning
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequestSigningMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequestSigningMiddleware {
+            service: Rc::new(service),
+            config: self.config.clone(),
+            nonces: self.nonces.clone(),
+        })
+    }
+}
+
+pub struct RequestSigningMiddleware<S> {
+    service: Rc<S>,
+    config: Rc<SigningConfig>,
+    nonces: NonceCache,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestSigningMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let params = req
+            .headers()
+            .get("Authorization")
+            .and_then(|h| h.to_str().ok())
+            .filter(|h| h.starts_with(SIGNATURE_SCHEME))
+            .map(SignatureParams::parse);
+
+        // Requests without a signature are left to the other authentication methods
+        let Some(params) = params else {
+            return Box::pin(self.service.call(req));
+        };
+
+        let service = self.service.clone();
+        let config = self.config.clone();
+        let nonces = self.nonces.clone();
+
+        Box::pin(async move {
+            let params = params.ok_or_else(|| unauthorized("Malformed signature header"))?;
+            // Unknown keys and missing headers are refused before the body is read
+            let signed = check_headers(&req, &params, &config)?;
+            let body = read_body(req.take_payload(), body_limit(&req)).await?;
+            let auth_data = verify(&req, &params, signed, &body, &config, &nonces)?;
+
+            req.extensions_mut().insert(auth_data);
+            req.set_payload(Payload::from(Bytes::from(body)));
+            service.call(req).await
+        })
+    }
+}
+
+fn unauthorized(message: &str) -> AppError {
+    AppError::Authentication {
+        message: message.to_string(),
+    }
+}
+
+/// Largest body a signed request may have: it is read before the route is
+/// known, so the larger of the configured limits
+fn body_limit(req: &ServiceRequest) -> usize {
+    let limits = req
+        .app_data::<web::Data<BodyLimitConfig>>()
+        .map(|limits| limits.get_ref().clone())
+        .unwrap_or_default();
+    limits.json_bytes.max(limits.upload_bytes)
+}
+
+/// Header fields of a signed request, checked before its body is read
+struct SignedHeaders<'a> {
+    key: &'a SigningKey,
+    timestamp: String,
+    nonce: String,
+    signed_headers: Vec<(String, String)>,
+    now: i64,
+}
+
+fn check_headers<'a>(
+    req: &ServiceRequest,
+    params: &SignatureParams,
+    config: &'a SigningConfig,
+) -> Result<SignedHeaders<'a>, AppError> {
+    let key = config
+        .keys
+        .get(&params.key_id)
+        .ok_or_else(|| unauthorized("Unknown signing key"))?;
+
+    if let Some(missing) = config
+        .required_headers
+        .iter()
+        .find(|required| !params.headers.contains(&required.to_lowercase()))
+    {
+        return Err(unauthorized(&format!(
+            "Signature must cover the {} header",
+            missing
+        )));
+    }
+
+    let header = |name: &str| {
+        req.headers()
+            .get(name)
+            .and_then(|h| h.to_str().ok())
+            .map(str::to_string)
+    };
+
+    let timestamp =
+        header(TIMESTAMP_HEADER).ok_or_else(|| unauthorized("Missing signature timestamp"))?;
+    let nonce = header(NONCE_HEADER).ok_or_else(|| unauthorized("Missing signature nonce"))?;
+
+    let now = Utc::now().timestamp();
+    let signed_at: i64 = timestamp
+        .parse()
+        .map_err(|_| unauthorized("Invalid signature timestamp"))?;
+    if (now - signed_at).abs() > config.max_skew_seconds {
+        return Err(unauthorized("Signature has expired"));
+    }
+
+    let signed_headers = params
+        .headers
+        .iter()
+        .map(|name| {
+            header(name)
+                .map(|value| (name.clone(), value))
+                .ok_or_else(|| unauthorized(&format!("Signed header {} is missing", name)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(SignedHeaders {
+        key,
+        timestamp,
+        nonce,
+        signed_headers,
+        now,
+    })
+}
+
+fn verify(
+    req: &ServiceRequest,
+    params: &SignatureParams,
+    signed: SignedHeaders,
+    body: &[u8],
+    config: &SigningConfig,
+    nonces: &NonceCache,
+) -> Result<AuthData, AppError> {
+    let SignedHeaders {
+        key,
+        timestamp,
+        nonce,
+        signed_headers,
+        now,
+    } = signed;
+
+    let path_and_query = req
+        .uri()
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or("/");
+    let expected = sign(
+        &key.secret,
+        &string_to_sign(
+            req.method().as_str(),
+            path_and_query,
+            &timestamp,
+            &nonce,
+            &signed_headers,
+            body,
+        ),
+    );
+    if !constant_time_eq(expected.as_bytes(), params.signature.as_bytes()) {
+        return Err(unauthorized("Invalid request signature"));
+    }
+
+    // Only remember nonces of valid signatures so they cannot be poisoned
+    if !nonces.insert(
+        format!("{}:{}", params.key_id, nonce),
+        now,
+        config.max_skew_seconds * 2,
+    ) {
+        return Err(unauthorized("Signature nonce has already been used"));
+    }
+
+    Ok(AuthData::service(
+        &key.service,
+        &params.key_id,
+        key.role.clone(),
+    ))
+}
@@ -2,4 +2,6 @@ pub mod handler;
 pub mod middleware;
 pub mod openapi;
 pub mod router;
+pub mod scim;
 pub mod validation;
+pub mod version;
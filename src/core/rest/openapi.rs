@@ -1,59 +1,114 @@
+use crate::core::rest::{
+    router::{ScimRoutes, SharedRoutes, UnversionedRoutes, V1Routes, V2Routes},
+    scim::model::SCIM_PREFIX,
+    version::ApiVersion,
+};
 use crate::core::{
     domain::{
-        error::ErrorResponse,
-        users::model::{AuthResponse, CreateUserRequest, LoginRequest, PublicUser, User, UserRole},
+        error::{ErrorCode, FieldError},
+        users::bulk::{BulkMode, BulkOperation, BulkRequest},
+        users::import::{ImportFormat, ImportReport, ImportRowError},
+        users::model::{
+            AuthResponse, CreateUserRequest, LoginRequest, PublicUser, RoleDetails,
+            SearchHighlights, UpdateRoleRequest, UpdateUserRequest, User, UserRole,
+            UserSearchResult,
+        },
     },
     rest::handler::{
         auth::{ChangePasswordRequest, CreateUserWithRoleRequest},
-        response::{Meta, Response},
+        oauth::{IntrospectionRequest, IntrospectionResponse},
+        response::{
+            AuthTokenResponse, BulkItemResult, BulkResponse, BulkResultResponse, Empty,
+            EmptyResponse, ErrorResponse, ImportReportResponse, Meta, ProblemDetails,
+            StoredUserResponse, UserPage, UserPageResponse, UserResponse, UserSearchPage,
+            UserSearchPageResponse,
+        },
         users::CreateUserPayload,
     },
+    rest::scim::model::{
+        ScimEmail, ScimError, ScimGroup, ScimGroupList, ScimMember, ScimMeta, ScimName,
+        ScimPatchOperation, ScimPatchRequest, ScimUser, ScimUserList,
+    },
+};
+use utoipa::{
+    openapi::{path::Paths, Deprecated},
+    OpenApi,
 };
-use utoipa::OpenApi;
 
+/// Schemas, tags, security schemes and metadata shared by every version.
+/// The paths come from the route registries in
+/// [`router`](crate::core::rest::router).
 #[derive(OpenApi)]
 #[openapi(
-    paths(
-        // User management endpoints
-        crate::core::rest::handler::users::get_users,
-        crate::core::rest::handler::users::create_user,
-
-        // Authentication endpoints
-        crate::core::rest::handler::auth::register,
-        crate::core::rest::handler::auth::login,
-        crate::core::rest::handler::auth::me,
-        crate::core::rest::handler::auth::change_password,
-        crate::core::rest::handler::auth::admin_create_user,
-    ),
     components(
         schemas(
             // User models
             User,
             PublicUser,
             UserRole,
+            RoleDetails,
 
             // Request/Response types
             CreateUserRequest,
             CreateUserPayload,
+            UpdateUserRequest,
+            UpdateRoleRequest,
+            BulkRequest,
+            BulkOperation,
+            BulkMode,
+            BulkResponse,
+            BulkItemResult,
+            ImportFormat,
+            ImportReport,
+            ImportRowError,
             LoginRequest,
             AuthResponse,
             ChangePasswordRequest,
             CreateUserWithRoleRequest,
+            IntrospectionRequest,
+            IntrospectionResponse,
+
+            // SCIM resources
+            ScimUser,
+            ScimName,
+            ScimEmail,
+            ScimMember,
+            ScimGroup,
+            ScimMeta,
+            ScimUserList,
+            ScimGroupList,
+            ScimPatchRequest,
+            ScimPatchOperation,
+            ScimError,
 
             // Error handling
             ErrorResponse,
+            ErrorCode,
+            FieldError,
+            ProblemDetails,
 
             // Response wrappers
-            Response<User>,
-            Response<Vec<User>>,
-            Response<PublicUser>,
-            Response<AuthResponse>,
+            UserResponse,
+            StoredUserResponse,
+            UserPageResponse,
+            UserSearchPageResponse,
+            AuthTokenResponse,
+            BulkResultResponse,
+            ImportReportResponse,
+            EmptyResponse,
+            Empty,
+            UserPage,
+            UserSearchPage,
+            UserSearchResult,
+            SearchHighlights,
             Meta,
         )
     ),
     tags(
+        (name = "home", description = "Service status"),
         (name = "users", description = "User management endpoints"),
-        (name = "auth", description = "Authentication and authorization endpoints")
+        (name = "auth", description = "Authentication and authorization endpoints"),
+        (name = "scim", description = "SCIM 2.0 user provisioning endpoints")
     ),
     modifiers(&SecurityAddon),
     info(
@@ -72,10 +127,75 @@ use utoipa::OpenApi;
 )]
 pub struct ApiDoc;
 
-use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+/// The OpenAPI document of `version`: its versioned routes under its prefix
+/// and the unversioned ones. Operations that the next version replaces are
+/// marked deprecated.
+pub fn openapi(version: ApiVersion) -> utoipa::openapi::OpenApi {
+    let mut doc = ApiDoc::openapi();
+    doc.info.version = format!("{}.0.0", version.number());
+    doc.paths = UnversionedRoutes::openapi().paths;
+    for (path, item) in ScimRoutes::openapi().paths.paths {
+        doc.paths
+            .paths
+            .insert(format!("{}{}", SCIM_PREFIX, path), item);
+    }
+
+    let mut paths = SharedRoutes::openapi().paths;
+    for (path, item) in version_paths(version).paths {
+        let entry = paths.paths.entry(path).or_default();
+        entry.operations.extend(item.operations);
+    }
+    if let Some(successor) = version.successor() {
+        for (path, item) in version_paths(successor).paths {
+            let Some(entry) = paths.paths.get_mut(&path) else {
+                continue;
+            };
+            for method in item.operations.keys() {
+                if let Some(operation) = entry.operations.get_mut(method) {
+                    operation.deprecated = Some(Deprecated::True);
+                }
+            }
+        }
+    }
+
+    for (path, item) in paths.paths {
+        doc.paths
+            .paths
+            .insert(format!("{}{}", version.prefix(), path), item);
+    }
+    doc
+}
+
+/// Paths of the routes that only `version` serves
+fn version_paths(version: ApiVersion) -> Paths {
+    match version {
+        ApiVersion::V1 => V1Routes::openapi().paths,
+        ApiVersion::V2 => V2Routes::openapi().paths,
+    }
+}
+
+/// Method, path and summary of every operation in a document, in path order
+pub fn endpoints(doc: &utoipa::openapi::OpenApi) -> Vec<(String, String, String)> {
+    doc.paths
+        .paths
+        .iter()
+        .flat_map(|(path, item)| {
+            item.operations.iter().map(move |(method, operation)| {
+                let method = serde_json::to_value(method)
+                    .ok()
+                    .and_then(|v| v.as_str().map(str::to_uppercase))
+                    .unwrap_or_default();
+                let summary = operation.summary.clone().unwrap_or_default();
+                (method, path.clone(), summary)
+            })
+        })
+        .collect()
+}
+
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, Http, HttpAuthScheme, SecurityScheme};
 use utoipa::Modify;
 
-/// Security configuration for JWT Bearer tokens
+/// Security configuration for JWT Bearer tokens, session cookies, introspection and SCIM client credentials
 pub struct SecurityAddon;
 
 impl Modify for SecurityAddon {
@@ -84,6 +204,22 @@ impl Modify for SecurityAddon {
         components.add_security_scheme(
             "bearer_auth",
             SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
-        )
+        );
+        components.add_security_scheme(
+            "basic_auth",
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Basic)),
+        );
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-API-Key"))),
+        );
+        components.add_security_scheme(
+            "session_cookie",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new("session"))),
+        );
+        components.add_security_scheme(
+            "scim_bearer",
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+        );
     }
 }
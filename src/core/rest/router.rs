@@ -1,27 +1,192 @@
-use crate::core::rest::handler::{
-    auth::{admin_create_user, change_password, login, me, register},
-    users::{create_user, get_users},
+//! Route registration.
+//!
+//! Routes are listed once, in the registries declared with [`routes!`]. A
+//! registry registers its handlers with actix and documents them in OpenAPI,
+//! where the path and method come from the handler's actix attribute, so the
+//! document cannot drift from the routes. A handler without
+//! `#[utoipa::path]` does not compile in a registry.
+
+use crate::core::rest::handler::{auth, extract::AllowedMethods, home, oauth, scim, users, v2};
+use crate::core::rest::{
+    middleware::api_version::ApiVersioning, openapi, scim::model::SCIM_PREFIX, version::ApiVersion,
 };
-use crate::core::rest::openapi::ApiDoc;
-use actix_web::web;
-use utoipa::OpenApi;
-use utoipa_swagger_ui::SwaggerUi;
+use actix_web::{http::Method, web, HttpResponse};
+use utoipa_swagger_ui::{SwaggerUi, Url};
 
-pub fn config(cfg: &mut web::ServiceConfig) {
-    cfg
+/// Declare a route registry: a type whose OpenAPI document holds the paths
+/// of its handlers and whose `register` adds them, in order, to a service
+/// config
+macro_rules! routes {
+    ($(#[$attr:meta])* $name:ident { $($($segment:ident)::+),* $(,)? }) => {
+        $(#[$attr])*
+        #[derive(utoipa::OpenApi)]
+        #[openapi(paths($($($segment)::+),*))]
+        pub struct $name;
+
+        impl $name {
+            pub fn register(cfg: &mut web::ServiceConfig) {
+                $(cfg.service($($segment)::+);)*
+            }
+        }
+    };
+}
+
+routes! {
+    /// Routes outside the version scopes
+    UnversionedRoutes {
         // Public routes
-        .service(super::handler::home::home)
+        home::home,
+        // OAuth routes
+        oauth::introspect,
+    }
+}
+
+routes! {
+    /// SCIM provisioning routes, served under [`SCIM_PREFIX`]
+    ScimRoutes {
+        scim::scim_service_provider_config,
+        scim::scim_resource_types,
+        scim::scim_schemas,
+        scim::scim_list_users,
+        scim::scim_create_user,
+        scim::scim_get_user,
+        scim::scim_replace_user,
+        scim::scim_patch_user,
+        scim::scim_delete_user,
+        scim::scim_list_groups,
+        scim::scim_get_group,
+        scim::scim_replace_group,
+        scim::scim_patch_group,
+    }
+}
+
+routes! {
+    /// Routes every version serves with the same handler
+    SharedRoutes {
         // User management routes
-        .service(get_users)
-        .service(create_user)
+        users::get_users,
+        users::bulk_users,
+        users::import_users_file,
+        users::search_users, // Before /users/{id} so "search" is not taken as an ID
+        users::export_users_file, // Likewise for "export"
+        users::get_user,
+        users::update_user,
+        users::update_user_role,
+        users::delete_user,
+        users::restore_user,
         // Authentication routes
-        .service(register)
-        .service(login)
-        .service(me)
-        .service(change_password)
-        .service(admin_create_user)
-        // Swagger UI
-        .service(
-            SwaggerUi::new("/swagger-ui/{_:.*}").url("/api-docs/openapi.json", ApiDoc::openapi()),
+        auth::register,
+        auth::login,
+        auth::logout,
+        auth::me,
+        auth::change_password,
+        auth::admin_create_user,
+    }
+}
+
+routes! {
+    /// v1 routes that v2 replaces
+    V1Routes {
+        users::create_user,
+    }
+}
+
+routes! {
+    /// v2 routes replacing v1 ones
+    V2Routes {
+        v2::users::create_user,
+    }
+}
+
+/// Registry of the routes that only `version` serves
+fn version_routes(version: ApiVersion) -> fn(&mut web::ServiceConfig) {
+    match version {
+        ApiVersion::V1 => V1Routes::register,
+        ApiVersion::V2 => V2Routes::register,
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.app_data(allowed_methods());
+    UnversionedRoutes::register(cfg);
+    // SCIM routes, whose extractor failures are SCIM errors
+    cfg.service(
+        web::scope(SCIM_PREFIX)
+            .configure(scim::configure)
+            .configure(ScimRoutes::register),
+    );
+    // Swagger UI with a document per version
+    cfg.service(
+        SwaggerUi::new("/swagger-ui/{_:.*}").urls(
+            ApiVersion::ALL
+                .into_iter()
+                .map(|version| (document_url(version), openapi::openapi(version)))
+                .collect(),
+        ),
+    );
+    // The document's URL before there was one per version
+    cfg.route("/api-docs/openapi.json", web::get().to(default_document));
+
+    // Versioned routes, then the same routes without a prefix for the
+    // version selected by Api-Version. The unversioned scopes match every
+    // path, so they come last and the default version's comes after the
+    // others.
+    for version in ApiVersion::ALL {
+        cfg.service(
+            web::scope(&version.prefix())
+                .wrap(ApiVersioning(version))
+                .configure(versioned(version)),
+        );
+    }
+    let mut unversioned = ApiVersion::ALL.to_vec();
+    unversioned.sort_by_key(|version| *version == ApiVersion::DEFAULT);
+    for version in unversioned {
+        cfg.service(
+            web::scope("")
+                .guard(version.header_guard())
+                .wrap(ApiVersioning(version))
+                .configure(versioned(version)),
         );
+    }
+}
+
+/// Methods of the routes registered by [`config`], taken from the OpenAPI
+/// documents; unversioned paths serve the routes of every version
+fn allowed_methods() -> AllowedMethods {
+    let mut allowed = AllowedMethods::default();
+    for version in ApiVersion::ALL {
+        for (method, path, _) in openapi::endpoints(&openapi::openapi(version)) {
+            let Ok(method) = Method::from_bytes(method.as_bytes()) else {
+                continue;
+            };
+            if let Some(unversioned) = path.strip_prefix(&version.prefix()) {
+                allowed.add(unversioned, method.clone());
+            }
+            allowed.add(&path, method);
+        }
+    }
+    allowed.add("/swagger-ui/{_:.*}", Method::GET);
+    allowed.add("/api-docs/{_:.*}", Method::GET);
+    allowed
+}
+
+/// Where Swagger UI serves the OpenAPI document of `version`
+fn document_url(version: ApiVersion) -> Url<'static> {
+    match version {
+        ApiVersion::V1 => Url::new("v1", "/api-docs/v1/openapi.json"),
+        ApiVersion::V2 => Url::new("v2", "/api-docs/v2/openapi.json"),
+    }
+}
+
+/// The OpenAPI document of [`ApiVersion::DEFAULT`]
+async fn default_document() -> HttpResponse {
+    HttpResponse::Ok().json(openapi::openapi(ApiVersion::DEFAULT))
+}
+
+/// User and authentication routes of `version`
+fn versioned(version: ApiVersion) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        version_routes(version)(cfg);
+        SharedRoutes::register(cfg);
+    }
 }
@@ -0,0 +1,152 @@
+use uuid::Uuid;
+
+use crate::core::{
+    domain::{
+        users::query::{Filter, FilterValue, Operator, UserField},
+        validation::normalize_email,
+    },
+    rest::scim::model::ScimError,
+};
+
+/// User attributes that can be used in a SCIM filter
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScimFilterAttribute {
+    Id,
+    UserName,
+    Email,
+    DisplayName,
+}
+
+/// A parsed `attribute eq "value"` filter expression.
+///
+/// Only equality filters are supported, which covers the lookups identity
+/// providers perform before creating a user (RFC 7644, section 3.4.2.2).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScimFilter {
+    pub attribute: ScimFilterAttribute,
+    pub value: String,
+}
+
+impl ScimFilter {
+    pub fn parse(filter: &str) -> Result<Self, ScimError> {
+        let invalid =
+            || ScimError::bad_request("invalidFilter", format!("Unsupported filter: {}", filter));
+
+        let filter = filter.trim();
+        let (attribute, rest) = filter.split_once(char::is_whitespace).ok_or_else(invalid)?;
+        let (operator, value) = rest
+            .trim_start()
+            .split_once(char::is_whitespace)
+            .ok_or_else(invalid)?;
+
+        if !operator.eq_ignore_ascii_case("eq") {
+            return Err(invalid());
+        }
+
+        let attribute = match attribute.to_ascii_lowercase().as_str() {
+            "id" => ScimFilterAttribute::Id,
+            "username" => ScimFilterAttribute::UserName,
+            "emails" | "emails.value" => ScimFilterAttribute::Email,
+            "displayname" => ScimFilterAttribute::DisplayName,
+            _ => return Err(invalid()),
+        };
+
+        let value = value
+            .trim()
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .ok_or_else(invalid)?
+            .replace("\\\"", "\"");
+
+        Ok(Self { attribute, value })
+    }
+
+    /// This filter as a user query filter; `None` when no user can match,
+    /// as for an `id` that is not a UUID. User names are emails, which are
+    /// stored normalized, so they match regardless of case.
+    pub fn to_user_filter(&self) -> Option<Filter> {
+        let (field, value) = match self.attribute {
+            ScimFilterAttribute::Id => (
+                UserField::Id,
+                FilterValue::Uuid(Uuid::parse_str(&self.value).ok()?),
+            ),
+            ScimFilterAttribute::UserName | ScimFilterAttribute::Email => {
+                let mut email = self.value.clone();
+                normalize_email(&mut email);
+                (UserField::Email, FilterValue::Text(email))
+            }
+            ScimFilterAttribute::DisplayName => {
+                (UserField::Name, FilterValue::Text(self.value.clone()))
+            }
+        };
+        Some(Filter::Compare {
+            field,
+            op: Operator::Eq,
+            value,
+        })
+    }
+}
+
+/// Extract the member ID from a `members[value eq "..."]` PATCH path
+pub fn parse_member_path(path: &str) -> Option<String> {
+    let inner = path
+        .trim()
+        .strip_prefix("members[")
+        .and_then(|p| p.strip_suffix(']'))?;
+    let (attribute, rest) = inner.trim().split_once(char::is_whitespace)?;
+    let (operator, value) = rest.trim_start().split_once(char::is_whitespace)?;
+    if !attribute.eq_ignore_ascii_case("value") || !operator.eq_ignore_ascii_case("eq") {
+        return None;
+    }
+    value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_equality_filter() {
+        let filter = ScimFilter::parse(r#"userName eq "john@example.com""#).unwrap();
+        assert_eq!(filter.attribute, ScimFilterAttribute::UserName);
+        assert_eq!(filter.value, "john@example.com");
+
+        let filter = ScimFilter::parse(r#"emails.value EQ "a@b.io""#).unwrap();
+        assert_eq!(filter.attribute, ScimFilterAttribute::Email);
+
+        assert!(ScimFilter::parse(r#"userName co "john""#).is_err());
+        assert!(ScimFilter::parse(r#"nickName eq "john""#).is_err());
+        assert!(ScimFilter::parse("userName eq john").is_err());
+        assert!(ScimFilter::parse("userName").is_err());
+    }
+
+    #[test]
+    fn test_to_user_filter() {
+        let filter = ScimFilter::parse(r#"userName eq " John@Example.com""#).unwrap();
+        assert_eq!(
+            filter.to_user_filter(),
+            Some(Filter::Compare {
+                field: UserField::Email,
+                op: Operator::Eq,
+                value: FilterValue::Text("john@example.com".to_string()),
+            })
+        );
+
+        let filter = ScimFilter::parse(r#"id eq "not-a-uuid""#).unwrap();
+        assert_eq!(filter.to_user_filter(), None);
+    }
+
+    #[test]
+    fn test_parse_member_path() {
+        assert_eq!(
+            parse_member_path(r#"members[value eq "abc"]"#),
+            Some("abc".to_string())
+        );
+        assert_eq!(parse_member_path("members"), None);
+        assert_eq!(parse_member_path(r#"members[display eq "abc"]"#), None);
+    }
+}
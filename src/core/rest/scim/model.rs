@@ -0,0 +1,366 @@
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use crate::core::domain::{
+    error::AppError,
+    users::model::{User, UserRole},
+};
+
+pub const SCIM_CONTENT_TYPE: &str = "application/scim+json";
+
+/// Path of the scope serving the SCIM API
+pub const SCIM_PREFIX: &str = "/scim/v2";
+
+pub const USER_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+pub const GROUP_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:Group";
+pub const LIST_RESPONSE_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+pub const PATCH_OP_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:PatchOp";
+pub const ERROR_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+
+/// SCIM user resource (RFC 7643, section 4.1)
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimUser {
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    /// Server assigned identifier
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Unique user name; mapped onto the user's email address
+    #[schema(example = "john.doe@example.com")]
+    pub user_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<ScimName>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "John Doe")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub emails: Vec<ScimEmail>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active: Option<bool>,
+    /// Write-only password
+    #[serde(default, skip_serializing)]
+    pub password: Option<String>,
+    /// Groups the user belongs to (read-only, derived from the user's role)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<ScimMember>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<ScimMeta>,
+}
+
+/// Components of a user's name
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimName {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub formatted: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub given_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub family_name: Option<String>,
+}
+
+/// Email address of a user
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ScimEmail {
+    pub value: String,
+    #[serde(default)]
+    pub primary: bool,
+}
+
+/// Reference from a group to a member, or from a user to a group
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ScimMember {
+    pub value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+    #[serde(rename = "$ref", skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+}
+
+/// SCIM group resource (RFC 7643, section 4.2); each group is one `UserRole`
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimGroup {
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[schema(example = "admin")]
+    pub display_name: String,
+    #[serde(default)]
+    pub members: Vec<ScimMember>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<ScimMeta>,
+}
+
+/// Resource metadata
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimMeta {
+    pub resource_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    pub location: String,
+}
+
+/// Paged list of resources (RFC 7644, section 3.4.2)
+#[derive(Debug, Serialize, ToSchema)]
+#[aliases(ScimUserList = ScimListResponse<ScimUser>, ScimGroupList = ScimListResponse<ScimGroup>)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimListResponse<T> {
+    pub schemas: Vec<String>,
+    pub total_results: i64,
+    pub start_index: i64,
+    pub items_per_page: i64,
+    #[serde(rename = "Resources")]
+    pub resources: Vec<T>,
+}
+
+impl<T> ScimListResponse<T> {
+    pub fn new(resources: Vec<T>, total_results: i64, start_index: i64) -> Self {
+        Self {
+            schemas: vec![LIST_RESPONSE_SCHEMA.to_string()],
+            total_results,
+            start_index,
+            items_per_page: resources.len() as i64,
+            resources,
+        }
+    }
+}
+
+/// PATCH request body (RFC 7644, section 3.5.2)
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ScimPatchRequest {
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    #[serde(rename = "Operations")]
+    pub operations: Vec<ScimPatchOperation>,
+}
+
+/// A single PATCH operation
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ScimPatchOperation {
+    /// One of `add`, `remove` or `replace` (case-insensitive)
+    #[schema(example = "replace")]
+    pub op: String,
+    #[serde(default)]
+    #[schema(example = "displayName")]
+    pub path: Option<String>,
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub value: Option<Value>,
+}
+
+/// Query parameters accepted by list endpoints
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimListQuery {
+    pub filter: Option<String>,
+    pub start_index: Option<i64>,
+    pub count: Option<i64>,
+}
+
+/// SCIM error response (RFC 7644, section 3.12)
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimError {
+    pub schemas: Vec<String>,
+    #[schema(example = "400")]
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "invalidFilter")]
+    pub scim_type: Option<String>,
+    pub detail: String,
+}
+
+impl ScimError {
+    pub fn new(status: StatusCode, scim_type: Option<&str>, detail: impl Into<String>) -> Self {
+        Self {
+            schemas: vec![ERROR_SCHEMA.to_string()],
+            status: status.as_u16().to_string(),
+            scim_type: scim_type.map(str::to_string),
+            detail: detail.into(),
+        }
+    }
+
+    pub fn bad_request(scim_type: &str, detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, Some(scim_type), detail)
+    }
+
+    pub fn not_found(detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, None, detail)
+    }
+
+    pub fn unauthorized() -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, None, "Authorization failure")
+    }
+}
+
+impl std::fmt::Display for ScimError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SCIM error {}: {}", self.status, self.detail)
+    }
+}
+
+impl ResponseError for ScimError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+            .parse()
+            .ok()
+            .and_then(|code| StatusCode::from_u16(code).ok())
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code())
+            .content_type(SCIM_CONTENT_TYPE)
+            .json(self)
+    }
+}
+
+impl From<AppError> for ScimError {
+    fn from(error: AppError) -> Self {
+        match error {
+            AppError::Database(sqlx::Error::RowNotFound) | AppError::NotFound { .. } => {
+                ScimError::not_found("Resource not found")
+            }
+            AppError::Database(sqlx::Error::Database(db_err)) if db_err.constraint().is_some() => {
+                ScimError::new(
+                    StatusCode::CONFLICT,
+                    Some("uniqueness"),
+                    "userName is already in use",
+                )
+            }
+            AppError::Database(e) => {
+                log::error!("SCIM database error: {:?}", e);
+                ScimError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    None,
+                    "Database operation failed",
+                )
+            }
+            AppError::Validation { message, .. } => ScimError::bad_request("invalidValue", message),
+            AppError::Authentication { .. } => ScimError::unauthorized(),
+            AppError::Authorization { message } => {
+                ScimError::new(StatusCode::FORBIDDEN, None, message)
+            }
+            AppError::Conflict { message } => {
+                ScimError::new(StatusCode::CONFLICT, Some("uniqueness"), message)
+            }
+            AppError::PreconditionFailed { message } => {
+                ScimError::new(StatusCode::PRECONDITION_FAILED, None, message)
+            }
+            AppError::PreconditionRequired { message } => {
+                ScimError::new(StatusCode::PRECONDITION_REQUIRED, None, message)
+            }
+            AppError::FailedDependency { message } => {
+                ScimError::new(StatusCode::FAILED_DEPENDENCY, None, message)
+            }
+            AppError::NotAcceptable { message } => {
+                ScimError::new(StatusCode::NOT_ACCEPTABLE, None, message)
+            }
+            AppError::MethodNotAllowed { message } => {
+                ScimError::new(StatusCode::METHOD_NOT_ALLOWED, None, message)
+            }
+            AppError::PayloadTooLarge { message } => {
+                ScimError::new(StatusCode::PAYLOAD_TOO_LARGE, None, message)
+            }
+            AppError::UnsupportedMediaType { message } => {
+                ScimError::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, None, message)
+            }
+            AppError::Internal => ScimError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                None,
+                "Internal server error",
+            ),
+        }
+    }
+}
+
+impl From<sqlx::Error> for ScimError {
+    fn from(error: sqlx::Error) -> Self {
+        AppError::from(error).into()
+    }
+}
+
+impl ScimUser {
+    /// Build the SCIM representation of a stored user
+    pub fn from_user(user: &User, base_url: &str) -> Self {
+        let role = user.get_role().unwrap_or_default();
+        Self {
+            schemas: vec![USER_SCHEMA.to_string()],
+            id: Some(user.id.to_string()),
+            user_name: user.email.clone(),
+            name: Some(ScimName {
+                formatted: Some(user.name.clone()),
+                given_name: None,
+                family_name: None,
+            }),
+            display_name: Some(user.name.clone()),
+            emails: vec![ScimEmail {
+                value: user.email.clone(),
+                primary: true,
+            }],
+            active: Some(true),
+            password: None,
+            groups: vec![ScimMember {
+                value: role.to_string(),
+                display: Some(role.to_string()),
+                reference: Some(format!("{}/Groups/{}", base_url, role)),
+            }],
+            meta: Some(ScimMeta {
+                resource_type: "User".to_string(),
+                created: Some(user.created_at.to_rfc3339()),
+                last_modified: Some(user.updated_at.to_rfc3339()),
+                location: format!("{}/Users/{}", base_url, user.id),
+            }),
+        }
+    }
+
+    /// Resolve the name to store for this user, falling back to the user name
+    pub fn resolved_name(&self) -> String {
+        self.display_name
+            .clone()
+            .or_else(|| self.name.as_ref().and_then(|n| n.formatted.clone()))
+            .or_else(|| {
+                let name = self.name.as_ref()?;
+                let parts: Vec<&str> = [name.given_name.as_deref(), name.family_name.as_deref()]
+                    .into_iter()
+                    .flatten()
+                    .collect();
+                (!parts.is_empty()).then(|| parts.join(" "))
+            })
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| self.user_name.clone())
+    }
+}
+
+impl ScimGroup {
+    /// Build the SCIM representation of the group backing a role
+    pub fn from_role(role: &UserRole, members: &[User], base_url: &str) -> Self {
+        Self {
+            schemas: vec![GROUP_SCHEMA.to_string()],
+            id: Some(role.to_string()),
+            display_name: role.to_string(),
+            members: members
+                .iter()
+                .map(|user| ScimMember {
+                    value: user.id.to_string(),
+                    display: Some(user.email.clone()),
+                    reference: Some(format!("{}/Users/{}", base_url, user.id)),
+                })
+                .collect(),
+            meta: Some(ScimMeta {
+                resource_type: "Group".to_string(),
+                created: None,
+                last_modified: None,
+                location: format!("{}/Groups/{}", base_url, role),
+            }),
+        }
+    }
+}
@@ -1,92 +1,47 @@
-use serde::Deserialize;
-use utoipa::ToSchema;
-use validator::Validate;
-
-use crate::core::domain::error::{AppError, Result};
-
-/// User creation payload with validation
-#[derive(Debug, Deserialize, Validate, ToSchema)]
-pub struct CreateUserPayload {
-    /// Full name of the user (must be between 2 and 100 characters)
-    #[validate(length(
-        min = 2,
-        max = 100,
-        message = "Name must be between 2 and 100 characters"
-    ))]
-    #[schema(example = "John Doe")]
-    pub name: String,
-
-    /// Email address of the user (must be a valid email format)
-    #[validate(email(message = "Invalid email format"))]
-    #[schema(example = "john.doe@example.com")]
-    pub email: String,
-
-    /// Password (must be at least 8 characters long)
-    #[validate(length(min = 8, message = "Password must be at least 8 characters long"))]
-    #[schema(example = "password123")]
-    pub password: String,
-}
-
-/// User login payload with validation
-#[derive(Debug, Deserialize, Validate, ToSchema)]
-pub struct LoginPayload {
-    /// Email address of the user
-    #[validate(email(message = "Invalid email format"))]
-    #[schema(example = "john.doe@example.com")]
-    pub email: String,
-
-    /// User password
-    #[validate(length(min = 1, message = "Password is required"))]
-    #[schema(example = "password123")]
-    pub password: String,
+use validator::ValidationError;
+
+use crate::core::{
+    domain::error::{AppError, Result},
+    rest::handler::{
+        auth::{ChangePasswordRequest, CreateUserWithRoleRequest},
+        users::CreateUserPayload,
+        validator::is_valid_email,
+    },
+};
+
+pub use crate::core::domain::validation::{
+    normalize_email, normalize_name, normalize_role, ValidatedPayload,
+};
+
+/// `validator` rule for [`is_valid_email`]
+pub fn email_format(email: &str) -> std::result::Result<(), ValidationError> {
+    match is_valid_email(email) {
+        true => Ok(()),
+        false => Err(ValidationError::new("email")),
+    }
 }
 
-/// User update payload with validation (all fields optional)
-#[derive(Debug, Deserialize, Validate, ToSchema)]
-pub struct UpdateUserPayload {
-    /// Full name of the user (optional, must be between 2 and 100 characters if provided)
-    #[validate(length(
-        min = 2,
-        max = 100,
-        message = "Name must be between 2 and 100 characters"
-    ))]
-    #[schema(example = "John Doe")]
-    pub name: Option<String>,
-
-    /// Email address of the user (optional, must be valid email format if provided)
-    #[validate(email(message = "Invalid email format"))]
-    #[schema(example = "john.doe@example.com")]
-    pub email: Option<String>,
+impl ValidatedPayload for CreateUserPayload {
+    fn normalize(&mut self) {
+        if let Some(name) = &mut self.name {
+            normalize_name(name);
+        }
+        if let Some(email) = &mut self.email {
+            normalize_email(email);
+        }
+    }
 }
 
-/// Password change payload with validation
-#[derive(Debug, Deserialize, Validate, ToSchema)]
-pub struct ChangePasswordPayload {
-    /// Current password
-    #[validate(length(min = 1, message = "Current password is required"))]
-    #[schema(example = "old_password")]
-    pub current_password: String,
-
-    /// New password (must be at least 8 characters long)
-    #[validate(length(min = 8, message = "New password must be at least 8 characters long"))]
-    #[schema(example = "new_password123")]
-    pub new_password: String,
-}
+impl ValidatedPayload for ChangePasswordRequest {}
 
-/// Trait for validating request payloads
-pub trait ValidatedPayload: Validate {
-    /// Validate the payload and return an AppResult
-    fn validate_payload(&self) -> Result<()> {
-        self.validate().map_err(AppError::from)
+impl ValidatedPayload for CreateUserWithRoleRequest {
+    fn normalize(&mut self) {
+        normalize_name(&mut self.name);
+        normalize_email(&mut self.email);
+        normalize_role(&mut self.role);
     }
 }
 
-// Implement ValidatedPayload for all our payload types
-impl ValidatedPayload for CreateUserPayload {}
-impl ValidatedPayload for LoginPayload {}
-impl ValidatedPayload for UpdateUserPayload {}
-impl ValidatedPayload for ChangePasswordPayload {}
-
 /// Custom validator for password strength
 pub fn validate_password_strength(password: &str) -> Result<()> {
     let mut has_upper = false;
@@ -106,12 +61,14 @@ pub fn validate_password_strength(password: &str) -> Result<()> {
     if password.len() < 8 {
         return Err(AppError::Validation {
             message: "Password must be at least 8 characters long".to_string(),
+            errors: Vec::new(),
         });
     }
 
     if !has_upper || !has_lower || !has_digit {
         return Err(AppError::Validation {
             message: "Password must contain at least one uppercase letter, one lowercase letter, and one digit".to_string(),
+            errors: Vec::new(),
         });
     }
 
@@ -121,38 +78,43 @@ pub fn validate_password_strength(password: &str) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::domain::users::model::{CreateUserRequest, LoginRequest, UpdateUserRequest};
 
     #[test]
     fn test_create_user_payload_validation() {
         // Valid payload
-        let valid_payload = CreateUserPayload {
+        let valid_payload = CreateUserRequest {
             name: "John Doe".to_string(),
             email: "john@example.com".to_string(),
             password: "password123".to_string(),
+            role: None,
         };
         assert!(valid_payload.validate_payload().is_ok());
 
         // Invalid email
-        let invalid_email = CreateUserPayload {
+        let invalid_email = CreateUserRequest {
             name: "John Doe".to_string(),
             email: "invalid-email".to_string(),
             password: "password123".to_string(),
+            role: None,
         };
         assert!(invalid_email.validate_payload().is_err());
 
-        // Short name
-        let short_name = CreateUserPayload {
-            name: "J".to_string(),
+        // Empty name
+        let empty_name = CreateUserRequest {
+            name: "".to_string(),
             email: "john@example.com".to_string(),
             password: "password123".to_string(),
+            role: None,
         };
-        assert!(short_name.validate_payload().is_err());
+        assert!(empty_name.validate_payload().is_err());
 
         // Short password
-        let short_password = CreateUserPayload {
+        let short_password = CreateUserRequest {
             name: "John Doe".to_string(),
             email: "john@example.com".to_string(),
             password: "pass".to_string(),
+            role: None,
         };
         assert!(short_password.validate_payload().is_err());
     }
@@ -179,24 +141,36 @@ mod tests {
     #[test]
     fn test_login_payload_validation() {
         // Valid payload
-        let valid_payload = LoginPayload {
+        let valid_payload = LoginRequest {
             email: "john@example.com".to_string(),
             password: "password".to_string(),
         };
         assert!(valid_payload.validate_payload().is_ok());
 
         // Invalid email
-        let invalid_email = LoginPayload {
+        let invalid_email = LoginRequest {
             email: "not-an-email".to_string(),
             password: "password".to_string(),
         };
         assert!(invalid_email.validate_payload().is_err());
 
         // Empty password
-        let empty_password = LoginPayload {
+        let empty_password = LoginRequest {
             email: "john@example.com".to_string(),
             password: "".to_string(),
         };
         assert!(empty_password.validate_payload().is_err());
     }
+
+    #[test]
+    fn test_normalize_payload() {
+        let mut payload = UpdateUserRequest {
+            name: Some("  John Doe ".to_string()),
+            email: Some(" John.Doe@Example.COM".to_string()),
+        };
+        payload.normalize();
+        assert_eq!(payload.name.as_deref(), Some("John Doe"));
+        assert_eq!(payload.email.as_deref(), Some("john.doe@example.com"));
+        assert!(payload.validate_payload().is_ok());
+    }
 }
@@ -0,0 +1,123 @@
+//! API versions.
+//!
+//! User and auth routes are served under a scope per version, `/api/v1` and
+//! `/api/v2`. The same routes without a prefix serve the version selected by
+//! the `Api-Version` header, or [`ApiVersion::DEFAULT`] without one, so
+//! clients written before versioning keep working. SCIM, OAuth and the
+//! documentation are versioned by their own paths.
+
+use actix_web::{
+    guard::{Guard, GuardContext},
+    http::header::{HeaderMap, HeaderName},
+};
+use chrono::NaiveDate;
+use std::fmt;
+
+/// Header selecting the version of unversioned paths, echoed on responses
+pub const API_VERSION_HEADER: HeaderName = HeaderName::from_static("api-version");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ApiVersion {
+    V1,
+    V2,
+}
+
+impl ApiVersion {
+    /// Every version, oldest first
+    pub const ALL: [ApiVersion; 2] = [ApiVersion::V1, ApiVersion::V2];
+
+    /// Version of unversioned paths without an `Api-Version` header
+    pub const DEFAULT: ApiVersion = ApiVersion::V1;
+
+    /// The newest version
+    pub const LATEST: ApiVersion = ApiVersion::V2;
+
+    pub fn number(self) -> u8 {
+        match self {
+            ApiVersion::V1 => 1,
+            ApiVersion::V2 => 2,
+        }
+    }
+
+    /// Path prefix of the version's scope, such as `/api/v1`
+    pub fn prefix(self) -> String {
+        format!("/api/v{}", self.number())
+    }
+
+    /// Release date, which is when the routes it replaces were deprecated.
+    /// v1 is the API as it was before versioning.
+    pub fn released(self) -> Option<NaiveDate> {
+        match self {
+            ApiVersion::V1 => None,
+            ApiVersion::V2 => NaiveDate::from_ymd_opt(2026, 10, 18),
+        }
+    }
+
+    /// The version after this one, if any
+    pub fn successor(self) -> Option<ApiVersion> {
+        Self::ALL.into_iter().find(|version| *version > self)
+    }
+
+    /// A version as written in `Api-Version`: `2` or `v2`
+    pub fn parse(value: &str) -> Option<ApiVersion> {
+        let value = value.trim();
+        let number = value
+            .strip_prefix(['v', 'V'])
+            .unwrap_or(value)
+            .parse::<u8>()
+            .ok()?;
+        Self::ALL
+            .into_iter()
+            .find(|version| version.number() == number)
+    }
+
+    /// The version requested with `Api-Version`: `None` without the header,
+    /// `Some(Err(value))` for a version that does not exist
+    pub fn requested(headers: &HeaderMap) -> Option<Result<ApiVersion, String>> {
+        let value = headers.get(&API_VERSION_HEADER)?;
+        let value = value.to_str().unwrap_or_default();
+        Some(Self::parse(value).ok_or_else(|| value.to_string()))
+    }
+
+    /// Guard for the unversioned scope of a version. The default version
+    /// takes every request that does not select another one, so that
+    /// unknown versions are answered by its [`ApiVersioning`] middleware.
+    ///
+    /// [`ApiVersioning`]: crate::core::rest::middleware::api_version::ApiVersioning
+    pub fn header_guard(self) -> impl Guard {
+        VersionGuard(self)
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.number())
+    }
+}
+
+struct VersionGuard(ApiVersion);
+
+impl Guard for VersionGuard {
+    fn check(&self, ctx: &GuardContext<'_>) -> bool {
+        match ApiVersion::requested(ctx.head().headers()) {
+            Some(Ok(version)) => version == self.0,
+            _ => self.0 == ApiVersion::DEFAULT,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(ApiVersion::parse("2"), Some(ApiVersion::V2));
+        assert_eq!(ApiVersion::parse("v1"), Some(ApiVersion::V1));
+        assert_eq!(ApiVersion::parse("3"), None);
+        assert_eq!(ApiVersion::parse("latest"), None);
+        assert_eq!(ApiVersion::V1.successor(), Some(ApiVersion::V2));
+        assert_eq!(ApiVersion::V2.successor(), None);
+        assert!(ApiVersion::V2.released().is_some());
+    }
+}
@@ -1,4 +1,10 @@
 use afaf_rest_rust::cmd;
-fn main() {
-    let _ = cmd::run();
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    // Failed commands have already reported why
+    match cmd::run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(_) => ExitCode::FAILURE,
+    }
 }
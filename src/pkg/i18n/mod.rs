@@ -0,0 +1,151 @@
+//! Message catalogs and locale negotiation.
+//!
+//! Messages are written in English in the code. The Fluent catalogs under
+//! `locales/` translate them for other locales, keyed by error code, message,
+//! `validator` rule and role. A key a catalog lacks has no translation, so
+//! that callers keep the English message rather than a generic one.
+
+pub use fluent_templates::LanguageIdentifier;
+use fluent_templates::{fluent_bundle::FluentValue, langid, static_loader, Loader};
+use serde_json::{Map, Value};
+use std::{borrow::Cow, collections::HashMap};
+
+static_loader! {
+    static LOCALES = {
+        locales: "./locales",
+        fallback_language: "en",
+        // Keep interpolated values free of Unicode isolation marks
+        customise: |bundle| bundle.set_use_isolating(false),
+    };
+}
+
+/// The language messages are written in
+pub const SOURCE_LOCALE: LanguageIdentifier = langid!("en");
+
+/// Locales with a catalog
+pub fn supported() -> Vec<LanguageIdentifier> {
+    let mut locales: Vec<_> = LOCALES.locales().cloned().collect();
+    locales.sort_by_key(|locale| locale.to_string());
+    locales
+}
+
+/// The supported locale for a configured name such as `DEFAULT_LOCALE`
+pub fn parse(name: &str) -> Option<LanguageIdentifier> {
+    let locale: LanguageIdentifier = name.parse().ok()?;
+    matching(&locale)
+}
+
+/// The best supported locale for language tags in order of preference, as
+/// ranked from `Accept-Language`. `*` stands for `default`, as does a list
+/// without any supported locale.
+pub fn negotiate<'a>(
+    tags: impl IntoIterator<Item = &'a str>,
+    default: &LanguageIdentifier,
+) -> LanguageIdentifier {
+    tags.into_iter()
+        .find_map(|tag| match tag {
+            "*" => Some(default.clone()),
+            tag => tag.parse().ok().and_then(|locale| matching(&locale)),
+        })
+        .unwrap_or_else(|| default.clone())
+}
+
+/// A supported locale equal to `locale` or with the same language, so that
+/// `de-AT` selects `de`
+fn matching(locale: &LanguageIdentifier) -> Option<LanguageIdentifier> {
+    let supported = supported();
+    supported
+        .iter()
+        .find(|supported| *supported == locale)
+        .or_else(|| {
+            supported
+                .iter()
+                .find(|supported| supported.language == locale.language)
+        })
+        .cloned()
+}
+
+/// The message for `key` in `locale` with `args` interpolated, if the
+/// catalog of `locale` has one
+pub fn lookup(locale: &LanguageIdentifier, key: &str, args: &Map<String, Value>) -> Option<String> {
+    let args: HashMap<Cow<'static, str>, FluentValue> = args
+        .iter()
+        .map(|(name, value)| (Cow::Owned(name.clone()), fluent_value(value)))
+        .collect();
+    LOCALES.lookup_no_default_fallback(locale, key, Some(&args))
+}
+
+/// The catalog key translating the English `message`: `message-` and the
+/// message in lower case with dashes between words, so that `Email already
+/// exists` is `message-email-already-exists`
+pub fn message_key(message: &str) -> String {
+    let words: Vec<String> = message
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    format!("message-{}", words.join("-"))
+}
+
+fn fluent_value(value: &Value) -> FluentValue<'static> {
+    match value {
+        Value::Number(n) => match n.as_i64() {
+            Some(n) => FluentValue::from(n),
+            None => FluentValue::from(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => FluentValue::from(s.clone()),
+        other => FluentValue::from(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_negotiate() {
+        let en = SOURCE_LOCALE;
+        assert_eq!(negotiate(["id-ID", "en"], &en), langid!("id"));
+        assert_eq!(negotiate(["pt", "de-AT"], &en), langid!("de"));
+        assert_eq!(negotiate(["pt", "*"], &langid!("fr")), langid!("fr"));
+        assert_eq!(negotiate([], &en), en);
+        assert_eq!(parse("xx"), None);
+        assert_eq!(parse("id-ID"), Some(langid!("id")));
+    }
+
+    #[test]
+    fn test_lookup() {
+        let args = json!({ "field": "password", "min": 1 });
+        let args = args.as_object().unwrap();
+        assert_eq!(
+            lookup(&langid!("fr"), "validation-length-min", args).as_deref(),
+            Some("password doit contenir au moins 1 caractère")
+        );
+        assert_eq!(
+            lookup(&langid!("id"), "not_found", &Map::new()).as_deref(),
+            Some("Sumber daya tidak ditemukan")
+        );
+        assert_eq!(lookup(&langid!("de"), "no-such-key", &Map::new()), None);
+        assert_eq!(
+            lookup(&langid!("de"), "message-invalid-credentials", &Map::new()).as_deref(),
+            Some("Ungültige Anmeldedaten")
+        );
+    }
+
+    #[test]
+    fn test_message_key() {
+        assert_eq!(
+            message_key("Email already exists"),
+            "message-email-already-exists"
+        );
+        assert_eq!(
+            message_key("If-Match header is required"),
+            "message-if-match-header-is-required"
+        );
+        assert_eq!(
+            message_key("Missing or invalid CSRF token."),
+            "message-missing-or-invalid-csrf-token"
+        );
+    }
+}
@@ -1 +1,5 @@
+pub mod crypto;
+pub mod i18n;
 pub mod logger;
+pub mod openapi_diff;
+pub mod tls;
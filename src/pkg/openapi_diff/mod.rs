@@ -0,0 +1,602 @@
+//! Breaking change detection between two OpenAPI documents.
+//!
+//! Documents are compared as JSON values, so a spec published earlier can be
+//! read back from JSON or YAML. A change is breaking when a client written
+//! against the old document can fail against the new one: a removed path,
+//! operation or success response, a newly required parameter or request
+//! field, an enum that lost a value, a response that may return values or
+//! omit fields the old one did not, or a changed type.
+//! Additions and relaxations are reported as non-breaking.
+//!
+//! Schemas are followed through local `$ref`s. Changes inside a component
+//! schema are reported once, under the schema's name, whichever operations
+//! use it.
+
+use serde_json::{Map, Value};
+use std::{collections::HashSet, fmt};
+
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Longest `$ref` chain that is followed
+const MAX_REF_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Breaking,
+    NonBreaking,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Breaking => "breaking",
+            Severity::NonBreaking => "non-breaking",
+        })
+    }
+}
+
+/// One difference between two documents
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub severity: Severity,
+    /// Operation, parameter, body or schema that changed, such as
+    /// `POST /api/v2/users request body` or `schema UserRole`
+    pub location: String,
+    pub message: String,
+}
+
+impl Change {
+    pub fn is_breaking(&self) -> bool {
+        self.severity == Severity::Breaking
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// Changes from `old` to `new`, in the order of the old document's paths
+pub fn diff(old: &Value, new: &Value) -> Vec<Change> {
+    let mut differ = Differ {
+        old,
+        new,
+        changes: Vec::new(),
+        visited: HashSet::new(),
+    };
+    differ.paths();
+    differ.changes
+}
+
+/// Which side of an exchange a schema describes. Requests may accept more
+/// and responses may return less than before, not the other way around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Direction {
+    Request,
+    Response,
+}
+
+struct Differ<'a> {
+    old: &'a Value,
+    new: &'a Value,
+    changes: Vec<Change>,
+    /// Pairs of component schemas already compared, per direction
+    visited: HashSet<(String, String, Direction)>,
+}
+
+impl<'a> Differ<'a> {
+    fn report(&mut self, severity: Severity, location: &str, message: String) {
+        self.changes.push(Change {
+            severity,
+            location: location.to_string(),
+            message,
+        });
+    }
+
+    fn paths(&mut self) {
+        let old_paths = object(self.old.get("paths"));
+        let new_paths = object(self.new.get("paths"));
+
+        for (path, old_item) in old_paths {
+            let Some(new_item) = new_paths.get(path) else {
+                self.report(Severity::Breaking, path, "path removed".to_string());
+                continue;
+            };
+            let old_item = resolve(self.old, old_item).0;
+            let new_item = resolve(self.new, new_item).0;
+            for method in METHODS {
+                let location = format!("{} {}", method.to_uppercase(), path);
+                match (old_item.get(method), new_item.get(method)) {
+                    (Some(old_op), Some(new_op)) => self.operation(&location, old_op, new_op),
+                    (Some(_), None) => {
+                        self.report(Severity::Breaking, &location, "operation removed".into())
+                    }
+                    (None, Some(_)) => {
+                        self.report(Severity::NonBreaking, &location, "operation added".into())
+                    }
+                    (None, None) => {}
+                }
+            }
+        }
+        for path in new_paths.keys() {
+            if !old_paths.contains_key(path) {
+                self.report(Severity::NonBreaking, path, "path added".to_string());
+            }
+        }
+    }
+
+    fn operation(&mut self, location: &str, old: &Value, new: &Value) {
+        if !flag(old, "deprecated") && flag(new, "deprecated") {
+            self.report(Severity::NonBreaking, location, "deprecated".to_string());
+        }
+        self.parameters(location, old, new);
+        self.request_body(location, old.get("requestBody"), new.get("requestBody"));
+        self.responses(location, old, new);
+    }
+
+    fn parameters(&mut self, location: &str, old: &Value, new: &Value) {
+        let old_params = parameters(self.old, old);
+        let new_params = parameters(self.new, new);
+
+        for (key, old_param) in &old_params {
+            let param_location = format!("{} {} parameter `{}`", location, key.0, key.1);
+            let Some((_, new_param)) = new_params.iter().find(|(new_key, _)| new_key == key) else {
+                self.report(Severity::Breaking, &param_location, "removed".to_string());
+                continue;
+            };
+            if !flag(old_param, "required") && flag(new_param, "required") {
+                self.report(Severity::Breaking, &param_location, "now required".into());
+            }
+            if let (Some(old_schema), Some(new_schema)) =
+                (old_param.get("schema"), new_param.get("schema"))
+            {
+                self.schema(&param_location, old_schema, new_schema, Direction::Request);
+            }
+        }
+        for (key, new_param) in &new_params {
+            if old_params.iter().any(|(old_key, _)| old_key == key) {
+                continue;
+            }
+            let param_location = format!("{} {} parameter `{}`", location, key.0, key.1);
+            if flag(new_param, "required") {
+                self.report(
+                    Severity::Breaking,
+                    &param_location,
+                    "added as required".into(),
+                );
+            } else {
+                self.report(Severity::NonBreaking, &param_location, "added".into());
+            }
+        }
+    }
+
+    fn request_body(&mut self, location: &str, old: Option<&Value>, new: Option<&Value>) {
+        let location = format!("{} request body", location);
+        let (old, new) = match (old, new) {
+            (Some(old), Some(new)) => (resolve(self.old, old).0, resolve(self.new, new).0),
+            (None, Some(new)) => {
+                if flag(resolve(self.new, new).0, "required") {
+                    self.report(Severity::Breaking, &location, "added as required".into());
+                } else {
+                    self.report(Severity::NonBreaking, &location, "added".into());
+                }
+                return;
+            }
+            (Some(_), None) => {
+                self.report(Severity::NonBreaking, &location, "removed".into());
+                return;
+            }
+            (None, None) => return,
+        };
+        if !flag(old, "required") && flag(new, "required") {
+            self.report(Severity::Breaking, &location, "now required".into());
+        }
+        self.content(&location, old, new, Direction::Request);
+    }
+
+    fn responses(&mut self, location: &str, old: &Value, new: &Value) {
+        let old_responses = object(old.get("responses"));
+        let new_responses = object(new.get("responses"));
+
+        for (status, old_response) in old_responses {
+            let response_location = format!("{} response {}", location, status);
+            let Some(new_response) = new_responses.get(status) else {
+                if status.starts_with('2') {
+                    self.report(Severity::Breaking, &response_location, "removed".into());
+                } else {
+                    self.report(Severity::NonBreaking, &response_location, "removed".into());
+                }
+                continue;
+            };
+            let old_response = resolve(self.old, old_response).0;
+            let new_response = resolve(self.new, new_response).0;
+            self.content(
+                &response_location,
+                old_response,
+                new_response,
+                Direction::Response,
+            );
+        }
+        for status in new_responses.keys() {
+            if !old_responses.contains_key(status) {
+                let response_location = format!("{} response {}", location, status);
+                self.report(Severity::NonBreaking, &response_location, "added".into());
+            }
+        }
+    }
+
+    /// Compare the schemas of the media types of a request body or response
+    fn content(&mut self, location: &str, old: &Value, new: &Value, direction: Direction) {
+        let old_content = object(old.get("content"));
+        let new_content = object(new.get("content"));
+
+        for (media_type, old_media) in old_content {
+            let Some(new_media) = new_content.get(media_type) else {
+                self.report(
+                    Severity::Breaking,
+                    location,
+                    format!("no longer uses `{}`", media_type),
+                );
+                continue;
+            };
+            if let (Some(old_schema), Some(new_schema)) =
+                (old_media.get("schema"), new_media.get("schema"))
+            {
+                self.schema(location, old_schema, new_schema, direction);
+            }
+        }
+        for media_type in new_content.keys() {
+            if !old_content.contains_key(media_type) {
+                self.report(
+                    Severity::NonBreaking,
+                    location,
+                    format!("also uses `{}`", media_type),
+                );
+            }
+        }
+    }
+
+    fn schema(&mut self, location: &str, old: &Value, new: &Value, direction: Direction) {
+        let (old, old_ref) = resolve(self.old, old);
+        let (new, new_ref) = resolve(self.new, new);
+        let mut location = location.to_string();
+        if let (Some(old_ref), Some(new_ref)) = (old_ref, new_ref) {
+            if !self
+                .visited
+                .insert((old_ref.to_string(), new_ref.to_string(), direction))
+            {
+                return;
+            }
+            if old_ref == new_ref {
+                location = format!("schema {}", schema_name(old_ref));
+            }
+        }
+        let location = location.as_str();
+
+        let (old_type, new_type) = (old.get("type"), new.get("type"));
+        if let (Some(old_type), Some(new_type)) = (old_type, new_type) {
+            if old_type != new_type {
+                self.report(
+                    Severity::Breaking,
+                    location,
+                    format!("type changed from {} to {}", old_type, new_type),
+                );
+                return;
+            }
+        }
+
+        match (flag(old, "nullable"), flag(new, "nullable"), direction) {
+            (true, false, Direction::Request) => self.report(
+                Severity::Breaking,
+                location,
+                "no longer accepts null".into(),
+            ),
+            (false, true, Direction::Response) => {
+                self.report(Severity::Breaking, location, "may now be null".into())
+            }
+            (false, true, Direction::Request) => {
+                self.report(Severity::NonBreaking, location, "now accepts null".into())
+            }
+            (true, false, Direction::Response) => {
+                self.report(Severity::NonBreaking, location, "no longer null".into())
+            }
+            _ => {}
+        }
+
+        self.enumeration(location, old.get("enum"), new.get("enum"), direction);
+        self.properties(location, old, new, direction);
+
+        if let (Some(old_items), Some(new_items)) = (old.get("items"), new.get("items")) {
+            self.schema(&format!("{}[]", location), old_items, new_items, direction);
+        }
+    }
+
+    fn enumeration(
+        &mut self,
+        location: &str,
+        old: Option<&Value>,
+        new: Option<&Value>,
+        direction: Direction,
+    ) {
+        let values = |values: Option<&Value>| values.and_then(Value::as_array).cloned();
+        let (removed, added) = match (values(old), values(new)) {
+            (Some(old), Some(new)) => (
+                old.iter()
+                    .filter(|v| !new.contains(v))
+                    .cloned()
+                    .collect::<Vec<_>>(),
+                new.iter()
+                    .filter(|v| !old.contains(v))
+                    .cloned()
+                    .collect::<Vec<_>>(),
+            ),
+            (None, Some(new)) => {
+                let severity = match direction {
+                    Direction::Request => Severity::Breaking,
+                    Direction::Response => Severity::NonBreaking,
+                };
+                self.report(severity, location, format!("restricted to {}", list(&new)));
+                return;
+            }
+            (Some(_), None) => {
+                let severity = match direction {
+                    Direction::Request => Severity::NonBreaking,
+                    Direction::Response => Severity::Breaking,
+                };
+                self.report(severity, location, "no longer restricted to an enum".into());
+                return;
+            }
+            (None, None) => return,
+        };
+
+        // Breaking either way, as clients send back values they have read
+        // from responses, such as a role
+        if !removed.is_empty() {
+            let message = match direction {
+                Direction::Request => "no longer accepts",
+                Direction::Response => "no longer returns",
+            };
+            self.report(
+                Severity::Breaking,
+                location,
+                format!("{} {}", message, list(&removed)),
+            );
+        }
+        if !added.is_empty() {
+            let (severity, message) = match direction {
+                Direction::Request => (Severity::NonBreaking, "now accepts"),
+                Direction::Response => (Severity::Breaking, "may now return"),
+            };
+            self.report(severity, location, format!("{} {}", message, list(&added)));
+        }
+    }
+
+    fn properties(&mut self, location: &str, old: &Value, new: &Value, direction: Direction) {
+        let (old_properties, old_required) = object_parts(self.old, old);
+        let (new_properties, new_required) = object_parts(self.new, new);
+
+        for (name, old_property) in &old_properties {
+            let Some(new_property) = new_properties.get(name) else {
+                let severity = match direction {
+                    Direction::Response if old_required.contains(name) => Severity::Breaking,
+                    _ => Severity::NonBreaking,
+                };
+                self.report(severity, location, format!("property `{}` removed", name));
+                continue;
+            };
+            match direction {
+                Direction::Request
+                    if !old_required.contains(name) && new_required.contains(name) =>
+                {
+                    self.report(
+                        Severity::Breaking,
+                        location,
+                        format!("property `{}` is now required", name),
+                    )
+                }
+                Direction::Response
+                    if old_required.contains(name) && !new_required.contains(name) =>
+                {
+                    self.report(
+                        Severity::Breaking,
+                        location,
+                        format!("property `{}` is no longer always returned", name),
+                    )
+                }
+                _ => {}
+            }
+            let property_location = format!("{}.{}", location, name);
+            self.schema(&property_location, old_property, new_property, direction);
+        }
+        for name in new_properties.keys() {
+            if old_properties.contains_key(name) {
+                continue;
+            }
+            if direction == Direction::Request && new_required.contains(name) {
+                self.report(
+                    Severity::Breaking,
+                    location,
+                    format!("required property `{}` added", name),
+                );
+            } else {
+                self.report(
+                    Severity::NonBreaking,
+                    location,
+                    format!("property `{}` added", name),
+                );
+            }
+        }
+    }
+}
+
+/// Parameters of an operation keyed by where they go and their name
+fn parameters<'v>(doc: &'v Value, operation: &'v Value) -> Vec<((String, String), &'v Value)> {
+    operation
+        .get("parameters")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|param| resolve(doc, param).0)
+        .map(|param| {
+            let key = |name| {
+                param
+                    .get(name)
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            ((key("in"), key("name")), param)
+        })
+        .collect()
+}
+
+/// The value behind a chain of local `$ref`s, with the last reference
+fn resolve<'v>(doc: &'v Value, mut value: &'v Value) -> (&'v Value, Option<&'v str>) {
+    let mut reference = None;
+    for _ in 0..MAX_REF_DEPTH {
+        let Some(target) = value.get("$ref").and_then(Value::as_str) else {
+            break;
+        };
+        let Some(resolved) = target.strip_prefix('#').and_then(|p| doc.pointer(p)) else {
+            break;
+        };
+        reference = Some(target);
+        value = resolved;
+    }
+    (value, reference)
+}
+
+/// Properties and required property names of an object schema, including
+/// those of its `allOf` members
+fn object_parts(doc: &Value, schema: &Value) -> (Map<String, Value>, HashSet<String>) {
+    let mut properties = object(schema.get("properties")).clone();
+    let mut required: HashSet<String> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|name| name.as_str().map(str::to_string))
+        .collect();
+    for member in schema
+        .get("allOf")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let (member_properties, member_required) = object_parts(doc, resolve(doc, member).0);
+        properties.extend(member_properties);
+        required.extend(member_required);
+    }
+    (properties, required)
+}
+
+fn object(value: Option<&Value>) -> &Map<String, Value> {
+    static EMPTY: std::sync::OnceLock<Map<String, Value>> = std::sync::OnceLock::new();
+    value
+        .and_then(Value::as_object)
+        .unwrap_or_else(|| EMPTY.get_or_init(Map::new))
+}
+
+fn flag(value: &Value, name: &str) -> bool {
+    value.get(name).and_then(Value::as_bool).unwrap_or_default()
+}
+
+/// `UserRole` for `#/components/schemas/UserRole`
+fn schema_name(reference: &str) -> &str {
+    reference.rsplit('/').next().unwrap_or(reference)
+}
+
+fn list(values: &[Value]) -> String {
+    values
+        .iter()
+        .map(Value::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn spec(role_values: &[&str], create_required: &[&str]) -> Value {
+        json!({
+            "paths": {
+                "/users": {
+                    "post": {
+                        "requestBody": {
+                            "required": true,
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "object",
+                                        "required": create_required,
+                                        "properties": {
+                                            "name": { "type": "string" },
+                                            "role": { "$ref": "#/components/schemas/UserRole" }
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        "responses": { "201": { "description": "Created" } }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "UserRole": { "type": "string", "enum": role_values }
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_identical_documents() {
+        let doc = spec(&["user", "admin"], &["name"]);
+        assert!(diff(&doc, &doc).is_empty());
+    }
+
+    #[test]
+    fn test_breaking_changes() {
+        let old = spec(&["user", "admin", "moderator"], &["name"]);
+        let new = spec(&["user", "admin"], &["name", "role"]);
+        let changes = diff(&old, &new);
+        assert_eq!(
+            changes,
+            vec![
+                Change {
+                    severity: Severity::Breaking,
+                    location: "POST /users request body".into(),
+                    message: "property `role` is now required".into(),
+                },
+                Change {
+                    severity: Severity::Breaking,
+                    location: "schema UserRole".into(),
+                    message: "no longer accepts \"moderator\"".into(),
+                },
+            ]
+        );
+
+        let mut removed = new.clone();
+        removed["paths"].as_object_mut().unwrap().remove("/users");
+        let changes = diff(&new, &removed);
+        assert_eq!(changes.len(), 1);
+        assert!(changes[0].is_breaking());
+        assert_eq!(changes[0].to_string(), "/users: path removed");
+    }
+
+    #[test]
+    fn test_non_breaking_changes() {
+        let old = spec(&["user"], &["name", "role"]);
+        let mut new = spec(&["user", "admin"], &["name"]);
+        new["paths"]["/users"]["get"] = json!({ "responses": {} });
+        let changes = diff(&old, &new);
+        assert!(changes.iter().all(|c| !c.is_breaking()), "{:?}", changes);
+        let messages: Vec<_> = changes.iter().map(|c| c.message.as_str()).collect();
+        assert_eq!(messages, ["operation added", "now accepts \"admin\""]);
+    }
+}
@@ -0,0 +1,86 @@
+use actix_rt::net::TcpStream;
+use actix_tls::accept::rustls_0_23::TlsStream;
+use actix_web::dev::Extensions;
+use rustls::{
+    crypto::{ring, CryptoProvider},
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+    server::WebPkiClientVerifier,
+    RootCertStore, ServerConfig,
+};
+use std::{any::Any, io, sync::Arc};
+
+use crate::config::TlsConfig;
+
+/// DER encoded end-entity certificate presented by the client of a connection
+#[derive(Debug, Clone)]
+pub struct PeerCertificate(pub Vec<u8>);
+
+/// Build the rustls server configuration, requesting client certificates
+/// when a client CA bundle is configured
+pub fn load_server_config(config: &TlsConfig) -> io::Result<ServerConfig> {
+    let (Some(cert_path), Some(key_path)) = (&config.cert_path, &config.key_path) else {
+        return Err(invalid("TLS_CERT_PATH and TLS_KEY_PATH must both be set"));
+    };
+
+    let cert_chain = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| invalid(&format!("Failed to read {}: {}", cert_path, e)))?;
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .map_err(|e| invalid(&format!("Failed to read {}: {}", key_path, e)))?;
+
+    let provider = Arc::new(ring::default_provider());
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| invalid(&e.to_string()))?;
+
+    let builder = match &config.client_ca_path {
+        Some(ca_path) => {
+            let verifier = client_verifier(ca_path, config.client_cert_required, provider)?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut server_config = builder
+        .with_single_cert(cert_chain, key)
+        .map_err(|e| invalid(&e.to_string()))?;
+    server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(server_config)
+}
+
+fn client_verifier(
+    ca_path: &str,
+    required: bool,
+    provider: Arc<CryptoProvider>,
+) -> io::Result<Arc<dyn rustls::server::danger::ClientCertVerifier>> {
+    let mut roots = RootCertStore::empty();
+    for cert in CertificateDer::pem_file_iter(ca_path)
+        .map_err(|e| invalid(&format!("Failed to read {}: {}", ca_path, e)))?
+    {
+        let cert = cert.map_err(|e| invalid(&format!("Failed to read {}: {}", ca_path, e)))?;
+        roots.add(cert).map_err(|e| invalid(&e.to_string()))?;
+    }
+
+    let builder = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+    let builder = if required {
+        builder
+    } else {
+        builder.allow_unauthenticated()
+    };
+    builder.build().map_err(|e| invalid(&e.to_string()))
+}
+
+/// `HttpServer::on_connect` callback exposing the client certificate of a
+/// TLS connection as [`PeerCertificate`] connection data
+pub fn on_connect(connection: &dyn Any, data: &mut Extensions) {
+    if let Some(stream) = connection.downcast_ref::<TlsStream<TcpStream>>() {
+        let (_, session) = stream.get_ref();
+        if let Some(cert) = session.peer_certificates().and_then(|certs| certs.first()) {
+            data.insert(PeerCertificate(cert.to_vec()));
+        }
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message.to_string())
+}
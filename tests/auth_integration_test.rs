@@ -169,10 +169,11 @@ fn test_error_types() {
     // Test that our error types can be created and match expected patterns
     let validation_error = AppError::Validation {
         message: "Test validation error".to_string(),
+        errors: Vec::new(),
     };
 
     match validation_error {
-        AppError::Validation { message } => {
+        AppError::Validation { message, .. } => {
             assert_eq!(message, "Test validation error");
         }
         _ => panic!("Expected validation error"),
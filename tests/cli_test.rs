@@ -1,14 +1,95 @@
 #[cfg(test)]
 mod tests {
-    use afaf_rest_rust::cmd::cli::{run, CliArgs};
+    use afaf_rest_rust::cmd::cli::{run, CliArgs, OpenApiArgs};
+    use std::path::PathBuf;
 
     #[test]
     fn test_cli_run() {
         let args = CliArgs {
             task: String::from("test_task"),
+            ..Default::default()
         };
 
         let result = run(args);
         assert!(result.is_ok());
     }
+
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("cli-test-{}-{}", std::process::id(), name))
+    }
+
+    fn openapi_task(task: &str, openapi: OpenApiArgs) -> Result<(), std::io::Error> {
+        run(CliArgs {
+            task: task.to_string(),
+            openapi,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_export_and_diff_openapi() {
+        let json = temp_file("openapi.json");
+        let yaml = temp_file("openapi.yaml");
+        for spec in [&json, &yaml] {
+            openapi_task(
+                "export-openapi",
+                OpenApiArgs {
+                    spec: Some(spec.clone()),
+                    api_version: Some("1".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+
+        let exported: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&json).unwrap()).unwrap();
+        assert_eq!(exported["info"]["version"], "1.0.0");
+        assert!(exported["paths"]["/api/v1/users"].is_object());
+        let exported_yaml = std::fs::read_to_string(&yaml).unwrap();
+        assert!(exported_yaml.starts_with("openapi: 3."));
+
+        // The base document selects the version it is compared with
+        for base in [&json, &yaml] {
+            let result = openapi_task(
+                "diff-openapi",
+                OpenApiArgs {
+                    base: Some(base.clone()),
+                    ..Default::default()
+                },
+            );
+            assert!(result.is_ok(), "{:?}", result);
+        }
+
+        // A previous document with a role the API no longer has
+        let mut previous = exported.clone();
+        previous["components"]["schemas"]["UserRole"]["enum"]
+            .as_array_mut()
+            .unwrap()
+            .push("superadmin".into());
+        let base = temp_file("previous.json");
+        std::fs::write(&base, serde_json::to_vec(&previous).unwrap()).unwrap();
+        let result = openapi_task(
+            "diff-openapi",
+            OpenApiArgs {
+                base: Some(base.clone()),
+                spec: Some(yaml.clone()),
+                ..Default::default()
+            },
+        );
+        assert!(result.is_err());
+
+        let result = openapi_task(
+            "export-openapi",
+            OpenApiArgs {
+                spec: Some(temp_file("openapi.txt")),
+                ..Default::default()
+            },
+        );
+        assert!(result.is_err());
+
+        for file in [json, yaml, base] {
+            std::fs::remove_file(file).unwrap();
+        }
+    }
 }
@@ -0,0 +1,33 @@
+//! Fixtures shared by the integration tests.
+//!
+//! Declared with `mod common;`, or `#[macro_use] mod common;` where [`app!`]
+//! is used; each test crate uses only some of them.
+#![allow(dead_code, unused_macros)]
+
+use actix_web::{dev::ServiceResponse, http::header::AsHeaderName};
+use afaf_rest_rust::config::Config;
+use sqlx::PgPool;
+
+/// Initialize a test service from an `App` set up by `$configure`, a
+/// `FnOnce(&mut web::ServiceConfig)` holding the routes under test
+macro_rules! app {
+    ($configure:expr) => {
+        actix_web::test::init_service(actix_web::App::new().configure($configure)).await
+    };
+}
+
+/// Value of the `name` header, empty when the response has none
+pub fn header_value(resp: &ServiceResponse, name: impl AsHeaderName) -> String {
+    resp.headers()
+        .get(name)
+        .map(|v| v.to_str().unwrap().to_string())
+        .unwrap_or_default()
+}
+
+/// Connect to the database configured by `DATABASE_URL`
+pub async fn setup() -> PgPool {
+    let config = Config::from_env();
+    PgPool::connect(&config.database_url)
+        .await
+        .expect("Failed to connect to database")
+}
@@ -0,0 +1,157 @@
+#[macro_use]
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::header_value;
+    use actix_web::{
+        http::header::{self, HttpDate},
+        test, web, HttpResponse,
+    };
+    use afaf_rest_rust::core::rest::middleware::conditional_get::{
+        weak_etag, CachePolicy, ConditionalGet,
+    };
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    fn modified_at() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_767_225_600)
+    }
+
+    async fn items() -> HttpResponse {
+        HttpResponse::Ok().json(vec!["a", "b"])
+    }
+
+    async fn profile() -> HttpResponse {
+        HttpResponse::Ok()
+            .insert_header(header::ETag(header::EntityTag::new_strong("7".to_string())))
+            .insert_header(header::LastModified(modified_at().into()))
+            .body("profile")
+    }
+
+    async fn missing() -> HttpResponse {
+        HttpResponse::NotFound().finish()
+    }
+
+    fn routes(cfg: &mut web::ServiceConfig) {
+        cfg.service(
+            web::resource("/items")
+                .wrap(ConditionalGet::new(CachePolicy::public(60)))
+                .route(web::get().to(items))
+                .route(web::post().to(items)),
+        )
+        .service(
+            web::resource("/profile")
+                .wrap(ConditionalGet::new(CachePolicy::private(0)))
+                .route(web::get().to(profile)),
+        )
+        .service(
+            web::resource("/missing")
+                .wrap(ConditionalGet::new(CachePolicy::private(0)))
+                .route(web::get().to(missing)),
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_weak_etag_and_if_none_match() {
+        let app = app!(routes);
+
+        let req = test::TestRequest::get().uri("/items").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let etag = header_value(&resp, header::ETAG);
+        assert_eq!(etag, weak_etag(br#"["a","b"]"#).to_string());
+        assert!(etag.starts_with("W/"));
+        assert_eq!(
+            header_value(&resp, header::CACHE_CONTROL),
+            "public, max-age=60"
+        );
+        assert!(resp.headers().get(header::VARY).is_none());
+
+        let req = test::TestRequest::get()
+            .uri("/items")
+            .insert_header((header::IF_NONE_MATCH, etag.as_str()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 304);
+        assert_eq!(header_value(&resp, header::ETAG), etag);
+        assert!(test::read_body(resp).await.is_empty());
+
+        let req = test::TestRequest::get()
+            .uri("/items")
+            .insert_header((header::IF_NONE_MATCH, "W/\"stale\""))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(test::read_body(resp).await, r#"["a","b"]"#);
+
+        // Only GET is conditional
+        let req = test::TestRequest::post()
+            .uri("/items")
+            .insert_header((header::IF_NONE_MATCH, etag.as_str()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert!(resp.headers().get(header::ETAG).is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_handler_etag_and_last_modified() {
+        let app = app!(routes);
+
+        let req = test::TestRequest::get().uri("/profile").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(header_value(&resp, header::ETAG), "\"7\"");
+        assert_eq!(
+            header_value(&resp, header::CACHE_CONTROL),
+            "private, max-age=0"
+        );
+        assert_eq!(header_value(&resp, header::VARY), "Authorization, Cookie");
+
+        // Weak comparison matches the strong handler ETag
+        let req = test::TestRequest::get()
+            .uri("/profile")
+            .insert_header((header::IF_NONE_MATCH, "W/\"7\""))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 304);
+
+        let since = |time: SystemTime| HttpDate::from(time).to_string();
+        let req = test::TestRequest::get()
+            .uri("/profile")
+            .insert_header((header::IF_MODIFIED_SINCE, since(modified_at())))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 304);
+        assert_eq!(header_value(&resp, header::VARY), "Authorization, Cookie");
+
+        let req = test::TestRequest::get()
+            .uri("/profile")
+            .insert_header((
+                header::IF_MODIFIED_SINCE,
+                since(modified_at() - Duration::from_secs(60)),
+            ))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+        // If-None-Match takes precedence over If-Modified-Since
+        let req = test::TestRequest::get()
+            .uri("/profile")
+            .insert_header((header::IF_NONE_MATCH, "\"6\""))
+            .insert_header((header::IF_MODIFIED_SINCE, since(modified_at())))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+    }
+
+    #[actix_web::test]
+    async fn test_errors_are_not_cached() {
+        let app = app!(routes);
+
+        let req = test::TestRequest::get()
+            .uri("/missing")
+            .insert_header((header::IF_NONE_MATCH, "*"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+        assert!(resp.headers().get(header::ETAG).is_none());
+        assert!(resp.headers().get(header::CACHE_CONTROL).is_none());
+    }
+}
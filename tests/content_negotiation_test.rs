@@ -0,0 +1,225 @@
+#[macro_use]
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::header_value;
+    use actix_web::{http::header, test, web, HttpResponse};
+    use afaf_rest_rust::core::{
+        domain::error::AppError,
+        rest::{
+            handler::{body::Body, response::build_success_response},
+            middleware::{
+                conditional_get::{CachePolicy, ConditionalGet},
+                content_negotiation::ContentNegotiation,
+            },
+        },
+    };
+    use serde::{Deserialize, Serialize};
+    use serde_json::{json, Value};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Item {
+        name: String,
+        count: u32,
+    }
+
+    async fn item() -> HttpResponse {
+        HttpResponse::Ok().json(build_success_response(
+            Item {
+                name: "widget".to_string(),
+                count: 3,
+            },
+            "Item retrieved",
+        ))
+    }
+
+    async fn create(calls: web::Data<Arc<AtomicUsize>>, body: Body<Item>) -> HttpResponse {
+        calls.fetch_add(1, Ordering::SeqCst);
+        HttpResponse::Created().json(build_success_response(body.into_inner(), "Item created"))
+    }
+
+    async fn missing() -> Result<HttpResponse, AppError> {
+        Err(AppError::NotFound {
+            resource: "Item".to_string(),
+        })
+    }
+
+    async fn text() -> HttpResponse {
+        HttpResponse::Ok().content_type("text/csv").body("a,b\n")
+    }
+
+    fn routes(calls: &Arc<AtomicUsize>) -> impl FnOnce(&mut web::ServiceConfig) {
+        let calls = calls.clone();
+        move |cfg| {
+            cfg.app_data(web::Data::new(calls)).service(
+                web::scope("")
+                    .wrap(ContentNegotiation)
+                    .service(
+                        web::resource("/item")
+                            .wrap(ConditionalGet::new(CachePolicy::private(0)))
+                            .route(web::get().to(item)),
+                    )
+                    .route("/items", web::post().to(create))
+                    .route("/missing", web::get().to(missing))
+                    .route("/export", web::get().to(text)),
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_response_encodings() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app!(routes(&calls));
+
+        let req = test::TestRequest::get().uri("/item").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            header_value(&resp, header::CONTENT_TYPE),
+            "application/json"
+        );
+        assert_eq!(
+            header_value(&resp, header::VARY),
+            "Authorization, Cookie, Accept"
+        );
+        let etag = header_value(&resp, header::ETAG);
+
+        let req = test::TestRequest::get()
+            .uri("/item")
+            .insert_header((header::ACCEPT, "application/msgpack"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(
+            header_value(&resp, header::CONTENT_TYPE),
+            "application/msgpack"
+        );
+        let body: Value = rmp_serde::from_slice(&test::read_body(resp).await).unwrap();
+        assert_eq!(body["data"], json!({ "name": "widget", "count": 3 }));
+
+        // Highest quality wins
+        let req = test::TestRequest::get()
+            .uri("/item")
+            .insert_header((
+                header::ACCEPT,
+                "application/json;q=0.5, application/cbor, text/html",
+            ))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            header_value(&resp, header::CONTENT_TYPE),
+            "application/cbor"
+        );
+        let body: Value = ciborium::from_reader(&test::read_body(resp).await[..]).unwrap();
+        assert_eq!(body["message"], "Item retrieved");
+
+        // Not modified responses are negotiated too
+        let req = test::TestRequest::get()
+            .uri("/item")
+            .insert_header((header::ACCEPT, "application/cbor"))
+            .insert_header((header::IF_NONE_MATCH, etag))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 304);
+        assert!(header_value(&resp, header::VARY).ends_with("Accept"));
+
+        // Errors are encoded like any other response
+        let req = test::TestRequest::get()
+            .uri("/missing")
+            .insert_header((header::ACCEPT, "application/x-msgpack"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+        let body: Value = rmp_serde::from_slice(&test::read_body(resp).await).unwrap();
+        assert_eq!(body["error"], "not_found");
+
+        // Non-JSON responses are left alone
+        let req = test::TestRequest::get()
+            .uri("/export")
+            .insert_header((header::ACCEPT, "text/csv"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(test::read_body(resp).await, "a,b\n");
+    }
+
+    #[actix_web::test]
+    async fn test_not_acceptable() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app!(routes(&calls));
+
+        for accept in ["text/html", "application/json;q=0, application/xml"] {
+            let req = test::TestRequest::get()
+                .uri("/item")
+                .insert_header((header::ACCEPT, accept))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 406);
+            let body: Value = test::read_body_json(resp).await;
+            assert_eq!(body["error"], "not_acceptable");
+        }
+
+        // Refused before the handler runs
+        let req = test::TestRequest::post()
+            .uri("/items")
+            .insert_header((header::ACCEPT, "text/html"))
+            .set_json(json!({ "name": "widget", "count": 1 }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 406);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_request_bodies() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app!(routes(&calls));
+        let item = Item {
+            name: "gadget".to_string(),
+            count: 2,
+        };
+
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&item, &mut cbor).unwrap();
+        let bodies = [
+            ("application/json", serde_json::to_vec(&item).unwrap()),
+            (
+                "application/msgpack",
+                rmp_serde::to_vec_named(&item).unwrap(),
+            ),
+            ("application/cbor", cbor),
+        ];
+        for (content_type, body) in bodies {
+            let req = test::TestRequest::post()
+                .uri("/items")
+                .insert_header((header::CONTENT_TYPE, content_type))
+                .set_payload(body)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 201, "{}", content_type);
+            let body: Value = test::read_body_json(resp).await;
+            assert_eq!(body["data"]["name"], "gadget");
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let req = test::TestRequest::post()
+            .uri("/items")
+            .insert_header((header::CONTENT_TYPE, "text/plain"))
+            .set_payload("gadget")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 415);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"], "unsupported_media_type");
+
+        let req = test::TestRequest::post()
+            .uri("/items")
+            .insert_header((header::CONTENT_TYPE, "application/msgpack"))
+            .set_payload(vec![0xc1])
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+    }
+}
@@ -0,0 +1,213 @@
+#[macro_use]
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::header_value;
+    use actix_web::{
+        dev::ServiceResponse,
+        http::{header, Method},
+        test, web, HttpResponse,
+    };
+    use afaf_rest_rust::{
+        config::BodyLimitConfig,
+        core::rest::{
+            handler::{
+                body::Body,
+                extract::{self, AllowedMethods},
+            },
+            router,
+        },
+    };
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+
+    #[derive(Debug, Deserialize)]
+    struct Contact {
+        #[allow(dead_code)]
+        email: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Signup {
+        #[allow(dead_code)]
+        name: String,
+        #[allow(dead_code)]
+        contacts: Vec<Contact>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Search {
+        #[allow(dead_code)]
+        q: String,
+        #[allow(dead_code)]
+        limit: Option<u32>,
+    }
+
+    async fn signup(_: Body<Signup>) -> HttpResponse {
+        HttpResponse::Created().finish()
+    }
+
+    async fn scim_signup(_: web::Json<Signup>) -> HttpResponse {
+        HttpResponse::Created().finish()
+    }
+
+    async fn item(_: web::Path<Uuid>) -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    async fn search(_: web::Query<Search>) -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    fn allowed_methods() -> AllowedMethods {
+        let mut allowed = AllowedMethods::default();
+        allowed.add("/signup", Method::POST);
+        allowed.add("/search", Method::GET);
+        allowed
+    }
+
+    fn routes(cfg: &mut web::ServiceConfig) {
+        cfg.app_data(allowed_methods())
+            .configure(extract::configure(BodyLimitConfig {
+                json_bytes: 64,
+                upload_bytes: 64,
+            }))
+            .route("/signup", web::post().to(signup))
+            .route("/scim/signup", web::post().to(scim_signup))
+            .route("/items/{id}", web::get().to(item))
+            .route("/search", web::get().to(search));
+    }
+
+    async fn error_body(resp: ServiceResponse, status: u16) -> Value {
+        assert_eq!(resp.status(), status);
+        test::read_body_json(resp).await
+    }
+
+    #[actix_web::test]
+    async fn test_body_errors() {
+        let app = app!(routes);
+
+        // Nested fields are located by path
+        let req = test::TestRequest::post()
+            .uri("/signup")
+            .set_json(json!({ "name": "Ada", "contacts": [{ "email": 1 }] }))
+            .to_request();
+        let body = error_body(test::call_service(&app, req).await, 400).await;
+        assert_eq!(body["error"], "validation_error");
+        assert_eq!(body["errors"][0]["field"], "contacts[0].email");
+        assert_eq!(body["errors"][0]["code"], "invalid");
+        assert_eq!(body["errors"][0]["params"]["line"], 1);
+
+        let req = test::TestRequest::post()
+            .uri("/signup")
+            .set_json(json!({ "contacts": [] }))
+            .to_request();
+        let body = error_body(test::call_service(&app, req).await, 400).await;
+        assert_eq!(body["errors"][0]["field"], "name");
+        assert_eq!(body["errors"][0]["code"], "required");
+
+        let req = test::TestRequest::post()
+            .uri("/signup")
+            .insert_header((header::CONTENT_TYPE, "application/json"))
+            .set_payload("{\"name\":")
+            .to_request();
+        let body = error_body(test::call_service(&app, req).await, 400).await;
+        assert_eq!(body["errors"][0]["field"], "name");
+        assert_eq!(body["errors"][0]["code"], "syntax");
+
+        let req = test::TestRequest::post()
+            .uri("/signup")
+            .insert_header((header::CONTENT_TYPE, "application/json"))
+            .set_payload("nope")
+            .to_request();
+        let body = error_body(test::call_service(&app, req).await, 400).await;
+        assert_eq!(body["errors"][0]["field"], "body");
+
+        let req = test::TestRequest::post()
+            .uri("/signup")
+            .set_json(json!({ "name": "x".repeat(100), "contacts": [] }))
+            .to_request();
+        let body = error_body(test::call_service(&app, req).await, 413).await;
+        assert_eq!(body["error"], "payload_too_large");
+
+        // web::Json reports through the same envelope
+        let req = test::TestRequest::post()
+            .uri("/scim/signup")
+            .set_json(json!({ "name": "Ada" }))
+            .to_request();
+        let body = error_body(test::call_service(&app, req).await, 400).await;
+        assert_eq!(body["errors"][0]["field"], "contacts");
+
+        let req = test::TestRequest::post()
+            .uri("/scim/signup")
+            .insert_header((header::CONTENT_TYPE, "text/plain"))
+            .set_payload("Ada")
+            .to_request();
+        let body = error_body(test::call_service(&app, req).await, 415).await;
+        assert_eq!(body["error"], "unsupported_media_type");
+
+        let req = test::TestRequest::post()
+            .uri("/scim/signup")
+            .set_json(json!({ "name": "x".repeat(100), "contacts": [] }))
+            .to_request();
+        error_body(test::call_service(&app, req).await, 413).await;
+    }
+
+    #[actix_web::test]
+    async fn test_path_and_query_errors() {
+        let app = app!(routes);
+
+        let req = test::TestRequest::get()
+            .uri("/items/not-a-uuid")
+            .to_request();
+        let body = error_body(test::call_service(&app, req).await, 400).await;
+        assert_eq!(body["errors"][0]["field"], "id");
+
+        let req = test::TestRequest::get().uri("/search?limit=2").to_request();
+        let body = error_body(test::call_service(&app, req).await, 400).await;
+        assert_eq!(body["errors"][0]["field"], "q");
+        assert_eq!(body["errors"][0]["code"], "required");
+
+        let req = test::TestRequest::get()
+            .uri("/search?q=ada&limit=many")
+            .to_request();
+        let body = error_body(test::call_service(&app, req).await, 400).await;
+        assert_eq!(body["errors"][0]["field"], "query");
+    }
+
+    #[actix_web::test]
+    async fn test_unmatched_routes() {
+        let app = app!(routes);
+
+        let req = test::TestRequest::get().uri("/nowhere").to_request();
+        let body = error_body(test::call_service(&app, req).await, 404).await;
+        assert_eq!(body["error"], "not_found");
+
+        let req = test::TestRequest::delete().uri("/search").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(header_value(&resp, header::ALLOW), "GET");
+        let body = error_body(resp, 405).await;
+        assert_eq!(body["error"], "method_not_allowed");
+        assert_eq!(body["message"], "DELETE is not supported for /search");
+
+        // The application's routes list their methods, with and without a
+        // version prefix
+        let app = app!(|cfg: &mut web::ServiceConfig| {
+            cfg.configure(extract::configure(BodyLimitConfig::default()))
+                .configure(router::config);
+        });
+        for path in ["/api/v1/users/{id}", "/users/{id}"] {
+            let req = test::TestRequest::put()
+                .uri(&path.replace("{id}", &Uuid::new_v4().to_string()))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 405);
+            let allow = header_value(&resp, header::ALLOW);
+            let mut allow: Vec<&str> = allow.split(", ").collect();
+            allow.sort();
+            assert_eq!(allow, ["DELETE", "GET", "PATCH"]);
+        }
+    }
+}
@@ -0,0 +1,243 @@
+#[macro_use]
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::header_value;
+    use actix_web::{http::header, test, web, HttpResponse};
+    use afaf_rest_rust::{
+        config::LocaleConfig,
+        core::{
+            domain::error::AppError,
+            rest::{
+                handler::{
+                    body::ValidatedJson,
+                    fields::{FieldsQuery, Selection, USER_FIELDS},
+                },
+                middleware::{
+                    conditional_get::{CachePolicy, ConditionalGet},
+                    request_id::RequestId,
+                },
+                validation::ValidatedPayload,
+            },
+        },
+    };
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+    use validator::Validate;
+
+    #[derive(Debug, Deserialize, Validate)]
+    struct Signup {
+        #[validate(email(message = "Invalid email format."))]
+        email: String,
+        #[validate(length(min = 8, message = "Password is too short."))]
+        password: String,
+    }
+
+    impl ValidatedPayload for Signup {}
+
+    async fn signup(_: ValidatedJson<Signup>) -> HttpResponse {
+        HttpResponse::Created().finish()
+    }
+
+    async fn missing() -> Result<HttpResponse, AppError> {
+        Err(AppError::NotFound {
+            resource: "Item".to_string(),
+        })
+    }
+
+    async fn taken() -> Result<HttpResponse, AppError> {
+        Err(AppError::Conflict {
+            message: "Email already exists".to_string(),
+        })
+    }
+
+    async fn unsortable() -> Result<HttpResponse, AppError> {
+        Err(AppError::Validation {
+            message: "Cannot sort by nickname".to_string(),
+            errors: Vec::new(),
+        })
+    }
+
+    async fn user(query: web::Query<FieldsQuery>) -> Result<HttpResponse, AppError> {
+        let selection = Selection::parse(&query, &USER_FIELDS)?;
+        let user = json!({ "id": 1, "role": "admin" });
+        Ok(HttpResponse::Ok().json(selection.select(user)?))
+    }
+
+    fn routes(default_locale: &str) -> impl FnOnce(&mut web::ServiceConfig) {
+        let config = LocaleConfig {
+            default_locale: default_locale.to_string(),
+        };
+        move |cfg| {
+            cfg.app_data(web::Data::new(config)).service(
+                web::scope("")
+                    .wrap(RequestId)
+                    .route("/signup", web::post().to(signup))
+                    .route("/missing", web::get().to(missing))
+                    .route("/taken", web::get().to(taken))
+                    .route("/unsortable", web::get().to(unsortable))
+                    .service(
+                        web::resource("/user")
+                            .wrap(ConditionalGet::new(CachePolicy::private(0)))
+                            .route(web::get().to(user)),
+                    ),
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_localized_errors() {
+        let app = app!(routes("en"));
+
+        // English stays as written in the code
+        let req = test::TestRequest::get().uri("/missing").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(header_value(&resp, header::CONTENT_LANGUAGE), "en");
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["message"], "Resource not found");
+
+        // The most preferred language with a catalog wins
+        let req = test::TestRequest::get()
+            .uri("/missing")
+            .insert_header((header::ACCEPT_LANGUAGE, "pt;q=0.9, id-ID;q=0.8, en;q=0.5"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+        assert_eq!(header_value(&resp, header::CONTENT_LANGUAGE), "id");
+        assert_eq!(header_value(&resp, header::VARY), "Accept-Language");
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"], "not_found");
+        assert_eq!(body["message"], "Sumber daya tidak ditemukan");
+
+        // Refused languages are skipped
+        let req = test::TestRequest::get()
+            .uri("/missing")
+            .insert_header((header::ACCEPT_LANGUAGE, "de;q=0, fr;q=0.1"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(header_value(&resp, header::CONTENT_LANGUAGE), "fr");
+    }
+
+    #[actix_web::test]
+    async fn test_localized_field_errors() {
+        let app = app!(routes("en"));
+
+        let req = test::TestRequest::post()
+            .uri("/signup")
+            .insert_header((header::ACCEPT_LANGUAGE, "fr-CA"))
+            .set_json(json!({ "email": "nope", "password": "short" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+        assert_eq!(header_value(&resp, header::CONTENT_LANGUAGE), "fr");
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["message"], "La requête est invalide");
+        assert_eq!(body["errors"][0]["field"], "email");
+        assert_eq!(
+            body["errors"][0]["message"],
+            "email doit être une adresse e-mail valide"
+        );
+        assert_eq!(body["errors"][1]["field"], "password");
+        assert_eq!(
+            body["errors"][1]["message"],
+            "password doit contenir au moins 8 caractères"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_localized_specific_messages() {
+        let app = app!(routes("en"));
+
+        // A message with a translation of its own keeps its meaning
+        let req = test::TestRequest::get()
+            .uri("/taken")
+            .insert_header((header::ACCEPT_LANGUAGE, "de"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 409);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["message"], "E-Mail-Adresse existiert bereits");
+        assert!(body.get("detail").is_none());
+
+        // Otherwise the English message is kept next to the generic one
+        let req = test::TestRequest::get()
+            .uri("/unsortable")
+            .insert_header((header::ACCEPT_LANGUAGE, "fr"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["message"], "La requête est invalide");
+        assert_eq!(body["detail"], "Cannot sort by nickname");
+
+        // Problem details carry the English message as the detail
+        let req = test::TestRequest::get()
+            .uri("/unsortable")
+            .insert_header((header::ACCEPT_LANGUAGE, "fr"))
+            .insert_header((header::ACCEPT, "application/problem+json"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["title"], "La requête est invalide");
+        assert_eq!(body["detail"], "Cannot sort by nickname");
+
+        // English responses have no detail
+        let req = test::TestRequest::get().uri("/unsortable").to_request();
+        let resp = test::call_service(&app, req).await;
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["message"], "Cannot sort by nickname");
+        assert!(body.get("detail").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_default_locale() {
+        let app = app!(routes("de"));
+
+        // Languages without a catalog fall back to the configured default
+        let req = test::TestRequest::get()
+            .uri("/missing")
+            .insert_header((header::ACCEPT_LANGUAGE, "ja"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(header_value(&resp, header::CONTENT_LANGUAGE), "de");
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["message"], "Ressource nicht gefunden");
+
+        // An unsupported default is the source language
+        let app = app!(routes("xx"));
+        let req = test::TestRequest::get().uri("/missing").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(header_value(&resp, header::CONTENT_LANGUAGE), "en");
+    }
+
+    #[actix_web::test]
+    async fn test_localized_expansion() {
+        let app = app!(routes("en"));
+
+        let req = test::TestRequest::get()
+            .uri("/user?expand=role")
+            .insert_header((header::ACCEPT_LANGUAGE, "de"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(header_value(&resp, header::CONTENT_LANGUAGE), "de");
+        assert_eq!(
+            header_value(&resp, header::VARY),
+            "Authorization, Cookie, Accept-Language"
+        );
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body["role"]["description"],
+            "Administrator mit allen Berechtigungen"
+        );
+
+        // Nothing is localized without the expansion
+        let req = test::TestRequest::get()
+            .uri("/user")
+            .insert_header((header::ACCEPT_LANGUAGE, "de"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(header_value(&resp, header::CONTENT_LANGUAGE), "");
+        assert_eq!(header_value(&resp, header::VARY), "Authorization, Cookie");
+    }
+}
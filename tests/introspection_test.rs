@@ -0,0 +1,193 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::header, test, web, App};
+    use afaf_rest_rust::{
+        config::{Config, IntrospectionConfig},
+        core::{
+            domain::{
+                auth::{
+                    jwt::{Claims, JwtService},
+                    revocation::TokenRevocationList,
+                },
+                users::repository::UserRepository,
+            },
+            rest::handler::oauth::{introspect, IntrospectionResponse},
+        },
+    };
+    use sqlx::PgPool;
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    fn introspection_config() -> IntrospectionConfig {
+        IntrospectionConfig {
+            api_keys: vec!["test-api-key".to_string()],
+            clients: HashMap::from([("gateway".to_string(), "gateway-secret".to_string())]),
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_introspect_active_token() {
+        let jwt_service = JwtService::new("test_secret");
+        let user_id = Uuid::new_v4();
+        let token = jwt_service
+            .generate_token(user_id, "test@example.com", "moderator")
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(jwt_service))
+                .app_data(web::Data::new(TokenRevocationList::new()))
+                .app_data(web::Data::new(introspection_config()))
+                .service(introspect),
+        )
+        .await;
+
+        // "gateway:gateway-secret" encoded as base64
+        let req = test::TestRequest::post()
+            .uri("/oauth/introspect")
+            .insert_header((
+                header::AUTHORIZATION,
+                "Basic Z2F0ZXdheTpnYXRld2F5LXNlY3JldA==",
+            ))
+            .set_form([("token", token.as_str())])
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert!(resp.status().is_success());
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-store"
+        );
+
+        let body: IntrospectionResponse = test::read_body_json(resp).await;
+        assert!(body.active);
+        assert_eq!(body.sub, Some(user_id.to_string()));
+        assert_eq!(body.client_id.as_deref(), Some("gateway"));
+        assert_eq!(body.role.as_deref(), Some("moderator"));
+        assert_eq!(body.scope.as_deref(), Some("user moderator"));
+    }
+
+    #[actix_web::test]
+    async fn test_introspect_revoked_and_invalid_tokens() {
+        let jwt_service = JwtService::new("test_secret");
+        let token = jwt_service
+            .generate_token(Uuid::new_v4(), "test@example.com", "user")
+            .unwrap();
+        let claims = jwt_service.verify_token(&token).unwrap();
+
+        let revocations = TokenRevocationList::new();
+        revocations.revoke(&claims.jti, claims.exp);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(jwt_service))
+                .app_data(web::Data::new(revocations))
+                .app_data(web::Data::new(introspection_config()))
+                .service(introspect),
+        )
+        .await;
+
+        // Signed, but its subject is not a user id
+        let malformed_sub = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &Claims {
+                sub: "not-a-uuid".to_string(),
+                jti: Uuid::new_v4().to_string(),
+                ..claims
+            },
+            &jsonwebtoken::EncodingKey::from_secret(b"test_secret"),
+        )
+        .unwrap();
+
+        for token in [token.as_str(), malformed_sub.as_str(), "not-a-token"] {
+            let req = test::TestRequest::post()
+                .uri("/oauth/introspect")
+                .insert_header(("X-API-Key", "test-api-key"))
+                .set_form([("token", token)])
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+
+            assert!(resp.status().is_success());
+            assert_eq!(
+                resp.headers().get(header::CACHE_CONTROL).unwrap(),
+                "no-store"
+            );
+
+            let body: IntrospectionResponse = test::read_body_json(resp).await;
+            assert!(!body.active);
+            assert!(body.sub.is_none());
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_introspect_requires_client_authentication() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(JwtService::new("test_secret")))
+                .app_data(web::Data::new(TokenRevocationList::new()))
+                .app_data(web::Data::new(introspection_config()))
+                .service(introspect),
+        )
+        .await;
+
+        let missing = test::TestRequest::post()
+            .uri("/oauth/introspect")
+            .set_form([("token", "anything")])
+            .to_request();
+        assert_eq!(test::call_service(&app, missing).await.status(), 401);
+
+        let wrong_key = test::TestRequest::post()
+            .uri("/oauth/introspect")
+            .insert_header(("X-API-Key", "wrong-key"))
+            .set_form([("token", "anything")])
+            .to_request();
+        assert_eq!(test::call_service(&app, wrong_key).await.status(), 401);
+    }
+
+    #[actix_web::test]
+    async fn test_introspect_token_of_deleted_user() {
+        let config = Config::from_env();
+        let pool = PgPool::connect(&config.database_url)
+            .await
+            .expect("Failed to connect to database");
+        let repo = UserRepository { pool: &pool };
+        let user = repo
+            .create_user(
+                "Introspected",
+                &format!("introspect_{}@example.com", Uuid::new_v4()),
+            )
+            .await
+            .unwrap();
+
+        let jwt_service = JwtService::new("test_secret");
+        let token = jwt_service
+            .generate_token(user.id, &user.email, &user.role)
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(jwt_service))
+                .app_data(web::Data::new(TokenRevocationList::new()))
+                .app_data(web::Data::new(introspection_config()))
+                .service(introspect),
+        )
+        .await;
+        let introspect_request = || {
+            test::TestRequest::post()
+                .uri("/oauth/introspect")
+                .insert_header(("X-API-Key", "test-api-key"))
+                .set_form([("token", token.as_str())])
+                .to_request()
+        };
+
+        let body: IntrospectionResponse =
+            test::call_and_read_body_json(&app, introspect_request()).await;
+        assert!(body.active);
+
+        assert!(repo.delete_user(user.id).await.unwrap());
+        let body: IntrospectionResponse =
+            test::call_and_read_body_json(&app, introspect_request()).await;
+        assert!(!body.active);
+    }
+}
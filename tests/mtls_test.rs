@@ -0,0 +1,283 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{web, App, HttpResponse, HttpServer};
+    use afaf_rest_rust::{
+        config::{Config, SessionConfig, TlsConfig},
+        core::{
+            domain::{
+                auth::service::AuthService,
+                users::model::{CreateUserRequest, UserRole},
+            },
+            rest::middleware::{
+                auth::{AuthData, Principal},
+                client_cert::ClientCertAuth,
+            },
+        },
+        pkg::tls,
+    };
+    use rcgen::{
+        BasicConstraints, Certificate, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa,
+        KeyPair, SanType,
+    };
+    use rustls::{
+        crypto::ring,
+        pki_types::{PrivateKeyDer, ServerName},
+        ClientConfig, RootCertStore,
+    };
+    use sqlx::PgPool;
+    use std::{collections::HashMap, fs, net::SocketAddr, sync::Arc};
+    use tempfile::TempDir;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use tokio_rustls::TlsConnector;
+    use uuid::Uuid;
+
+    struct Ca {
+        cert: Certificate,
+        key: KeyPair,
+    }
+
+    impl Ca {
+        fn new(name: &str) -> Self {
+            let key = KeyPair::generate().unwrap();
+            let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            params.distinguished_name.push(DnType::CommonName, name);
+            let cert = params.self_signed(&key).unwrap();
+            Self { cert, key }
+        }
+
+        fn issue(
+            &self,
+            common_name: &str,
+            sans: Vec<SanType>,
+            usage: ExtendedKeyUsagePurpose,
+        ) -> (Certificate, KeyPair) {
+            let key = KeyPair::generate().unwrap();
+            let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+            params
+                .distinguished_name
+                .push(DnType::CommonName, common_name);
+            params.subject_alt_names = sans;
+            params.extended_key_usages = vec![usage];
+            let cert = params.signed_by(&key, &self.cert, &self.key).unwrap();
+            (cert, key)
+        }
+
+        fn client(&self, common_name: &str, sans: Vec<SanType>) -> (Certificate, KeyPair) {
+            self.issue(common_name, sans, ExtendedKeyUsagePurpose::ClientAuth)
+        }
+    }
+
+    /// Write the server certificate and CA bundle to a temporary directory,
+    /// which is removed when the returned `TempDir` is dropped
+    fn tls_config(ca: &Ca, client_cert_required: bool) -> (TempDir, TlsConfig) {
+        let dir = TempDir::with_prefix("mtls_").unwrap();
+
+        let (server_cert, server_key) = ca.issue(
+            "localhost",
+            vec![SanType::DnsName("localhost".try_into().unwrap())],
+            ExtendedKeyUsagePurpose::ServerAuth,
+        );
+        let path = |name: &str, contents: String| {
+            let path = dir.path().join(name);
+            fs::write(&path, contents).unwrap();
+            Some(path.to_string_lossy().into_owned())
+        };
+
+        let config = TlsConfig {
+            cert_path: path("server.pem", server_cert.pem()),
+            key_path: path("server.key", server_key.serialize_pem()),
+            client_ca_path: path("ca.pem", ca.cert.pem()),
+            client_cert_required,
+            client_services: HashMap::from([("billing.internal".to_string(), UserRole::Admin)]),
+        };
+        (dir, config)
+    }
+
+    async fn whoami(auth: AuthData) -> HttpResponse {
+        match auth.principal {
+            Principal::Service { name, .. } => {
+                HttpResponse::Ok().body(format!("service:{}:{}", name, auth.role))
+            }
+            Principal::User => HttpResponse::Ok().body(format!("user:{}", auth.email)),
+        }
+    }
+
+    /// Start a TLS server and return its address
+    fn start_server(config: TlsConfig, pool: Option<PgPool>) -> SocketAddr {
+        let server_config = tls::load_server_config(&config).unwrap();
+        let services = config.client_services.clone();
+
+        let server = HttpServer::new(move || {
+            let mut app = App::new();
+            if let Some(pool) = &pool {
+                app = app.app_data(web::Data::new(pool.clone()));
+            }
+            app.app_data(web::Data::new(SessionConfig::default()))
+                .wrap(ClientCertAuth::new(services.clone()))
+                .route("/whoami", web::get().to(whoami))
+                .route("/whoami", web::post().to(whoami))
+        })
+        .on_connect(tls::on_connect)
+        .workers(1)
+        .bind_rustls_0_23(("127.0.0.1", 0), server_config)
+        .unwrap();
+
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+        addr
+    }
+
+    /// Send `GET /whoami` over TLS, returning the status and body
+    async fn get_whoami(
+        addr: SocketAddr,
+        ca: &Ca,
+        client: Option<(Certificate, KeyPair)>,
+    ) -> std::io::Result<(u16, String)> {
+        send(addr, ca, client, "GET /whoami HTTP/1.1\r\n").await
+    }
+
+    /// Send a request without a body over TLS, returning the status and body;
+    /// `head` is the request line and any headers other than `Host`
+    async fn send(
+        addr: SocketAddr,
+        ca: &Ca,
+        client: Option<(Certificate, KeyPair)>,
+        head: &str,
+    ) -> std::io::Result<(u16, String)> {
+        let mut roots = RootCertStore::empty();
+        roots.add(ca.cert.der().clone()).unwrap();
+        let builder = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots);
+        let client_config = match client {
+            Some((cert, key)) => builder
+                .with_client_auth_cert(
+                    vec![cert.der().clone()],
+                    PrivateKeyDer::Pkcs8(key.serialize_der().into()),
+                )
+                .unwrap(),
+            None => builder.with_no_client_auth(),
+        };
+
+        let stream = TcpStream::connect(addr).await?;
+        let mut stream = TlsConnector::from(Arc::new(client_config))
+            .connect(ServerName::try_from("localhost").unwrap(), stream)
+            .await?;
+        let request = format!(
+            "{}Host: localhost\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            head
+        );
+        stream.write_all(request.as_bytes()).await?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await?;
+
+        let status = response[9..12].parse().unwrap();
+        let body = response
+            .split_once("\r\n\r\n")
+            .map(|(_, body)| body.to_string())
+            .unwrap_or_default();
+        Ok((status, body))
+    }
+
+    #[actix_web::test]
+    async fn test_client_certificate_maps_to_service() {
+        let ca = Ca::new("Test CA");
+        let (_dir, config) = tls_config(&ca, false);
+        let addr = start_server(config, None);
+
+        let billing = ca.client(
+            "billing",
+            vec![SanType::DnsName("billing.internal".try_into().unwrap())],
+        );
+        let (status, body) = get_whoami(addr, &ca, Some(billing)).await.unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(body, "service:billing.internal:admin");
+
+        // Services are not browsers and need no CSRF token
+        let billing = ca.client(
+            "billing",
+            vec![SanType::DnsName("billing.internal".try_into().unwrap())],
+        );
+        let (status, _) = send(addr, &ca, Some(billing), "POST /whoami HTTP/1.1\r\n")
+            .await
+            .unwrap();
+        assert_eq!(status, 200);
+
+        // Certificates without a mapping, or no certificate at all, are not authenticated
+        let stranger = ca.client("stranger", vec![]);
+        assert_eq!(get_whoami(addr, &ca, Some(stranger)).await.unwrap().0, 401);
+        assert_eq!(get_whoami(addr, &ca, None).await.unwrap().0, 401);
+
+        // Certificates issued by another CA fail the handshake
+        let other_ca = Ca::new("Other CA");
+        let forged = other_ca.client(
+            "billing",
+            vec![SanType::DnsName("billing.internal".try_into().unwrap())],
+        );
+        assert!(get_whoami(addr, &ca, Some(forged)).await.is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_client_certificate_required() {
+        let ca = Ca::new("Test CA");
+        let (_dir, config) = tls_config(&ca, true);
+        let addr = start_server(config, None);
+
+        assert!(get_whoami(addr, &ca, None).await.is_err());
+
+        let common_name = ca.client("billing.internal", vec![]);
+        let (status, body) = get_whoami(addr, &ca, Some(common_name)).await.unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(body, "service:billing.internal:admin");
+    }
+
+    #[actix_web::test]
+    async fn test_client_certificate_maps_to_user() {
+        let config = Config::from_env();
+        let pool = PgPool::connect(&config.database_url)
+            .await
+            .expect("Failed to connect to database");
+
+        let email = format!("mtls_{}@example.com", Uuid::new_v4());
+        AuthService::new(&config.jwt_secret)
+            .register_user(
+                &pool,
+                CreateUserRequest {
+                    name: "Certificate User".to_string(),
+                    email: email.clone(),
+                    password: "password123".to_string(),
+                    role: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let ca = Ca::new("Test CA");
+        let (_dir, config) = tls_config(&ca, false);
+        let addr = start_server(config, Some(pool));
+
+        let user = || {
+            ca.client(
+                "Certificate User",
+                vec![SanType::Rfc822Name(email.clone().try_into().unwrap())],
+            )
+        };
+        let (status, body) = get_whoami(addr, &ca, Some(user())).await.unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(body, format!("user:{}", email));
+
+        // State-changing requests of users need the double-submit CSRF token
+        let post = "POST /whoami HTTP/1.1\r\n";
+        let (status, _) = send(addr, &ca, Some(user()), post).await.unwrap();
+        assert_eq!(status, 403);
+        let with_csrf = format!("{}Cookie: csrf_token=abc\r\nX-CSRF-Token: abc\r\n", post);
+        let (status, body) = send(addr, &ca, Some(user()), &with_csrf).await.unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(body, format!("user:{}", email));
+    }
+}
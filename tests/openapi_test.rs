@@ -0,0 +1,125 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::Method, test, web, App, HttpResponse};
+    use afaf_rest_rust::core::rest::{
+        openapi::{endpoints, openapi},
+        router,
+        version::ApiVersion,
+    };
+    use serde_json::Value;
+
+    /// A documented path with its parameters filled in
+    fn concrete(path: &str) -> String {
+        path.split('/')
+            .map(|segment| match segment.starts_with('{') {
+                true => "00000000-0000-0000-0000-000000000000",
+                false => segment,
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Every documented operation is routed: the default service, answering
+    /// 418 here, never sees it. Registering a handler without documentation
+    /// does not compile.
+    #[actix_web::test]
+    async fn test_documented_routes_are_served() {
+        let app = test::init_service(
+            App::new()
+                .configure(router::config)
+                .default_service(web::to(HttpResponse::ImATeapot)),
+        )
+        .await;
+
+        for version in ApiVersion::ALL {
+            let doc = openapi(version);
+            for (path, item) in &doc.paths.paths {
+                for method in item.operations.keys() {
+                    let method = serde_json::to_value(method).unwrap();
+                    let method = method.as_str().unwrap().to_uppercase();
+                    let req = test::TestRequest::default()
+                        .method(Method::from_bytes(method.as_bytes()).unwrap())
+                        .uri(&concrete(path))
+                        .to_request();
+                    let resp = test::call_service(&app, req).await;
+                    assert_ne!(
+                        resp.status(),
+                        418,
+                        "{} {} in the {} document is not routed",
+                        method,
+                        path,
+                        version
+                    );
+                }
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_response_schemas() {
+        let doc = serde_json::to_value(openapi(ApiVersion::LATEST)).unwrap();
+        let schema = |path: &str, method: &str, status: &str| {
+            doc["paths"][path][method]["responses"][status]["content"]["application/json"]["schema"]
+                ["$ref"]
+                .clone()
+        };
+
+        assert_eq!(
+            schema("/api/v2/auth/me", "get", "200"),
+            "#/components/schemas/UserResponse"
+        );
+        assert_eq!(
+            schema("/api/v2/users", "get", "200"),
+            "#/components/schemas/UserPageResponse"
+        );
+        assert_eq!(
+            schema("/api/v2/auth/login", "post", "401"),
+            "#/components/schemas/ErrorResponse"
+        );
+        let envelope = &doc["components"]["schemas"]["UserResponse"]["properties"];
+        assert_eq!(envelope["data"]["$ref"], "#/components/schemas/PublicUser");
+        assert!(
+            doc["components"]["schemas"]["PublicUser"]["properties"]["password_hash"].is_null()
+        );
+        assert_eq!(
+            doc["components"]["schemas"]["UserPage"]["properties"]["items"]["items"]["$ref"],
+            "#/components/schemas/PublicUser"
+        );
+
+        // v1 keeps answering user creation with the stored user
+        let v1 = serde_json::to_value(openapi(ApiVersion::V1)).unwrap();
+        assert_eq!(
+            v1["paths"]["/api/v1/users"]["post"]["responses"]["201"]["content"]["application/json"]
+                ["schema"]["$ref"],
+            "#/components/schemas/StoredUserResponse"
+        );
+
+        // Every referenced schema exists
+        let text = doc.to_string();
+        for name in text.split("#/components/schemas/").skip(1) {
+            let name = name.split('"').next().unwrap();
+            assert_ne!(
+                doc["components"]["schemas"][name],
+                Value::Null,
+                "{} is referenced but not a component",
+                name
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_endpoints() {
+        let endpoints = endpoints(&openapi(ApiVersion::V1));
+        assert!(endpoints.contains(&(
+            "GET".to_string(),
+            "/".to_string(),
+            "Check that the service is up".to_string()
+        )));
+        assert!(endpoints
+            .iter()
+            .any(|(method, path, _)| method == "POST" && path == "/api/v1/auth/change-password"));
+        assert!(!endpoints
+            .iter()
+            .any(|(_, path, _)| path == "/auth/password"));
+    }
+}
@@ -0,0 +1,104 @@
+#[macro_use]
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::header_value;
+    use actix_web::{http::header, test, web, HttpResponse};
+    use afaf_rest_rust::core::{
+        domain::error::AppError,
+        rest::middleware::{
+            content_negotiation::ContentNegotiation,
+            request_id::{self, RequestId},
+        },
+    };
+    use serde_json::Value;
+
+    async fn ok() -> HttpResponse {
+        let id = request_id::current().map(|request| request.id);
+        HttpResponse::Ok().json(id)
+    }
+
+    async fn missing() -> Result<HttpResponse, AppError> {
+        Err(AppError::NotFound {
+            resource: "Item".to_string(),
+        })
+    }
+
+    fn routes(cfg: &mut web::ServiceConfig) {
+        cfg.service(
+            web::scope("")
+                .wrap(ContentNegotiation)
+                .wrap(RequestId)
+                .route("/ok", web::get().to(ok))
+                .route("/missing", web::get().to(missing)),
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_request_ids() {
+        let app = app!(routes);
+
+        // Generated when absent and visible to handlers
+        let req = test::TestRequest::get().uri("/ok").to_request();
+        let resp = test::call_service(&app, req).await;
+        let id = header_value(&resp, "x-request-id");
+        assert_eq!(id.len(), 36);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body, Value::String(id));
+
+        // Echoed when supplied
+        let req = test::TestRequest::get()
+            .uri("/ok")
+            .insert_header(("X-Request-Id", "trace-42"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(header_value(&resp, "x-request-id"), "trace-42");
+
+        // Replaced when not a reasonable token
+        let req = test::TestRequest::get()
+            .uri("/ok")
+            .insert_header(("X-Request-Id", "not an id"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_ne!(header_value(&resp, "x-request-id"), "not an id");
+    }
+
+    #[actix_web::test]
+    async fn test_error_bodies() {
+        let app = app!(routes);
+
+        let req = test::TestRequest::get()
+            .uri("/missing")
+            .insert_header(("X-Request-Id", "trace-43"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+        assert_eq!(header_value(&resp, "x-request-id"), "trace-43");
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"], "not_found");
+        assert_eq!(body["message"], "Resource not found");
+        assert_eq!(body["request_id"], "trace-43");
+        assert_eq!(body["meta"]["app"], "afaf-rest-rust");
+
+        let req = test::TestRequest::get()
+            .uri("/missing")
+            .insert_header(("X-Request-Id", "trace-44"))
+            .insert_header((header::ACCEPT, "application/problem+json"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/problem+json"
+        );
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["type"], "urn:afaf-rest-rust:error:not_found");
+        assert_eq!(body["title"], "Not Found");
+        assert_eq!(body["status"], 404);
+        assert_eq!(body["detail"], "Resource not found");
+        assert_eq!(body["instance"], "/missing");
+        assert_eq!(body["code"], "not_found");
+        assert_eq!(body["request_id"], "trace-44");
+    }
+}
@@ -0,0 +1,203 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        dev::{Service, ServiceResponse},
+        test, web, App, HttpMessage, HttpResponse,
+    };
+    use afaf_rest_rust::{
+        config::{BodyLimitConfig, SigningConfig, SigningKey},
+        core::{
+            domain::{auth::jwt::JwtService, users::model::UserRole},
+            rest::handler::auth,
+            rest::middleware::{
+                auth::{AuthData, Principal},
+                request_signing::{
+                    sign, string_to_sign, NonceCache, RequestSigning, NONCE_HEADER,
+                    TIMESTAMP_HEADER,
+                },
+            },
+        },
+    };
+    use chrono::Utc;
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    const SECRET: &str = "billing-secret";
+
+    fn signing_config() -> SigningConfig {
+        SigningConfig {
+            keys: HashMap::from([(
+                "billing-1".to_string(),
+                SigningKey {
+                    secret: SECRET.to_string(),
+                    service: "billing".to_string(),
+                    role: UserRole::Admin,
+                },
+            )]),
+            max_skew_seconds: 300,
+            required_headers: vec!["content-type".to_string()],
+        }
+    }
+
+    /// Status code of a response, including errors returned by the middleware
+    async fn status<S, R>(app: &S, req: R) -> u16
+    where
+        S: Service<R, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        match test::try_call_service(app, req).await {
+            Ok(resp) => resp.status().as_u16(),
+            Err(err) => err.error_response().status().as_u16(),
+        }
+    }
+
+    async fn whoami(auth: AuthData, body: web::Bytes) -> HttpResponse {
+        match auth.principal {
+            Principal::Service { name, .. } => {
+                HttpResponse::Ok().body(format!("{}:{}:{}", name, auth.role, body.len()))
+            }
+            Principal::User => HttpResponse::Ok().body("user"),
+        }
+    }
+
+    fn signed_request(timestamp: i64, nonce: &str, body: &str) -> test::TestRequest {
+        let timestamp = timestamp.to_string();
+        let signature = sign(
+            SECRET,
+            &string_to_sign(
+                "POST",
+                "/whoami?x=1",
+                &timestamp,
+                nonce,
+                &[("content-type".to_string(), "application/json".to_string())],
+                body.as_bytes(),
+            ),
+        );
+        test::TestRequest::post()
+            .uri("/whoami?x=1")
+            .insert_header(("Content-Type", "application/json"))
+            .insert_header((TIMESTAMP_HEADER, timestamp))
+            .insert_header((NONCE_HEADER, nonce.to_string()))
+            .insert_header((
+                "Authorization",
+                format!(
+                    r#"HMAC-SHA256 keyId="billing-1",headers="content-type",signature="{}""#,
+                    signature
+                ),
+            ))
+    }
+
+    #[actix_web::test]
+    async fn test_signed_request_authenticates_service() {
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestSigning::new(signing_config(), NonceCache::new()))
+                .route("/whoami", web::post().to(whoami)),
+        )
+        .await;
+
+        let nonce = Uuid::new_v4().to_string();
+        let body = r#"{"amount":42}"#;
+        let req = signed_request(Utc::now().timestamp(), &nonce, body)
+            .set_payload(body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        assert_eq!(test::read_body(resp).await, "billing:admin:13");
+
+        // The same nonce cannot be replayed
+        let req = signed_request(Utc::now().timestamp(), &nonce, body)
+            .set_payload(body)
+            .to_request();
+        assert_eq!(status(&app, req).await, 401);
+    }
+
+    #[actix_web::test]
+    async fn test_rejects_stale_or_tampered_signatures() {
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestSigning::new(signing_config(), NonceCache::new()))
+                .route("/whoami", web::post().to(whoami)),
+        )
+        .await;
+
+        let body = r#"{"amount":42}"#;
+
+        let stale = signed_request(
+            Utc::now().timestamp() - 600,
+            &Uuid::new_v4().to_string(),
+            body,
+        )
+        .set_payload(body)
+        .to_request();
+        assert_eq!(status(&app, stale).await, 401);
+
+        let tampered = signed_request(Utc::now().timestamp(), &Uuid::new_v4().to_string(), body)
+            .set_payload(r#"{"amount":4200}"#)
+            .to_request();
+        assert_eq!(status(&app, tampered).await, 401);
+
+        // Unsigned requests are passed through to the other authentication methods
+        let unsigned = test::TestRequest::post().uri("/whoami").to_request();
+        assert_eq!(status(&app, unsigned).await, 401);
+    }
+
+    #[actix_web::test]
+    async fn test_body_is_read_under_the_configured_limit() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(BodyLimitConfig {
+                    json_bytes: 8,
+                    upload_bytes: 16,
+                }))
+                .wrap(RequestSigning::new(signing_config(), NonceCache::new()))
+                .route("/whoami", web::post().to(whoami)),
+        )
+        .await;
+
+        let body = r#"{"amount":42}"#;
+        let req = signed_request(Utc::now().timestamp(), &Uuid::new_v4().to_string(), body)
+            .set_payload(body)
+            .to_request();
+        assert_eq!(status(&app, req).await, 200);
+
+        let body = r#"{"amount":42000000}"#;
+        let req = signed_request(Utc::now().timestamp(), &Uuid::new_v4().to_string(), body)
+            .set_payload(body)
+            .to_request();
+        assert_eq!(status(&app, req).await, 413);
+
+        // An unknown key is refused before the body is read
+        let req = signed_request(Utc::now().timestamp(), &Uuid::new_v4().to_string(), body)
+            .insert_header((
+                "Authorization",
+                r#"HMAC-SHA256 keyId="unknown",headers="content-type",signature="x""#,
+            ))
+            .set_payload(body)
+            .to_request();
+        assert_eq!(status(&app, req).await, 401);
+    }
+
+    #[actix_web::test]
+    async fn test_services_are_refused_on_user_endpoints() {
+        // Never connected: the request is refused before any query
+        let pool = sqlx::PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool))
+                .app_data(web::Data::new(JwtService::new(SECRET)))
+                .wrap_fn(|req, srv| {
+                    req.extensions_mut().insert(AuthData::service(
+                        "billing",
+                        "billing-1",
+                        UserRole::Admin,
+                    ));
+                    srv.call(req)
+                })
+                .service(auth::me),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/auth/me").to_request();
+        assert_eq!(status(&app, req).await, 403);
+    }
+}
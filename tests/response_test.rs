@@ -1,7 +1,11 @@
 #[cfg(test)]
 mod tests {
-    use afaf_rest_rust::core::rest::handler::response::{
-        build_error_response, build_success_response,
+    use afaf_rest_rust::core::{
+        domain::error::{AppError, ErrorCode},
+        rest::handler::response::{
+            build_success_response, decode_cursor, encode_cursor, ErrorResponse, Page, PageQuery,
+            MAX_PAGE_SIZE,
+        },
     };
     use serde::Serialize;
 
@@ -12,13 +16,17 @@ mod tests {
     }
 
     #[test]
-    fn test_build_error_response() {
-        let error_response = build_error_response("not_found", "Resource not found");
+    fn test_error_response() {
+        let (status, error_response) = ErrorResponse::from_error(&AppError::NotFound {
+            resource: "User".to_string(),
+        });
 
-        assert_eq!(error_response.error, "not_found");
+        assert_eq!(status, 404);
+        assert_eq!(error_response.error, ErrorCode::NotFound);
         assert_eq!(error_response.message, "Resource not found");
         assert_eq!(error_response.meta.app, "afaf-rest-rust");
         assert_eq!(error_response.meta.version, "1.0.0");
+        assert!(!error_response.request_id.is_empty());
     }
 
     #[test]
@@ -34,4 +42,51 @@ mod tests {
         assert_eq!(success_response.meta.app, "afaf-rest-rust");
         assert_eq!(success_response.meta.version, "1.0.0");
     }
+
+    #[test]
+    fn test_page_query_limits() {
+        let query = PageQuery {
+            limit: Some(10_000),
+            offset: Some(-5),
+            cursor: None,
+        };
+        assert_eq!(query.limit(), MAX_PAGE_SIZE);
+        assert_eq!(query.offset(), 0);
+        assert_eq!(PageQuery::default().limit(), 20);
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = encode_cursor(&("2026-01-01T00:00:00Z", 42));
+        assert!(!cursor.contains(['+', '/', '=']));
+        let decoded: (String, i32) = decode_cursor(&cursor).unwrap();
+        assert_eq!(decoded, ("2026-01-01T00:00:00Z".to_string(), 42));
+        assert!(decode_cursor::<(String, i32)>("not a cursor").is_err());
+    }
+
+    #[test]
+    fn test_page_link_header() {
+        let page = Page {
+            items: vec![1, 2],
+            total: 5,
+            limit: 2,
+            offset: Some(2),
+            next_cursor: Some("abc".to_string()),
+        };
+        assert_eq!(
+            page.link_header("/users", "sort=name&offset=2&limit=2"),
+            "</users?sort=name&limit=2>; rel=\"first\", \
+             </users?sort=name&limit=2&offset=0>; rel=\"prev\", \
+             </users?sort=name&limit=2&offset=4>; rel=\"next\""
+        );
+
+        let page = Page {
+            offset: None,
+            ..page
+        };
+        assert_eq!(
+            page.link_header("/users", "cursor=xyz"),
+            "</users?limit=2>; rel=\"first\", </users?limit=2&cursor=abc>; rel=\"next\""
+        );
+    }
 }
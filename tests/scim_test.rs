@@ -0,0 +1,304 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::setup;
+    use actix_web::{http::header, test, web, App};
+    use afaf_rest_rust::{config::ScimConfig, core::rest::router};
+    use serde_json::{json, Value};
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    const TOKEN: &str = "scim-test-token";
+
+    fn scim_config() -> ScimConfig {
+        ScimConfig {
+            bearer_token: Some(TOKEN.to_string()),
+        }
+    }
+
+    fn auth() -> (header::HeaderName, String) {
+        (header::AUTHORIZATION, format!("Bearer {}", TOKEN))
+    }
+
+    #[actix_web::test]
+    async fn test_scim_user_lifecycle() {
+        let pool = setup().await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool))
+                .app_data(web::Data::new(scim_config()))
+                .configure(router::config),
+        )
+        .await;
+
+        let email = format!("scim_{}@example.com", Uuid::new_v4());
+
+        // Create
+        let req = test::TestRequest::post()
+            .uri("/scim/v2/Users")
+            .insert_header(auth())
+            .insert_header((header::CONTENT_TYPE, "application/scim+json"))
+            .set_payload(
+                json!({
+                    "schemas": ["urn:ietf:params:scim:schemas:core:2.0:User"],
+                    "userName": email,
+                    "name": { "givenName": "Scim", "familyName": "User" }
+                })
+                .to_string(),
+            )
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        assert!(resp.headers().contains_key(header::LOCATION));
+        let created: Value = test::read_body_json(resp).await;
+        assert_eq!(created["displayName"], "Scim User");
+        assert_eq!(created["groups"][0]["value"], "user");
+        let id = created["id"].as_str().unwrap().to_string();
+
+        // Duplicate userName, compared in lower case
+        let req = test::TestRequest::post()
+            .uri("/scim/v2/Users")
+            .insert_header(auth())
+            .set_json(json!({ "userName": email.to_uppercase() }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 409);
+        let error: Value = test::read_body_json(resp).await;
+        assert_eq!(error["scimType"], "uniqueness");
+
+        // Filter
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/scim/v2/Users?filter=userName%20eq%20%22{}%22",
+                email
+            ))
+            .insert_header(auth())
+            .to_request();
+        let list: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(list["totalResults"], 1);
+        assert_eq!(list["Resources"][0]["id"], id);
+
+        // Patch, with a CBOR body
+        let mut cbor = Vec::new();
+        ciborium::into_writer(
+            &json!({
+                "schemas": ["urn:ietf:params:scim:api:messages:2.0:PatchOp"],
+                "Operations": [{ "op": "Replace", "path": "displayName", "value": "Renamed" }]
+            }),
+            &mut cbor,
+        )
+        .unwrap();
+        let req = test::TestRequest::patch()
+            .uri(&format!("/scim/v2/Users/{}", id))
+            .insert_header(auth())
+            .insert_header((header::CONTENT_TYPE, "application/cbor"))
+            .set_payload(cbor)
+            .to_request();
+        let patched: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(patched["displayName"], "Renamed");
+
+        // Group membership maps onto the user's role
+        let req = test::TestRequest::patch()
+            .uri("/scim/v2/Groups/moderator")
+            .insert_header(auth())
+            .set_json(json!({
+                "Operations": [{ "op": "add", "path": "members", "value": [{ "value": id }] }]
+            }))
+            .to_request();
+        let group: Value = test::call_and_read_body_json(&app, req).await;
+        assert!(group["members"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|m| m["value"] == id.as_str()));
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/scim/v2/Users/{}", id))
+            .insert_header(auth())
+            .to_request();
+        let user: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(user["groups"][0]["value"], "moderator");
+
+        // Delete
+        let req = test::TestRequest::delete()
+            .uri(&format!("/scim/v2/Users/{}", id))
+            .insert_header(auth())
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 204);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/scim/v2/Users/{}", id))
+            .insert_header(auth())
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+        // Deleting again finds no user
+        let req = test::TestRequest::delete()
+            .uri(&format!("/scim/v2/Users/{}", id))
+            .insert_header(auth())
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn test_scim_group_changes_are_atomic() {
+        let pool = setup().await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool))
+                .app_data(web::Data::new(scim_config()))
+                .configure(router::config),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/scim/v2/Users")
+            .insert_header(auth())
+            .set_json(json!({ "userName": format!("scim_{}@example.com", Uuid::new_v4()) }))
+            .to_request();
+        let created: Value = test::call_and_read_body_json(&app, req).await;
+        let id = created["id"].as_str().unwrap().to_string();
+
+        let req = test::TestRequest::patch()
+            .uri("/scim/v2/Groups/moderator")
+            .insert_header(auth())
+            .set_json(json!({
+                "Operations": [{ "op": "add", "path": "members", "value": [{ "value": id }] }]
+            }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+        // Replacing the members with an unknown user removes no one
+        let unknown = Uuid::new_v4().to_string();
+        let req = test::TestRequest::put()
+            .uri("/scim/v2/Groups/moderator")
+            .insert_header(auth())
+            .set_json(json!({
+                "displayName": "moderator",
+                "members": [{ "value": unknown }]
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        // Nor does a patch whose later operation fails
+        let req = test::TestRequest::patch()
+            .uri("/scim/v2/Groups/moderator")
+            .insert_header(auth())
+            .set_json(json!({
+                "Operations": [
+                    { "op": "remove", "path": format!("members[value eq \"{}\"]", id) },
+                    { "op": "add", "path": "members", "value": [{ "value": unknown }] }
+                ]
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/scim/v2/Users/{}", id))
+            .insert_header(auth())
+            .to_request();
+        let user: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(user["groups"][0]["value"], "moderator");
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/scim/v2/Users/{}", id))
+            .insert_header(auth())
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 204);
+    }
+
+    #[actix_web::test]
+    async fn test_scim_requires_bearer_token() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(scim_config()))
+                .configure(router::config),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/scim/v2/ServiceProviderConfig")
+            .insert_header((header::AUTHORIZATION, "Bearer wrong"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/scim+json"
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/scim/v2/ServiceProviderConfig")
+            .insert_header(auth())
+            .to_request();
+        let config: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(config["patch"]["supported"], true);
+    }
+
+    #[actix_web::test]
+    async fn test_scim_extractor_errors() {
+        // Requests are rejected before the database is used
+        let pool = PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool))
+                .app_data(web::Data::new(scim_config()))
+                .configure(router::config),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/scim/v2/Users")
+            .insert_header(auth())
+            .insert_header((header::CONTENT_TYPE, "application/scim+json"))
+            .set_payload("{\"userName\": ")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/scim+json"
+        );
+        let error: Value = test::read_body_json(resp).await;
+        assert_eq!(
+            error["schemas"][0],
+            "urn:ietf:params:scim:api:messages:2.0:Error"
+        );
+        assert_eq!(error["status"], "400");
+        assert_eq!(error["scimType"], "invalidSyntax");
+
+        let req = test::TestRequest::get()
+            .uri("/scim/v2/Users?startIndex=first")
+            .insert_header(auth())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+        let error: Value = test::read_body_json(resp).await;
+        assert_eq!(error["scimType"], "invalidValue");
+
+        let req = test::TestRequest::post()
+            .uri("/scim/v2/Users")
+            .insert_header(auth())
+            .insert_header((header::CONTENT_TYPE, "text/plain"))
+            .set_payload("userName")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 415);
+        let error: Value = test::read_body_json(resp).await;
+        assert_eq!(error["status"], "415");
+
+        // Passwords follow the same length rule as the user API
+        let req = test::TestRequest::post()
+            .uri("/scim/v2/Users")
+            .insert_header(auth())
+            .set_json(json!({ "userName": "short@example.com", "password": "a" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+        let error: Value = test::read_body_json(resp).await;
+        assert_eq!(error["scimType"], "invalidValue");
+    }
+}
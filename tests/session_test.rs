@@ -0,0 +1,128 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::setup;
+    use actix_web::{cookie::Cookie, test, web, App};
+    use afaf_rest_rust::{
+        config::SessionConfig,
+        core::{
+            domain::{
+                auth::{jwt::JwtService, revocation::TokenRevocationList, service::AuthService},
+                users::model::CreateUserRequest,
+            },
+            rest::router,
+        },
+    };
+    use serde_json::json;
+    use uuid::Uuid;
+
+    #[actix_web::test]
+    async fn test_cookie_session_with_csrf() {
+        let pool = setup().await;
+        let jwt_service = JwtService::new("session_test_secret");
+
+        let email = format!("session_{}@example.com", Uuid::new_v4());
+        AuthService::with_jwt_service(jwt_service.clone())
+            .register_user(
+                &pool,
+                CreateUserRequest {
+                    name: "Session User".to_string(),
+                    email: email.clone(),
+                    password: "password123".to_string(),
+                    role: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let session = SessionConfig {
+            enabled: true,
+            secure: false,
+            ..SessionConfig::default()
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool))
+                .app_data(web::Data::new(jwt_service))
+                .app_data(web::Data::new(TokenRevocationList::new()))
+                .app_data(web::Data::new(session))
+                .configure(router::config),
+        )
+        .await;
+
+        // Login sets the session and CSRF cookies
+        let req = test::TestRequest::post()
+            .uri("/auth/login")
+            .set_json(json!({ "email": email, "password": "password123" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let cookies: Vec<Cookie> = resp.response().cookies().map(|c| c.into_owned()).collect();
+        let session_cookie = cookies.iter().find(|c| c.name() == "session").unwrap();
+        let csrf_cookie = cookies.iter().find(|c| c.name() == "csrf_token").unwrap();
+        assert_eq!(session_cookie.http_only(), Some(true));
+        assert_ne!(csrf_cookie.http_only(), Some(true));
+
+        // Safe requests only need the session cookie
+        let req = test::TestRequest::get()
+            .uri("/auth/me")
+            .cookie(session_cookie.clone())
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+
+        // State-changing requests need the CSRF header as well
+        let req = test::TestRequest::post()
+            .uri("/auth/logout")
+            .cookie(session_cookie.clone())
+            .cookie(csrf_cookie.clone())
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+        let req = test::TestRequest::post()
+            .uri("/auth/logout")
+            .cookie(session_cookie.clone())
+            .cookie(csrf_cookie.clone())
+            .insert_header(("X-CSRF-Token", csrf_cookie.value()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        let cleared = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "session")
+            .unwrap();
+        assert!(cleared.value().is_empty());
+
+        // The logged out session is revoked
+        let req = test::TestRequest::get()
+            .uri("/auth/me")
+            .cookie(session_cookie.clone())
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+    }
+
+    #[actix_web::test]
+    async fn test_cookie_ignored_when_sessions_disabled() {
+        let jwt_service = JwtService::new("session_test_secret");
+        let token = jwt_service
+            .generate_token(Uuid::new_v4(), "nobody@example.com", "user")
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(jwt_service))
+                .app_data(web::Data::new(TokenRevocationList::new()))
+                .app_data(web::Data::new(SessionConfig::default()))
+                .configure(router::config),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/auth/logout")
+            .cookie(Cookie::new("session", token))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+    }
+}
@@ -1,16 +1,11 @@
+mod common;
+
 #[cfg(test)]
 mod tests {
-    use afaf_rest_rust::{config::Config, core::domain::users::repository::UserRepository};
-    use sqlx::PgPool;
+    use crate::common::setup;
+    use afaf_rest_rust::core::domain::users::repository::UserRepository;
     use uuid::Uuid;
 
-    async fn setup() -> PgPool {
-        let config = Config::from_env();
-        PgPool::connect(&config.database_url)
-            .await
-            .expect("Failed to connect to database")
-    }
-
     #[tokio::test]
     async fn test_create_user() {
         let pool = setup().await;
@@ -0,0 +1,1207 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::setup;
+    use actix_web::{test, web, App};
+    use afaf_rest_rust::{
+        config::ConcurrencyConfig,
+        core::{
+            domain::{
+                auth::{jwt::JwtService, revocation::TokenRevocationList},
+                users::{
+                    model::{User, UserRole},
+                    purge::purge_deleted_users,
+                    repository::UserRepository,
+                },
+            },
+            rest::router,
+        },
+    };
+    use serde_json::{json, Value};
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    const SECRET: &str = "users_handler_test_secret";
+
+    async fn create_user(pool: &PgPool, role: UserRole) -> (User, String) {
+        let repo = UserRepository { pool };
+        let user = repo
+            .create_user(
+                "Handler User",
+                &format!("handler_{}@example.com", Uuid::new_v4()),
+            )
+            .await
+            .unwrap();
+        let user = repo.update_user_role(user.id, &role).await.unwrap();
+        let token = JwtService::new(SECRET)
+            .generate_token(user.id, &user.email, &role.to_string())
+            .unwrap();
+        (user, token)
+    }
+
+    macro_rules! app {
+        ($pool:expr) => {
+            test::init_service(
+                App::new()
+                    .app_data(web::Data::new($pool.clone()))
+                    .app_data(web::Data::new(JwtService::new(SECRET)))
+                    .app_data(web::Data::new(TokenRevocationList::new()))
+                    .configure(router::config),
+            )
+            .await
+        };
+    }
+
+    fn bearer(token: &str) -> (&'static str, String) {
+        ("Authorization", format!("Bearer {}", token))
+    }
+
+    #[actix_web::test]
+    async fn test_tokens_of_deleted_users_are_rejected() {
+        let pool = setup().await;
+        let (admin, token) = create_user(&pool, UserRole::Admin).await;
+        let app = app!(pool);
+
+        let req = test::TestRequest::get()
+            .uri("/users?include_deleted=true")
+            .insert_header(bearer(&token))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+        UserRepository { pool: &pool }
+            .delete_user(admin.id)
+            .await
+            .unwrap();
+
+        // No longer an admin, nor anyone else
+        let req = test::TestRequest::get()
+            .uri("/users?include_deleted=true")
+            .insert_header(bearer(&token))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+        let req = test::TestRequest::get()
+            .uri("/auth/me")
+            .insert_header(bearer(&token))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+    }
+
+    #[actix_web::test]
+    async fn test_users_without_password_cannot_sign_in() {
+        let pool = setup().await;
+        let (user, token) = create_user(&pool, UserRole::User).await;
+        assert!(!user.has_password());
+        let app = app!(pool);
+
+        let req = test::TestRequest::post()
+            .uri("/auth/login")
+            .set_json(json!({ "email": user.email, "password": "anything" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+
+        let req = test::TestRequest::post()
+            .uri("/auth/change-password")
+            .insert_header(bearer(&token))
+            .set_json(json!({ "current_password": "anything", "new_password": "password123" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+    }
+
+    #[actix_web::test]
+    async fn test_emails_ignore_case() {
+        let pool = setup().await;
+        let tag = Uuid::new_v4().simple().to_string();
+        let hash = bcrypt::hash("Password123", 4).unwrap();
+        UserRepository { pool: &pool }
+            .create_user_with_password(
+                "Mixed Case",
+                &format!("Mixed.{}@Example.com", tag),
+                &hash,
+                &UserRole::User,
+            )
+            .await
+            .unwrap();
+        let app = app!(pool);
+
+        // Accounts stored with capitals still sign in with the lowercased email
+        let req = test::TestRequest::post()
+            .uri("/auth/login")
+            .set_json(
+                json!({ "email": format!("MIXED.{}@example.COM", tag), "password": "Password123" }),
+            )
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+        let req = test::TestRequest::post()
+            .uri("/users")
+            .set_json(
+                json!({ "name": "Other Case", "email": format!("mixed.{}@example.com", tag) }),
+            )
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 409);
+
+        // Bulk creates and imports normalize emails before checking them
+        let (_, admin_token) = create_user(&pool, UserRole::Admin).await;
+        let req = test::TestRequest::post()
+            .uri("/users/bulk")
+            .insert_header(bearer(&admin_token))
+            .set_json(json!({ "operations": [{
+                "op": "create",
+                "name": "Bulk Case",
+                "email": format!(" MIXED.{}@Example.com", tag),
+                "password": "password123"
+            }] }))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["data"]["results"][0]["status"], 409);
+
+        let req = test::TestRequest::post()
+            .uri("/users/import?dry_run=true")
+            .insert_header(bearer(&admin_token))
+            .insert_header(("Content-Type", "text/csv"))
+            .set_payload(format!(
+                "name,email,password\nImport Case,Mixed.{}@EXAMPLE.com,password123\n",
+                tag
+            ))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["data"]["invalid"], 1);
+        assert_eq!(
+            body["data"]["errors"][0]["errors"][0],
+            "email: Email already exists"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_get_and_update_own_profile() {
+        let pool = setup().await;
+        let (user, token) = create_user(&pool, UserRole::User).await;
+        let (other, _) = create_user(&pool, UserRole::User).await;
+        let app = app!(pool);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/users/{}", user.id))
+            .insert_header(bearer(&token))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["data"]["email"], user.email);
+        assert!(body["data"].get("password_hash").is_none());
+
+        // Users cannot see or change other users
+        let req = test::TestRequest::get()
+            .uri(&format!("/users/{}", other.id))
+            .insert_header(bearer(&token))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+        let req = test::TestRequest::patch()
+            .uri(&format!("/users/{}", other.id))
+            .insert_header(bearer(&token))
+            .set_json(json!({ "name": "Hijacked" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+        let req = test::TestRequest::patch()
+            .uri(&format!("/users/{}", user.id))
+            .insert_header(bearer(&token))
+            .set_json(json!({ "name": "Renamed" }))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["data"]["name"], "Renamed");
+        assert_eq!(body["data"]["email"], user.email);
+
+        // Taking another user's email is a conflict
+        let req = test::TestRequest::patch()
+            .uri(&format!("/users/{}", user.id))
+            .insert_header(bearer(&token))
+            .set_json(json!({ "email": other.email }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 409);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["message"], "Email already exists");
+
+        let req = test::TestRequest::patch()
+            .uri(&format!("/users/{}", user.id))
+            .insert_header(bearer(&token))
+            .set_json(json!({ "email": "not-an-email" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["errors"][0]["field"], "email");
+        assert_eq!(body["errors"][0]["code"], "email");
+        assert_eq!(body["errors"][0]["message"], "Invalid email format");
+
+        // Users cannot promote themselves
+        let req = test::TestRequest::put()
+            .uri(&format!("/users/{}/role", user.id))
+            .insert_header(bearer(&token))
+            .set_json(json!({ "role": "admin" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+    }
+
+    #[actix_web::test]
+    async fn test_create_user_validation() {
+        let pool = setup().await;
+        let app = app!(pool);
+
+        // Input is normalized before it is validated
+        let tag = Uuid::new_v4().simple().to_string();
+        let req = test::TestRequest::post()
+            .uri("/users")
+            .set_json(
+                json!({ "name": "  Ada Lovelace ", "email": format!(" Ada.{}@Example.COM ", tag) }),
+            )
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["data"]["name"], "Ada Lovelace");
+        assert_eq!(body["data"]["email"], format!("ada.{}@example.com", tag));
+
+        let req = test::TestRequest::post()
+            .uri("/users")
+            .set_json(json!({}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["errors"][0]["field"], "email");
+        assert_eq!(body["errors"][0]["code"], "required");
+        assert_eq!(body["errors"][1]["field"], "name");
+        assert_eq!(body["errors"][1]["message"], "Name is required.");
+
+        let req = test::TestRequest::post()
+            .uri("/users")
+            .set_json(json!({ "name": "   ", "email": "not-an-email" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["errors"][0]["message"], "Invalid email format.");
+        assert_eq!(body["errors"][1]["message"], "Name cannot be empty.");
+    }
+
+    #[actix_web::test]
+    async fn test_admin_manages_users() {
+        let pool = setup().await;
+        let (admin, admin_token) = create_user(&pool, UserRole::Admin).await;
+        let (user, token) = create_user(&pool, UserRole::User).await;
+        let app = app!(pool);
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/users/{}/role", user.id))
+            .insert_header(bearer(&admin_token))
+            .set_json(json!({ "role": " Moderator " }))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["data"]["role"], "moderator");
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/users/{}/role", user.id))
+            .insert_header(bearer(&admin_token))
+            .set_json(json!({ "role": "superuser" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["errors"][0]["field"], "role");
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/users/{}/role", admin.id))
+            .insert_header(bearer(&admin_token))
+            .set_json(json!({ "role": "user" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+        // Users may delete themselves, admins anyone
+        let req = test::TestRequest::delete()
+            .uri(&format!("/users/{}", user.id))
+            .insert_header(bearer(&token))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 204);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/users/{}", user.id))
+            .insert_header(bearer(&admin_token))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/users/{}", Uuid::new_v4()))
+            .insert_header(bearer(&admin_token))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/users/{}", admin.id))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+    }
+
+    #[actix_web::test]
+    async fn test_list_users_paginates() {
+        let pool = setup().await;
+        for _ in 0..3 {
+            create_user(&pool, UserRole::User).await;
+        }
+        let app = app!(pool);
+
+        let req = test::TestRequest::get()
+            .uri("/users?limit=2&offset=1")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let link = resp.headers().get("Link").unwrap().to_str().unwrap();
+        assert!(link.contains("</users?limit=2&offset=3>; rel=\"next\""));
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["data"]["items"].as_array().unwrap().len(), 2);
+        assert_eq!(body["data"]["offset"], 1);
+        assert!(body["data"]["total"].as_i64().unwrap() >= 3);
+
+        // Following cursors visits every user once
+        let mut uri = "/users?limit=2".to_string();
+        let mut seen = Vec::new();
+        loop {
+            let req = test::TestRequest::get().uri(&uri).to_request();
+            let body: Value = test::call_and_read_body_json(&app, req).await;
+            for user in body["data"]["items"].as_array().unwrap() {
+                seen.push(user["id"].as_str().unwrap().to_string());
+            }
+            match body["data"]["next_cursor"].as_str() {
+                Some(cursor) => uri = format!("/users?limit=2&cursor={}", cursor),
+                None => break,
+            }
+        }
+        let mut unique = seen.clone();
+        unique.sort();
+        unique.dedup();
+        assert!(seen.len() >= 3);
+        assert_eq!(unique.len(), seen.len());
+
+        let req = test::TestRequest::get()
+            .uri("/users?limit=1000")
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["data"]["limit"], 100);
+
+        let req = test::TestRequest::get()
+            .uri("/users?cursor=garbage")
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn test_list_users_filter_and_sort() {
+        let pool = setup().await;
+        let tag = Uuid::new_v4().simple().to_string();
+        let repo = UserRepository { pool: &pool };
+        for (suffix, role) in [
+            ("a", UserRole::Moderator),
+            ("b", UserRole::User),
+            ("c", UserRole::Moderator),
+        ] {
+            let (user, _) = create_user(&pool, role).await;
+            repo.update_user(user.id, Some(&format!("{} {}", tag, suffix)), None)
+                .await
+                .unwrap();
+        }
+        let app = app!(pool);
+
+        let filter = format!("name sw '{}' and role eq moderator", tag)
+            .replace(' ', "%20")
+            .replace('\'', "%27");
+        let names = |body: &Value| -> Vec<String> {
+            body["data"]["items"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|u| u["name"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/users?filter={}&sort=-name", filter))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["data"]["total"], 2);
+        assert_eq!(
+            names(&body),
+            vec![format!("{} c", tag), format!("{} a", tag)]
+        );
+
+        // Cursors follow the requested sort order
+        let req = test::TestRequest::get()
+            .uri(&format!("/users?filter={}&sort=-name&limit=1", filter))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(names(&body), vec![format!("{} c", tag)]);
+        let cursor = body["data"]["next_cursor"].as_str().unwrap().to_string();
+
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/users?filter={}&sort=-name&limit=1&cursor={}",
+                filter, cursor
+            ))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(names(&body), vec![format!("{} a", tag)]);
+        assert!(body["data"]["next_cursor"].is_null());
+
+        // A cursor is tied to the sort order it was issued for
+        let req = test::TestRequest::get()
+            .uri(&format!("/users?sort=-name,email&cursor={}", cursor))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+        for query in ["filter=password_hash%20eq%20x", "sort=password_hash"] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/users?{}", query))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), 400);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_search_users() {
+        let pool = setup().await;
+        let tag = Uuid::new_v4().simple().to_string();
+        let repo = UserRepository { pool: &pool };
+        let (zebulon, _) = create_user(&pool, UserRole::User).await;
+        repo.update_user(
+            zebulon.id,
+            Some(&format!("Zebulon {}", tag)),
+            Some(&format!("zeb.{}@example.com", tag)),
+        )
+        .await
+        .unwrap();
+        let (other, _) = create_user(&pool, UserRole::User).await;
+        repo.update_user(other.id, Some(&format!("Other {}", tag)), None)
+            .await
+            .unwrap();
+        let app = app!(pool);
+
+        // Word prefixes match, rank above fuzzy matches and are highlighted
+        let req = test::TestRequest::get()
+            .uri(&format!("/users/search?q=zebu%20{}", tag))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        let items = body["data"]["items"].as_array().unwrap();
+        assert!(items[0]["rank"].as_f64() > items.get(1).and_then(|u| u["rank"].as_f64()));
+        let hit = &items[0];
+        assert_eq!(hit["id"], zebulon.id.to_string());
+        assert_eq!(
+            hit["highlights"]["name"],
+            format!("<mark>Zebu</mark>lon <mark>{}</mark>", tag)
+        );
+        assert_eq!(
+            hit["highlights"]["email"],
+            format!("zeb.<mark>{}</mark>@example.com", tag)
+        );
+        assert!(hit.get("password_hash").is_none());
+
+        // Typos are tolerated
+        let req = test::TestRequest::get()
+            .uri("/users/search?q=Zebulom")
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert!(body["data"]["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|u| u["id"] == zebulon.id.to_string()));
+
+        // Results are paginated
+        let req = test::TestRequest::get()
+            .uri(&format!("/users/search?q={}&limit=1", tag))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.headers().get("Link").is_some());
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["data"]["total"], 2);
+        let first = body["data"]["items"][0]["id"].clone();
+        let cursor = body["data"]["next_cursor"].as_str().unwrap().to_string();
+
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/users/search?q={}&limit=1&cursor={}",
+                tag, cursor
+            ))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_ne!(body["data"]["items"][0]["id"], first);
+        assert!(body["data"]["next_cursor"].is_null());
+
+        for q in ["", "%25%26%7C"] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/users/search?q={}", q))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), 400);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_soft_delete_and_restore() {
+        let pool = setup().await;
+        let (_, admin_token) = create_user(&pool, UserRole::Admin).await;
+        let (user, token) = create_user(&pool, UserRole::User).await;
+        let (_, other_token) = create_user(&pool, UserRole::User).await;
+        let app = app!(pool);
+        let repo = UserRepository { pool: &pool };
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/users/{}", user.id))
+            .insert_header(bearer(&token))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 204);
+
+        // Deleted users are hidden but kept
+        assert!(repo.find_by_id(user.id).await.is_err());
+        let deleted = repo.find_by_id_including_deleted(user.id).await.unwrap();
+        assert!(deleted.deleted_at.is_some());
+
+        // Only admins may list deleted users
+        let filter = format!("email%20eq%20%27{}%27", user.email);
+        let req = test::TestRequest::get()
+            .uri(&format!("/users?filter={}", filter))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["data"]["total"], 0);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/users?filter={}&include_deleted=true", filter))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/users?filter={}&include_deleted=true", filter))
+            .insert_header(bearer(&admin_token))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["data"]["total"], 1);
+        assert!(body["data"]["items"][0]["deleted_at"].is_string());
+
+        // The deleted user's token no longer works, and restore is admin only
+        let req = test::TestRequest::post()
+            .uri(&format!("/users/{}/restore", user.id))
+            .insert_header(bearer(&token))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/users/{}/restore", user.id))
+            .insert_header(bearer(&other_token))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/users/{}/restore", user.id))
+            .insert_header(bearer(&admin_token))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["data"]["id"], user.id.to_string());
+        assert!(repo.find_by_id(user.id).await.is_ok());
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/users/{}/restore", user.id))
+            .insert_header(bearer(&admin_token))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 409);
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/users/{}/restore", Uuid::new_v4()))
+            .insert_header(bearer(&admin_token))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn test_deleted_email_can_be_reused() {
+        let pool = setup().await;
+        let (_, admin_token) = create_user(&pool, UserRole::Admin).await;
+        let (user, _) = create_user(&pool, UserRole::User).await;
+        let app = app!(pool);
+        let repo = UserRepository { pool: &pool };
+
+        repo.delete_user(user.id).await.unwrap();
+        assert!(!repo.email_exists(&user.email).await.unwrap());
+        let replacement = repo.create_user("Replacement", &user.email).await.unwrap();
+
+        // The old account cannot be restored while its email is taken
+        let req = test::TestRequest::post()
+            .uri(&format!("/users/{}/restore", user.id))
+            .insert_header(bearer(&admin_token))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 409);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["message"], "Email already exists");
+
+        repo.delete_user(replacement.id).await.unwrap();
+        let req = test::TestRequest::post()
+            .uri(&format!("/users/{}/restore", user.id))
+            .insert_header(bearer(&admin_token))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+    }
+
+    #[actix_web::test]
+    async fn test_purge_deleted_users() {
+        let pool = setup().await;
+        let (old, _) = create_user(&pool, UserRole::User).await;
+        let (recent, _) = create_user(&pool, UserRole::User).await;
+        let (active, _) = create_user(&pool, UserRole::User).await;
+        let repo = UserRepository { pool: &pool };
+
+        repo.delete_user(old.id).await.unwrap();
+        repo.delete_user(recent.id).await.unwrap();
+        sqlx::query("UPDATE users SET deleted_at = NOW() - INTERVAL '31 days' WHERE id = $1")
+            .bind(old.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(purge_deleted_users(&pool, 30).await.unwrap() >= 1);
+        assert!(repo.find_by_id_including_deleted(old.id).await.is_err());
+        assert!(repo.find_by_id_including_deleted(recent.id).await.is_ok());
+        assert!(repo.find_by_id(active.id).await.is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_if_match_updates() {
+        let pool = setup().await;
+        let (_, admin_token) = create_user(&pool, UserRole::Admin).await;
+        let (user, token) = create_user(&pool, UserRole::User).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(JwtService::new(SECRET)))
+                .app_data(web::Data::new(TokenRevocationList::new()))
+                .app_data(web::Data::new(ConcurrencyConfig {
+                    if_match_required: true,
+                }))
+                .configure(router::config),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/users/{}", user.id))
+            .insert_header(bearer(&token))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let etag = resp
+            .headers()
+            .get("ETag")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(etag, format!("W/\"{}\"", user.version));
+
+        let req = test::TestRequest::get()
+            .uri("/auth/me")
+            .insert_header(bearer(&token))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get("ETag").unwrap().to_str().unwrap(), etag);
+
+        // If-Match is required by the config
+        let req = test::TestRequest::patch()
+            .uri(&format!("/users/{}", user.id))
+            .insert_header(bearer(&token))
+            .set_json(json!({ "name": "First" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 428);
+
+        let req = test::TestRequest::patch()
+            .uri(&format!("/users/{}", user.id))
+            .insert_header(bearer(&token))
+            .insert_header(("If-Match", etag.as_str()))
+            .set_json(json!({ "name": "First" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let new_etag = resp
+            .headers()
+            .get("ETag")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert_ne!(new_etag, etag);
+
+        // A second writer holding the old ETag is rejected
+        let req = test::TestRequest::put()
+            .uri(&format!("/users/{}/role", user.id))
+            .insert_header(bearer(&admin_token))
+            .insert_header(("If-Match", etag.as_str()))
+            .set_json(json!({ "role": "moderator" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 412);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/users/{}", user.id))
+            .insert_header(bearer(&admin_token))
+            .insert_header(("If-Match", etag.as_str()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 412);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/users/{}", user.id))
+            .insert_header(bearer(&admin_token))
+            .insert_header(("If-Match", "*"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 204);
+    }
+
+    #[actix_web::test]
+    async fn test_compare_and_swap_updates() {
+        let pool = setup().await;
+        let (user, _) = create_user(&pool, UserRole::User).await;
+        let repo = UserRepository { pool: &pool };
+
+        let updated = repo
+            .update_user_if_version(user.id, user.version, Some("Swapped"), None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.version, user.version + 1);
+
+        // The stale version no longer matches
+        assert!(repo
+            .update_user_if_version(user.id, user.version, Some("Lost"), None)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(repo
+            .update_user_role_if_version(user.id, user.version, &UserRole::Admin)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(!repo
+            .delete_user_if_version(user.id, user.version)
+            .await
+            .unwrap());
+        assert!(repo
+            .delete_user_if_version(user.id, updated.version)
+            .await
+            .unwrap());
+    }
+
+    #[actix_web::test]
+    async fn test_conditional_get() {
+        let pool = setup().await;
+        let (user, token) = create_user(&pool, UserRole::User).await;
+        let app = app!(pool);
+
+        let req = test::TestRequest::get()
+            .uri("/auth/me")
+            .insert_header(bearer(&token))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let etag = resp.headers().get("ETag").unwrap().clone();
+        let last_modified = resp.headers().get("Last-Modified").unwrap().clone();
+        assert_eq!(
+            resp.headers().get("Cache-Control").unwrap(),
+            "private, max-age=0"
+        );
+        assert!(resp
+            .headers()
+            .get("Vary")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .contains("Authorization"));
+
+        let req = test::TestRequest::get()
+            .uri("/auth/me")
+            .insert_header(bearer(&token))
+            .insert_header(("If-None-Match", etag.clone()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 304);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/users/{}", user.id))
+            .insert_header(bearer(&token))
+            .insert_header(("If-Modified-Since", last_modified))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 304);
+
+        // Lists get a weak ETag from their content
+        let filter = format!("email%20eq%20%27{}%27", user.email);
+        let req = test::TestRequest::get()
+            .uri(&format!("/users?filter={}", filter))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let list_etag = resp.headers().get("ETag").unwrap().clone();
+        assert!(list_etag.to_str().unwrap().starts_with("W/"));
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/users?filter={}", filter))
+            .insert_header(("If-None-Match", list_etag.clone()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 304);
+
+        // Changing the user changes both ETags
+        UserRepository { pool: &pool }
+            .update_user(user.id, Some("Renamed"), None)
+            .await
+            .unwrap();
+        let req = test::TestRequest::get()
+            .uri("/auth/me")
+            .insert_header(bearer(&token))
+            .insert_header(("If-None-Match", etag))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+        let req = test::TestRequest::get()
+            .uri(&format!("/users?filter={}", filter))
+            .insert_header(("If-None-Match", list_etag))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+    }
+
+    #[actix_web::test]
+    async fn test_bulk_operations() {
+        let pool = setup().await;
+        let (admin, admin_token) = create_user(&pool, UserRole::Admin).await;
+        let (user, token) = create_user(&pool, UserRole::User).await;
+        let (other, _) = create_user(&pool, UserRole::User).await;
+        let app = app!(pool);
+        let repo = UserRepository { pool: &pool };
+        let new_email = format!("bulk_{}@example.com", Uuid::new_v4());
+
+        let operations = json!([
+            { "op": "create", "name": "Bulk User", "email": new_email, "password": "password123" },
+            { "op": "update", "id": user.id, "version": user.version, "name": "Bulk Renamed" },
+            { "op": "change_role", "id": other.id, "role": "moderator" },
+            { "op": "delete", "id": Uuid::new_v4() },
+        ]);
+
+        // Only admins may run bulk operations
+        let req = test::TestRequest::post()
+            .uri("/users/bulk")
+            .insert_header(bearer(&token))
+            .set_json(json!({ "operations": operations }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+        // Atomic mode rolls everything back on the failed delete
+        let req = test::TestRequest::post()
+            .uri("/users/bulk")
+            .insert_header(bearer(&admin_token))
+            .set_json(json!({ "operations": operations }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+        let body: Value = test::read_body_json(resp).await;
+        let statuses: Vec<u64> = body["data"]["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["status"].as_u64().unwrap())
+            .collect();
+        assert_eq!(statuses, vec![424, 424, 424, 404]);
+        assert_eq!(body["data"]["committed"], false);
+        assert_eq!(body["data"]["results"][3]["error"]["error"], "not_found");
+        assert!(!repo.email_exists(&new_email).await.unwrap());
+        assert_eq!(repo.find_by_id(user.id).await.unwrap().name, user.name);
+
+        // Best-effort mode keeps the successful operations
+        let req = test::TestRequest::post()
+            .uri("/users/bulk")
+            .insert_header(bearer(&admin_token))
+            .set_json(json!({ "mode": "best_effort", "operations": operations }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 207);
+        let body: Value = test::read_body_json(resp).await;
+        let statuses: Vec<u64> = body["data"]["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["status"].as_u64().unwrap())
+            .collect();
+        assert_eq!(statuses, vec![201, 200, 200, 404]);
+        assert_eq!(body["data"]["succeeded"], 3);
+        assert_eq!(body["data"]["failed"], 1);
+        assert!(repo.email_exists(&new_email).await.unwrap());
+        assert_eq!(repo.find_by_id(user.id).await.unwrap().name, "Bulk Renamed");
+        assert_eq!(repo.find_by_id(other.id).await.unwrap().role, "moderator");
+
+        // Per-item validation, conflicts and stale versions
+        let req = test::TestRequest::post()
+            .uri("/users/bulk")
+            .insert_header(bearer(&admin_token))
+            .set_json(json!({ "mode": "best_effort", "operations": [
+                { "op": "create", "name": "Dup", "email": new_email, "password": "password123" },
+                { "op": "create", "name": "", "email": "invalid", "password": "short" },
+                { "op": "update", "id": user.id, "version": user.version, "name": "Stale" },
+                { "op": "change_role", "id": admin.id, "role": "user" },
+                { "op": "delete", "id": other.id },
+            ]}))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        let statuses: Vec<u64> = body["data"]["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["status"].as_u64().unwrap())
+            .collect();
+        assert_eq!(statuses, vec![409, 400, 412, 400, 204]);
+        assert!(repo.find_by_id(other.id).await.is_err());
+
+        let too_many: Vec<Value> = (0..101)
+            .map(|_| json!({ "op": "delete", "id": Uuid::new_v4() }))
+            .collect();
+        for operations in [json!([]), json!(too_many)] {
+            let req = test::TestRequest::post()
+                .uri("/users/bulk")
+                .insert_header(bearer(&admin_token))
+                .set_json(json!({ "operations": operations }))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), 400);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_import_users() {
+        let pool = setup().await;
+        let (existing, admin_token) = create_user(&pool, UserRole::Admin).await;
+        let (_, token) = create_user(&pool, UserRole::User).await;
+        let app = app!(pool);
+        let repo = UserRepository { pool: &pool };
+        let first = format!("import_{}@example.com", Uuid::new_v4());
+        let second = format!("import_{}@example.com", Uuid::new_v4());
+
+        let csv = format!(
+            "full_name,mail,password,role\n\
+             Import One,{first},password123,\n\
+             Import Two,{second},password123,moderator\n\
+             Import Dup,{first},password123,\n\
+             Import Taken,{},password123,\n\
+             Import Bad,not-an-email,short,owner\n",
+            existing.email
+        );
+        let import = |query: &str, token: &str| {
+            test::TestRequest::post()
+                .uri(&format!(
+                    "/users/import?columns=full_name:name,mail:email{}",
+                    query
+                ))
+                .insert_header(bearer(token))
+                .insert_header(("Content-Type", "text/csv"))
+                .set_payload(csv.clone())
+                .to_request()
+        };
+
+        assert_eq!(
+            test::call_service(&app, import("", &token)).await.status(),
+            403
+        );
+
+        // Dry run reports every problem by line
+        let resp = test::call_service(&app, import("&dry_run=true", &admin_token)).await;
+        assert_eq!(resp.status(), 200);
+        let body: Value = test::read_body_json(resp).await;
+        let report = &body["data"];
+        assert_eq!(report["format"], "csv");
+        assert_eq!(report["total"], 5);
+        assert_eq!(report["valid"], 2);
+        assert_eq!(report["invalid"], 3);
+        let lines: Vec<u64> = report["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["line"].as_u64().unwrap())
+            .collect();
+        assert_eq!(lines, vec![4, 5, 6]);
+        assert_eq!(
+            report["errors"][0]["errors"][0],
+            "email: Duplicate email in import"
+        );
+        assert_eq!(
+            report["errors"][1]["errors"][0],
+            "email: Email already exists"
+        );
+        assert_eq!(report["errors"][2]["errors"].as_array().unwrap().len(), 3);
+        assert!(!repo.email_exists(&first).await.unwrap());
+
+        // Invalid rows reject the whole import
+        let resp = test::call_service(&app, import("", &admin_token)).await;
+        assert_eq!(resp.status(), 422);
+        assert!(!repo.email_exists(&first).await.unwrap());
+
+        let resp = test::call_service(&app, import("&skip_invalid=true", &admin_token)).await;
+        assert_eq!(resp.status(), 200);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["data"]["inserted"], 2);
+        assert_eq!(body["data"]["failed"], 0);
+        let imported = repo.find_by_email(&second).await.unwrap();
+        assert_eq!(imported.name, "Import Two");
+        assert_eq!(imported.role, "moderator");
+
+        // JSONL is detected from the content type
+        let third = format!("import_{}@example.com", Uuid::new_v4());
+        let req = test::TestRequest::post()
+            .uri("/users/import")
+            .insert_header(bearer(&admin_token))
+            .insert_header(("Content-Type", "application/x-ndjson"))
+            .set_payload(format!(
+                "{}\n",
+                json!({ "name": "Import Three", "email": third, "password": "password123" })
+            ))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["data"]["format"], "jsonl");
+        assert_eq!(body["data"]["inserted"], 1);
+        assert!(repo.email_exists(&third).await.unwrap());
+
+        let req = test::TestRequest::post()
+            .uri("/users/import?format=xlsx")
+            .insert_header(bearer(&admin_token))
+            .set_payload("")
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn test_export_users() {
+        let pool = setup().await;
+        let (_, admin_token) = create_user(&pool, UserRole::Admin).await;
+        let (_, token) = create_user(&pool, UserRole::User).await;
+        let app = app!(pool);
+        let repo = UserRepository { pool: &pool };
+        let prefix = format!("export_{}", Uuid::new_v4().simple());
+        let mut ids = Vec::new();
+        for name in ["Export B", "Export A", "Export, C"] {
+            let email = format!("{}_{}@example.com", prefix, ids.len());
+            ids.push(repo.create_user(name, &email).await.unwrap().id);
+        }
+        repo.delete_user(ids[2]).await.unwrap();
+        let filter = format!("email%20sw%20%27{}%27", prefix);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/users/export?filter={}", filter))
+            .insert_header(bearer(&token))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/users/export?filter={}&sort=name&columns=name,id",
+                filter
+            ))
+            .insert_header(bearer(&admin_token))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(
+            resp.headers().get("Content-Type").unwrap(),
+            "text/csv; charset=utf-8"
+        );
+        assert_eq!(
+            resp.headers().get("Content-Disposition").unwrap(),
+            "attachment; filename=\"users.csv\""
+        );
+        let body = test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body).unwrap(),
+            format!("name,id\nExport A,{}\nExport B,{}\n", ids[1], ids[0])
+        );
+
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/users/export?format=ndjson&include_deleted=true&columns=name,deleted_at&filter={}",
+                filter
+            ))
+            .insert_header(bearer(&admin_token))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get("Content-Type").unwrap(),
+            "application/x-ndjson"
+        );
+        let body = test::read_body(resp).await;
+        let rows: Vec<Value> = std::str::from_utf8(&body)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0], json!({ "name": "Export B", "deleted_at": null }));
+        assert_eq!(rows[2]["name"], "Export, C");
+        assert!(rows[2]["deleted_at"].is_string());
+
+        for query in ["format=xml", "columns=password_hash", "filter=nope"] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/users/export?{}", query))
+                .insert_header(bearer(&admin_token))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), 400);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_sparse_fieldsets() {
+        let pool = setup().await;
+        let (user, token) = create_user(&pool, UserRole::Moderator).await;
+        let app = app!(pool);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/users/{}?fields=name,id", user.id))
+            .insert_header(bearer(&token))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert!(resp
+            .headers()
+            .get("ETag")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("W/"));
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["data"], json!({ "id": user.id, "name": user.name }));
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/users/{}?fields=id&expand=role", user.id))
+            .insert_header(bearer(&token))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["data"]["role"]["name"], "moderator");
+        assert_eq!(
+            body["data"]["role"]["description"],
+            "Moderator with elevated permissions"
+        );
+
+        let filter = format!("id%20eq%20%27{}%27", user.id);
+        let req = test::TestRequest::get()
+            .uri(&format!("/users?filter={}&fields=email", filter))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["data"]["items"], json!([{ "email": user.email }]));
+        assert_eq!(body["data"]["total"], 1);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/users?filter={}&expand=role", filter))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["data"]["items"][0]["name"], user.name);
+        assert_eq!(body["data"]["items"][0]["role"]["name"], "moderator");
+
+        for query in ["fields=id,password_hash", "expand=email", "fields=rank"] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/users?{}", query))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 400);
+            let body: Value = test::read_body_json(resp).await;
+            assert!(body["message"].as_str().unwrap().starts_with("Unknown"));
+        }
+    }
+}
@@ -1,6 +1,33 @@
 #[cfg(test)]
 mod tests {
-    use afaf_rest_rust::core::rest::handler::validator::is_valid_email;
+    use afaf_rest_rust::core::{
+        domain::error::AppError,
+        rest::handler::{response::ErrorResponse, validator::is_valid_email},
+    };
+    use serde_json::json;
+    use validator::Validate;
+
+    #[derive(Validate)]
+    struct Address {
+        #[validate(length(min = 2, message = "City is too short"))]
+        city: String,
+    }
+
+    #[derive(Validate)]
+    struct Contact {
+        #[validate(email)]
+        email: String,
+    }
+
+    #[derive(Validate)]
+    struct Signup {
+        #[validate(length(min = 8, message = "Password is too short"))]
+        password: String,
+        #[validate(nested)]
+        address: Address,
+        #[validate(nested)]
+        contacts: Vec<Contact>,
+    }
 
     #[test]
     fn test_valid_emails() {
@@ -20,4 +47,56 @@ mod tests {
         assert!(!is_valid_email("user space@example.com"));
         assert!(!is_valid_email("user@example..com"));
     }
+
+    #[test]
+    fn test_field_errors() {
+        let signup = Signup {
+            password: "secret".to_string(),
+            address: Address {
+                city: "X".to_string(),
+            },
+            contacts: vec![
+                Contact {
+                    email: "ada@example.com".to_string(),
+                },
+                Contact {
+                    email: "not-an-email".to_string(),
+                },
+            ],
+        };
+
+        let error = AppError::from(signup.validate().unwrap_err());
+        let (status, body) = ErrorResponse::from_error(&error);
+        assert_eq!(status, 400);
+        assert_eq!(
+            body.message,
+            "address.city: City is too short; contacts[1].email: Invalid contacts[1].email; \
+             password: Password is too short"
+        );
+
+        let errors = serde_json::to_value(&body.errors).unwrap();
+        assert_eq!(
+            errors,
+            json!([
+                {
+                    "field": "address.city",
+                    "code": "length",
+                    "message": "City is too short",
+                    "params": { "min": 2 }
+                },
+                {
+                    "field": "contacts[1].email",
+                    "code": "email",
+                    "message": "Invalid contacts[1].email",
+                    "params": {}
+                },
+                {
+                    "field": "password",
+                    "code": "length",
+                    "message": "Password is too short",
+                    "params": { "min": 8 }
+                }
+            ])
+        );
+    }
 }
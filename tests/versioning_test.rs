@@ -0,0 +1,186 @@
+#[macro_use]
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{header_value, setup};
+    use actix_web::{test, web};
+    use afaf_rest_rust::{
+        config::ApiVersionConfig,
+        core::rest::{openapi::openapi, router, version::ApiVersion},
+    };
+    use chrono::NaiveDate;
+    use serde_json::{json, Value};
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    fn routes(pool: &PgPool) -> impl FnOnce(&mut web::ServiceConfig) {
+        let pool = pool.clone();
+        move |cfg| {
+            cfg.app_data(web::Data::new(pool))
+                .app_data(web::Data::new(ApiVersionConfig {
+                    v1_sunset: NaiveDate::from_ymd_opt(2027, 4, 30),
+                }))
+                .configure(router::config);
+        }
+    }
+
+    fn new_user() -> Value {
+        json!({
+            "name": "Versioned User",
+            "email": format!("versioned_{}@example.com", Uuid::new_v4()),
+        })
+    }
+
+    #[actix_web::test]
+    async fn test_deprecated_v1_route() {
+        let pool = setup().await;
+        let app = app!(routes(&pool));
+
+        let req = test::TestRequest::post()
+            .uri("/api/v1/users")
+            .set_json(new_user())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        assert_eq!(header_value(&resp, "api-version"), "1");
+        assert_eq!(header_value(&resp, "deprecation"), "@1792281600");
+        assert_eq!(
+            header_value(&resp, "sunset"),
+            "Fri, 30 Apr 2027 00:00:00 GMT"
+        );
+        assert_eq!(
+            header_value(&resp, "link"),
+            "</api/v2/users>; rel=\"successor-version\""
+        );
+        assert!(header_value(&resp, "location").is_empty());
+
+        // Routes v2 keeps are not deprecated
+        let req = test::TestRequest::get().uri("/api/v1/users").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert!(header_value(&resp, "deprecation").is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_v2_route() {
+        let pool = setup().await;
+        let app = app!(routes(&pool));
+
+        let req = test::TestRequest::post()
+            .uri("/api/v2/users")
+            .set_json(new_user())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        assert_eq!(header_value(&resp, "api-version"), "2");
+        assert!(header_value(&resp, "deprecation").is_empty());
+        assert_eq!(header_value(&resp, "etag"), "W/\"1\"");
+        let location = header_value(&resp, "location");
+        let body: Value = test::read_body_json(resp).await;
+        let id = body["data"]["id"].as_str().unwrap();
+        assert_eq!(location, format!("/api/v2/users/{}", id));
+
+        // v1 routes that v2 keeps are served under its prefix
+        let req = test::TestRequest::get()
+            .uri("/api/v2/users?limit=1")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(header_value(&resp, "api-version"), "2");
+    }
+
+    #[actix_web::test]
+    async fn test_header_selection() {
+        let pool = setup().await;
+        let app = app!(routes(&pool));
+
+        // Unversioned paths serve the default version
+        let req = test::TestRequest::post()
+            .uri("/users")
+            .set_json(new_user())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        assert_eq!(header_value(&resp, "api-version"), "1");
+        assert_eq!(
+            header_value(&resp, "link"),
+            "</api/v2/users>; rel=\"successor-version\""
+        );
+
+        // Or the one named in Api-Version
+        let req = test::TestRequest::post()
+            .uri("/users")
+            .insert_header(("Api-Version", "2"))
+            .set_json(new_user())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        assert_eq!(header_value(&resp, "api-version"), "2");
+        assert!(header_value(&resp, "location").starts_with("/users/"));
+
+        // Unknown versions are rejected by the middleware, so the error is
+        // rendered by the error handlers
+        let req = test::TestRequest::get()
+            .uri("/users")
+            .insert_header(("Api-Version", "9"))
+            .to_request();
+        let err = test::try_call_service(&app, req).await.unwrap_err();
+        assert_eq!(err.error_response().status(), 400);
+
+        // Unversioned routes are unaffected
+        let req = test::TestRequest::get()
+            .uri("/scim/v2/ServiceProviderConfig")
+            .insert_header(("Api-Version", "2"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_ne!(resp.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn test_openapi_per_version() {
+        let v1 = serde_json::to_value(openapi(ApiVersion::V1)).unwrap();
+        let v2 = serde_json::to_value(openapi(ApiVersion::V2)).unwrap();
+
+        assert_eq!(v1["info"]["version"], "1.0.0");
+        assert_eq!(v1["paths"]["/api/v1/users"]["post"]["deprecated"], true);
+        assert_eq!(
+            v1["paths"]["/api/v1/users"]["get"]["deprecated"],
+            Value::Null
+        );
+        assert!(v1["paths"]["/users"].is_null());
+
+        assert_eq!(v2["info"]["version"], "2.0.0");
+        assert_eq!(
+            v2["paths"]["/api/v2/users"]["post"]["deprecated"],
+            Value::Null
+        );
+        assert_eq!(
+            v2["paths"]["/api/v2/users"]["post"]["responses"]["201"]["content"]["application/json"]
+                ["schema"]["$ref"],
+            "#/components/schemas/UserResponse"
+        );
+        assert!(v2["paths"]["/api/v2/users"]["get"].is_object());
+        assert!(v2["paths"]["/api/v2/auth/me"].is_object());
+        assert!(v2["paths"]["/scim/v2/Users"].is_object());
+    }
+
+    #[actix_web::test]
+    async fn test_openapi_documents_are_served() {
+        let pool = setup().await;
+        let app = app!(routes(&pool));
+
+        for (uri, version) in [
+            ("/api-docs/v1/openapi.json", "1.0.0"),
+            ("/api-docs/v2/openapi.json", "2.0.0"),
+            // The default version's document stays at its former URL
+            ("/api-docs/openapi.json", "1.0.0"),
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 200, "{uri}");
+            let body: Value = test::read_body_json(resp).await;
+            assert_eq!(body["info"]["version"], version, "{uri}");
+        }
+    }
+}